@@ -0,0 +1,188 @@
+use common::metrics::{LatencyHistograms, Metric, RunStats, Stage};
+use common::trace::TraceLatency;
+use common::config::{ActuatorsConfig, EStopConfig};
+use common::estop::EStopReaction;
+use common::status::StatusTransition;
+use common::{ActuatorStatus, ActuatorType, LoadProfile, LoopPerformance};
+use std::time::Duration;
+
+/// Prints compliance, jitter, timing and per-actuator figures for one run.
+pub fn analyze_results_detailed(stats: &RunStats, name: &str, sensor_period: Duration) {
+    let all = stats.totals(Stage::All);
+    if all.cycles == 0 {
+        println!("{}: No results to analyze", name);
+        return;
+    }
+    let us = |value_ns: f64| value_ns / 1000.0;
+
+    println!("\n=== {} Detailed Analysis ===", name);
+    println!("Total cycles: {}", all.cycles);
+    println!("Deadline compliance: {:.2}% ({} missed)", all.compliance_percent(), all.missed_deadlines);
+
+    let jitter = stats.jitter();
+    if jitter.count > 0 {
+        println!("Release jitter (μs): mean={:.2}, std={:.2}, p99={:.2}, max={:.2}",
+            jitter.mean_us, jitter.std_dev_us, jitter.p99_us, jitter.max_us);
+        println!("Period drift: {:+.1} ns/cycle ({:+.1} ppm of {} ms)",
+            jitter.drift_ns_per_cycle, jitter.drift_ppm(sensor_period), sensor_period.as_millis());
+    }
+
+    println!("Processing time (μs): avg={:.2}, min={:.2}, max={:.2}",
+        us(all.avg_processing_ns()), us(all.min_processing_ns as f64), us(all.max_processing_ns as f64));
+    if all.latency_count > 0 {
+        println!("Total latency (μs): avg={:.2}, min={:.2}, max={:.2}",
+            us(all.avg_latency_ns()), us(all.min_latency_ns as f64), us(all.max_latency_ns as f64));
+    }
+    println!("Lock wait (μs): avg={:.2}, max={:.2}", us(all.avg_lock_wait_ns()), us(all.max_lock_wait_ns as f64));
+    println!("Max lateness: {} ns", all.max_lateness_ns);
+    println!("Cycles with lateness: {} ({:.2}%)", all.late_cycles, (all.late_cycles as f64 / all.cycles as f64) * 100.0);
+
+    let actuators: Vec<_> = ActuatorType::ALL
+        .into_iter()
+        .map(|actuator| (actuator, stats.totals(Stage::Actuator(actuator))))
+        .filter(|(_, totals)| totals.cycles > 0)
+        .collect();
+    if !actuators.is_empty() {
+        println!("\nActuator Performance:");
+        for (actuator, totals) in actuators {
+            println!("  {:?}: {} cycles, {:.2}% deadline met, avg {:.2} μs",
+                actuator, totals.cycles, totals.compliance_percent(), us(totals.avg_processing_ns()));
+        }
+    }
+
+    print_tail_percentiles(stats.histograms());
+}
+
+/// Percentile table per stage and metric, in μs.
+pub fn print_tail_percentiles(histograms: &LatencyHistograms) {
+    println!("\nTail percentiles (μs):");
+    println!("  {:<11} {:<16} {:>9} {:>9} {:>9} {:>9} {:>9} {:>9}",
+        "stage", "metric", "p50", "p90", "p99", "p99.9", "p99.99", "max");
+    for stage in Stage::ALL {
+        for metric in Metric::ALL {
+            let histogram = histograms.get(stage, metric);
+            if histogram.is_empty() {
+                continue;
+            }
+            let us = |value: u64| value as f64 / 1000.0;
+            println!("  {:<11} {:<16} {:>9.2} {:>9.2} {:>9.2} {:>9.2} {:>9.2} {:>9.2}",
+                stage.to_string(), format!("{:?}", metric),
+                us(histogram.value_at_percentile(50.0)), us(histogram.value_at_percentile(90.0)),
+                us(histogram.value_at_percentile(99.0)), us(histogram.value_at_percentile(99.9)),
+                us(histogram.value_at_percentile(99.99)), us(histogram.max()));
+        }
+    }
+}
+
+/// End-to-end latency per actuator from the sample traces, in μs.
+pub fn print_trace_latencies(latencies: &[TraceLatency]) {
+    if latencies.is_empty() {
+        println!("\nEnd-to-end traces: none completed");
+        return;
+    }
+    println!("\nEnd-to-end latency per actuator (μs):");
+    println!("  {:<11} {:>8} {:>12} {:>12} {:>12} {:>12} {:>12} {:>12}",
+        "actuator", "traces", "actuate p50", "actuate p99", "actuate max", "rtt p50", "rtt p99", "rtt max");
+    let us = |value: u64| value as f64 / 1000.0;
+    for row in latencies {
+        println!("  {:<11} {:>8} {:>12.2} {:>12.2} {:>12.2} {:>12.2} {:>12.2} {:>12.2}",
+            format!("{:?}", row.actuator), row.traces,
+            us(row.actuation_p50_ns), us(row.actuation_p99_ns), us(row.actuation_max_ns),
+            us(row.round_trip_p50_ns), us(row.round_trip_p99_ns), us(row.round_trip_max_ns));
+    }
+}
+
+/// Closed-loop response of each plant under `[plants]`; prints nothing when the loops are open.
+pub fn print_loop_performance(performance: &[LoopPerformance]) {
+    if performance.is_empty() {
+        return;
+    }
+    println!("\nClosed-loop performance:");
+    println!("  {:<11} {:<19} {:>8} {:>10} {:>10} {:>10} {:>11} {:>12} {:>10} {:>10} {:>7}",
+        "actuator", "plant", "samples", "initial", "final", "setpoint", "overshoot%", "settling ms", "iae", "max |u|", "stable");
+    for row in performance {
+        let settling = row.settling_time_ms.map_or("-".to_string(), |ms| format!("{:.1}", ms));
+        println!("  {:<11} {:<19} {:>8} {:>10.3} {:>10.3} {:>10.3} {:>11.1} {:>12} {:>10.4} {:>10.3} {:>7}",
+            format!("{:?}", row.actuator), row.model, row.samples, row.initial, row.final_output,
+            row.final_setpoint, row.overshoot_percent, settling, row.iae, row.max_abs_input, if row.stable { "yes" } else { "NO" });
+    }
+}
+
+/// Per-cycle compute cost of each actuator's controller against its deadline, in μs.
+pub fn print_controller_costs(histograms: &LatencyHistograms, actuators: &ActuatorsConfig) {
+    if histograms.get(Stage::All, Metric::ControlCost).is_empty() {
+        return;
+    }
+    println!("\nController cost per cycle (μs):");
+    println!("  {:<11} {:<15} {:>8} {:>9} {:>9} {:>9} {:>10} {:>12}",
+        "actuator", "controller", "cycles", "p50", "p99", "max", "deadline", "p99 of dl%");
+    let us = |value: u64| value as f64 / 1000.0;
+    for actuator in ActuatorType::ALL {
+        let histogram = histograms.get(Stage::Actuator(actuator), Metric::ControlCost);
+        if histogram.is_empty() {
+            continue;
+        }
+        let settings = actuators.get(actuator);
+        let p99_ns = histogram.value_at_percentile(99.0);
+        println!("  {:<11} {:<15} {:>8} {:>9.2} {:>9.2} {:>9.2} {:>10} {:>12.2}",
+            format!("{:?}", actuator), settings.controller.kind(), histogram.len(),
+            us(histogram.value_at_percentile(50.0)), us(p99_ns), us(histogram.max()), settings.deadline_us,
+            p99_ns as f64 / (settings.deadline_us as f64 * 1000.0) * 100.0);
+    }
+}
+
+/// Status machine activity per actuator; prints nothing when no actuator ran.
+pub fn print_status_transitions(transitions: &[StatusTransition]) {
+    if transitions.is_empty() {
+        return;
+    }
+    println!("\nActuator status:");
+    println!("  {:<11} {:>11} {:>11} {:>10} {:>9} {:>11}  final",
+        "actuator", "transitions", "emergencies", "safe stops", "degraded", "recoveries");
+    for actuator in ActuatorType::ALL {
+        let own: Vec<_> = transitions.iter().filter(|transition| transition.actuator == actuator).collect();
+        let Some(last) = own.last() else { continue };
+        let entered = |status: ActuatorStatus| own.iter().filter(|transition| transition.to == status).count();
+        println!("  {:<11} {:>11} {:>11} {:>10} {:>9} {:>11}  {:?}",
+            format!("{:?}", actuator), own.len(), entered(ActuatorStatus::Emergency), entered(ActuatorStatus::SafeStop),
+            entered(ActuatorStatus::Degraded), entered(ActuatorStatus::Recovering), last.to);
+    }
+}
+
+/// E-stop reaction time per actuator against the reaction deadline; prints nothing
+/// when no stop was triggered.
+pub fn print_estop_reactions(reactions: &[EStopReaction], estop: &EStopConfig) {
+    if reactions.is_empty() {
+        return;
+    }
+    let mut histograms = LatencyHistograms::new();
+    for reaction in reactions {
+        histograms.record_estop_reaction(reaction);
+    }
+    println!("\nE-stop reaction (μs):");
+    println!("  {:<11} {:>9} {:>9} {:>9} {:>9} {:>10} {:>7}",
+        "actuator", "reactions", "p50", "p99", "max", "deadline", "missed");
+    let us = |value: u64| value as f64 / 1000.0;
+    for actuator in ActuatorType::ALL {
+        let histogram = histograms.get(Stage::Actuator(actuator), Metric::EStopReaction);
+        if histogram.is_empty() {
+            continue;
+        }
+        let missed = reactions.iter().filter(|reaction| reaction.actuator == actuator && !reaction.deadline_met).count();
+        println!("  {:<11} {:>9} {:>9.2} {:>9.2} {:>9.2} {:>10} {:>7}",
+            format!("{:?}", actuator), histogram.len(), us(histogram.value_at_percentile(50.0)),
+            us(histogram.value_at_percentile(99.0)), us(histogram.max()), estop.reaction_deadline_us, missed);
+    }
+}
+
+pub fn print_load_profile(profile: Option<LoadProfile>) {
+    match profile {
+        Some(profile) if profile.threads == 0 => println!("\nBackground load: off"),
+        Some(profile) => {
+            println!("\nBackground load: {} threads {:?}, {} KiB cache buffer, {}% duty cycle",
+                profile.threads, profile.workers, profile.cache_buffer_kb, profile.duty_cycle_percent);
+            println!("  Work completed: {} units over {} ms", profile.total_iterations(), profile.active_ms);
+        }
+        None => println!("\nBackground load: none"),
+    }
+}
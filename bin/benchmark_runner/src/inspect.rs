@@ -0,0 +1,120 @@
+//! Summaries and dumps of run captures written under `[capture]`.
+
+use std::collections::HashMap;
+use std::error::Error;
+
+use common::capture::{CaptureEntry, CaptureReader, CaptureRecord};
+use common::{ActuatorStatus, ActuatorType};
+
+#[derive(Default)]
+struct Counts {
+    total: u64,
+    unsent: u64,
+}
+
+impl Counts {
+    fn add(&mut self, sent: bool) {
+        self.total += 1;
+        if !sent {
+            self.unsent += 1;
+        }
+    }
+}
+
+#[derive(Default)]
+struct ControlStats {
+    outputs: u64,
+    abs_error_sum: f64,
+    max_abs_output: f64,
+    emergencies: u64,
+    safe_stops: u64,
+}
+
+/// Prints what the capture at `path` holds: the run it came from and how many of each
+/// record it has. With `dump`, every entry is printed too, one per line.
+pub fn inspect_capture(path: &str, dump: bool) -> Result<(), Box<dyn Error>> {
+    let reader = CaptureReader::open(path)?;
+    let header = reader.header().clone();
+    let config = header.config()?;
+
+    println!("=== Capture {} ===", path);
+    println!("Runtime: {} (capture format v{})", header.runtime, header.version);
+    println!("Experiment: {} ({} mode), {}s at {}ms sensor period",
+             config.experiment_name, config.mode, config.duration_secs, config.sensor_period_ms);
+
+    let mut sensor = Counts::default();
+    let mut dispatched = Counts::default();
+    let mut feedback = Counts::default();
+    let mut controls: HashMap<ActuatorType, ControlStats> = HashMap::new();
+    let mut last_ns = 0;
+    let mut records = 0u64;
+    let mut read_error = None;
+
+    for entry in reader {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                read_error = Some(err);
+                break;
+            }
+        };
+        records += 1;
+        last_ns = last_ns.max(entry.at_ns);
+        if dump {
+            print_entry(&entry);
+        }
+        match entry.record {
+            CaptureRecord::Sensor { sent, .. } => sensor.add(sent),
+            CaptureRecord::Dispatched { sent, .. } => dispatched.add(sent),
+            CaptureRecord::Control { actuator, error, output, status, .. } => {
+                let stats = controls.entry(actuator).or_default();
+                stats.outputs += 1;
+                stats.abs_error_sum += error.abs();
+                stats.max_abs_output = stats.max_abs_output.max(output.abs());
+                match status {
+                    ActuatorStatus::Emergency => stats.emergencies += 1,
+                    ActuatorStatus::SafeStop => stats.safe_stops += 1,
+                    _ => {}
+                }
+            }
+            CaptureRecord::Feedback { sent, .. } => feedback.add(sent),
+        }
+    }
+
+    println!("Records: {} over {:.3}s", records, last_ns as f64 / 1e9);
+    println!("- Sensor samples: {} ({} not handed to the dispatcher)", sensor.total, sensor.unsent);
+    println!("- Dispatched: {} ({} dropped at a full actuator queue)", dispatched.total, dispatched.unsent);
+    for actuator in ActuatorType::ALL {
+        if let Some(stats) = controls.get(&actuator) {
+            println!("- {:?} control outputs: {}, mean |error| {:.3}, max |output| {:.3}, {} in emergency, {} in safe stop",
+                     actuator, stats.outputs, stats.abs_error_sum / stats.outputs as f64, stats.max_abs_output,
+                     stats.emergencies, stats.safe_stops);
+        }
+    }
+    println!("- Feedback: {} ({} dropped at a full feedback channel)", feedback.total, feedback.unsent);
+    if let Some(err) = read_error {
+        println!("[WARNING] Capture unreadable after record {}: {}", records, err);
+    }
+    Ok(())
+}
+
+fn print_entry(entry: &CaptureEntry) {
+    let at = format!("{:.6}s", entry.at_ns as f64 / 1e9);
+    match entry.record {
+        CaptureRecord::Sensor { data, raw_force, sent } => {
+            println!("[{:>8}] SENSOR #{} raw force {:.3}, filtered {:.3}, position {:.3}, temp {:.2}, sent {}",
+                     at, data.id, raw_force, data.force, data.position, data.temperature, sent);
+        }
+        CaptureRecord::Dispatched { actuator, data, sent } => {
+            println!("[{:>8}] DISPATCH #{} -> {:?}, sent {}", at, data.id, actuator, sent);
+        }
+        CaptureRecord::Control { actuator, sensor_id, error, output, status } => {
+            println!("[{:>8}] CONTROL #{} {:?} error {:.3}, output {:.3} ({:?})",
+                     at, sensor_id, actuator, error, output, status);
+        }
+        CaptureRecord::Feedback { feedback, sent } => {
+            println!("[{:>8}] FEEDBACK #{} {:?} {:?}, sent {}",
+                     at, feedback.sensor_id, feedback.actuator, feedback.status, sent);
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! Benchmark and analysis routines shared by the `benchmark_runner` binary and the root CLI.
+
+pub mod analysis;
+pub mod inspect;
+pub mod sweep;
+pub mod sync_bench;
+
+use analysis::{
+    analyze_results_detailed, print_controller_costs, print_load_profile, print_loop_performance, print_status_transitions,
+    print_estop_reactions,
+    print_trace_latencies,
+};
+use async_impl::AsyncRuntime;
+use common::config::ExperimentConfig;
+use common::sweep::Implementation;
+use common::Runtime;
+use criterion::{black_box, Criterion};
+use sim_impl::SimRuntime;
+use threaded_impl::ThreadedRuntime;
+use std::error::Error;
+use std::path::Path;
+use std::time::Duration;
+
+/// Default Criterion report location, relative to the project root.
+pub const CRITERION_REPORT_DIR: &str = "target/criterion";
+
+pub fn print_header(config_path: &str, config: &ExperimentConfig, use_criterion: bool) {
+    println!("========================================");
+    println!("Real-Time Sensor-Actuator Benchmark");
+    println!("========================================");
+    println!("Config: {}", config_path);
+    println!("Experiment: {}", config.experiment_name);
+    println!("Duration: {} seconds", config.duration_secs);
+    println!("Sensor period: {} ms", config.sensor_period_ms);
+    println!("Mode: {}", config.mode);
+    if config.is_contention_mode() {
+        println!("Shared resource: {:?} lock, {} ns critical section, {} accesses/cycle, high contention: {}",
+            config.shared_resource.lock, config.shared_resource.critical_section_ns,
+            config.shared_resource.accesses_per_cycle, config.shared_resource.high_contention);
+    }
+    println!("CPU load threads: {}", config.cpu_load_threads);
+    println!("Workload: {:?}, mean {} ns per stage", config.workload.distribution, config.processing_time_ns);
+    if use_criterion {
+        println!("Using Criterion for statistical analysis");
+        println!("Logging disabled for benchmark validity");
+    }
+    println!("========================================\n");
+}
+
+/// The [`Runtime`] behind an implementation. Every caller goes through this, so a new
+/// implementation only needs a variant here.
+pub fn create_runtime(implementation: Implementation) -> std::io::Result<Box<dyn Runtime>> {
+    Ok(match implementation {
+        Implementation::Threaded => Box::new(ThreadedRuntime::new()),
+        Implementation::Async => Box::new(AsyncRuntime::new()?),
+        Implementation::Sim => Box::new(SimRuntime::new()),
+    })
+}
+
+fn benchmark_runtime(c: &mut Criterion, config: &ExperimentConfig, implementation: Implementation) {
+    let config = config.clone();
+    let mut runtime = create_runtime(implementation).expect("failed to create runtime");
+
+    c.bench_function(&format!("{}_experiment", implementation), |b| {
+        b.iter(|| {
+            let recorder = runtime.run(black_box(config.clone())).expect("experiment failed");
+            black_box(recorder.get_results());
+        });
+    });
+}
+
+/// Criterion statistical benchmarks for `mode` (threaded, async, both or sync).
+/// Reports go to `report_dir`, or Criterion's usual `target/criterion` when `None`.
+pub fn run_criterion(config: &ExperimentConfig, mode: &str, report_dir: Option<&Path>) {
+    // Disable logging during Criterion benchmarks for methodological validity
+    let mut config = config.clone();
+    config.enable_logging = false;
+
+    let mut criterion = Criterion::default()
+        .sample_size(20)
+        .measurement_time(std::time::Duration::from_secs(30));
+    if let Some(dir) = report_dir {
+        criterion = criterion.output_directory(dir);
+    }
+    let report_dir = report_dir.map_or_else(|| CRITERION_REPORT_DIR.to_string(), |dir| dir.display().to_string());
+
+    if mode == "sync" {
+        println!("Running SYNCHRONIZATION STRATEGY statistical benchmarks...");
+        sync_bench::benchmark_sync_strategies(&mut criterion);
+
+        println!("\n========================================");
+        println!("Sync Strategy Benchmarking Complete!");
+        println!("========================================");
+        println!("HTML reports available in: {}/sync_strategies/", report_dir);
+        println!("This benchmark compares MutexStrategy vs RwLockStrategy vs AtomicStrategy");
+        println!("under realistic multi-threaded contention patterns.");
+        return;
+    }
+
+    if mode == "threaded" || mode == "both" {
+        println!("Running THREADED statistical benchmarks...");
+        benchmark_runtime(&mut criterion, &config, Implementation::Threaded);
+    }
+
+    if mode == "async" || mode == "both" {
+        println!("\nRunning ASYNC statistical benchmarks...");
+        benchmark_runtime(&mut criterion, &config, Implementation::Async);
+    }
+
+    println!("\n========================================");
+    println!("Criterion statistical analysis complete!");
+    println!("Check the {} directory for detailed HTML reports.", report_dir);
+    println!("========================================");
+}
+
+/// Normal (non-Criterion) runs for `mode` (threaded, async, sim or both), analysed and
+/// saved to `<impl>_results.csv`, with a Chrome trace of each run in `<impl>_trace.json`.
+pub fn run_experiments(config: &ExperimentConfig, mode: &str) -> Result<(), Box<dyn Error>> {
+    let implementations = match mode {
+        "both" => Implementation::ALL.to_vec(),
+        mode => vec![mode.parse::<Implementation>()?],
+    };
+    for (index, implementation) in implementations.into_iter().enumerate() {
+        let mut runtime = create_runtime(implementation)?;
+        let title = runtime.name().to_uppercase();
+        if index > 0 {
+            println!();
+        }
+        println!("Running {} experiment...", title);
+        let start = std::time::Instant::now();
+        let recorder = runtime.run(config.clone())?;
+        let elapsed = start.elapsed();
+
+        println!("{} experiment completed in {:.2} seconds", title, elapsed.as_secs_f64());
+
+        // Get results for analysis
+        let stats = recorder.stats();
+        analyze_results_detailed(&stats, &title, Duration::from_millis(config.sensor_period_ms));
+        print_trace_latencies(&recorder.trace_latencies());
+        print_loop_performance(&recorder.loop_performance());
+        print_controller_costs(stats.histograms(), &config.actuators);
+        print_status_transitions(&recorder.status_transitions());
+        print_estop_reactions(&recorder.estop_reactions(), &config.estop);
+        print_load_profile(recorder.load_profile());
+        if let Some(report) = recorder.shutdown_report() {
+            println!("Shutdown: {}", report);
+        }
+        if let Some(capture) = recorder.capture_summary() {
+            println!("Capture: {}", capture);
+        }
+
+        let results_path = format!("{}_results.csv", runtime.name());
+        recorder.save_to_csv(&results_path)?;
+        println!("Results saved to {}", results_path);
+        recorder.save_chrome_trace(&format!("{}_trace.json", runtime.name()), &format!("{}_impl", runtime.name()), config)?;
+    }
+
+    println!("\n========================================");
+    println!("Benchmark complete!");
+    println!("========================================");
+    Ok(())
+}
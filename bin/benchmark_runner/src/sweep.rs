@@ -0,0 +1,69 @@
+use common::metrics::{RunStats, RunSummary, Stage};
+use common::sweep::{SweepConfig, SweepPoint};
+use std::error::Error;
+use std::time::Instant;
+
+fn run_point(point: &SweepPoint) -> Result<RunStats, Box<dyn Error>> {
+    let recorder = crate::create_runtime(point.implementation)?.run(point.config.clone())?;
+    Ok(recorder.stats())
+}
+
+/// Expands the sweep, runs every point `repetitions` times and writes one tidy table
+/// with a row per (point, repetition, stage), keyed by the swept parameter values.
+pub fn run_sweep(sweep: &SweepConfig, sweep_path: &str) -> Result<(), Box<dyn Error>> {
+    let points = sweep.expand()?;
+    let total_runs = points.len() * sweep.repetitions as usize;
+    let estimated_secs: u64 = points.iter().map(|p| p.config.duration_secs).sum::<u64>() * sweep.repetitions as u64;
+
+    println!("========================================");
+    println!("Parameter Sweep");
+    println!("========================================");
+    println!("Sweep: {}", sweep_path);
+    println!("Base config: {}", sweep.base_config);
+    for (name, values) in &sweep.sweep {
+        println!("  {} = {}", name, values);
+    }
+    println!("Points: {} x {} repetitions = {} runs (~{} s)", points.len(), sweep.repetitions, total_runs, estimated_secs);
+    println!("Output: {}", sweep.output);
+    println!("========================================\n");
+
+    let mut writer = csv::Writer::from_path(&sweep.output)?;
+    let mut header: Vec<String> = points[0].params.iter().map(|(name, _)| name.clone()).collect();
+    header.extend(["repetition", "stage"].map(String::from));
+    header.extend(RunSummary::columns().map(String::from));
+    header.push("elapsed_secs".to_string());
+    writer.write_record(&header)?;
+
+    let mut run = 0;
+    for point in &points {
+        for repetition in 1..=sweep.repetitions {
+            run += 1;
+            let start = Instant::now();
+            let stats = run_point(point)?;
+            let elapsed = start.elapsed().as_secs_f64();
+
+            let overall = stats.summary(Stage::All);
+            println!("[{:>3}/{}] {} (rep {}/{}): {} cycles, {:.2}% deadline met, {:.2}s",
+                run, total_runs, point.label(), repetition, sweep.repetitions,
+                overall.cycles, overall.compliance_percent, elapsed);
+
+            // One row for the whole pipeline, then one per stage
+            for stage in Stage::ALL {
+                let summary = stats.summary(stage);
+                let mut row: Vec<String> = point.params.iter().map(|(_, value)| value.clone()).collect();
+                row.push(repetition.to_string());
+                row.push(stage.to_string());
+                row.extend(summary.values());
+                row.push(format!("{:.3}", elapsed));
+                writer.write_record(&row)?;
+            }
+            // Flush per run so an interrupted sweep keeps everything finished so far
+            writer.flush()?;
+        }
+    }
+
+    println!("\n========================================");
+    println!("Sweep complete! {} runs saved to {}", total_runs, sweep.output);
+    println!("========================================");
+    Ok(())
+}
@@ -1,150 +1,129 @@
-use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, Notify};
 use tokio::time::{Duration, Instant};
 
 use common::{
-    ActuatorFeedback, ActuatorStatus, ActuatorType,
-    BenchmarkRecorder, ExperimentConfig, SensorData,
-    DashboardBuffer, DashboardData, MetricsSnapshot,
+    ActuatorFeedback, ActuatorType, BenchmarkRecorder, ExperimentConfig, SensorData,
+    DashboardBuffer, DashboardData, MetricsSnapshot, PlantLoop, SharedClock, SharedDiagnostics, Workload,
 };
-use common::metrics::CycleResult;
-use common::pid::PidController;
-
-const FEEDBACK_DEADLINE_NS: u64 = 500_000; // 0.5 ms in nanoseconds
+use common::estop::EStopHandle;
+use common::pipeline::ActuatorStage;
+use common::shared_resource::SharedResourceClient;
 
+#[allow(clippy::too_many_arguments)]
 pub async fn run_actuator_task(
     actuator_type: ActuatorType,
-    deadline: Duration,
     config: ExperimentConfig,
     mut receiver: mpsc::Receiver<SensorData>,
     feedback_tx: mpsc::Sender<ActuatorFeedback>,
     recorder: Arc<BenchmarkRecorder>,
-    shutdown: Arc<AtomicBool>,
-    start_time: Instant,
+    diagnostics: Arc<SharedDiagnostics>,
+    estop: EStopHandle,
+    estop_wake: Arc<Notify>, // Notified on every stop, so an idle actuator reacts without waiting for a sample
+    clock: SharedClock,
     dashboard: Option<DashboardBuffer>,
+    mut shared_state: Option<SharedResourceClient>,
+    plants: Option<Arc<PlantLoop>>,
 ) {
-    let mut pid = PidController::new(1.0, 0.1, 0.01);
-    let mut error_threshold = 5.0; // Dynamic threshold for recalibration
+    let mut stage = ActuatorStage::new(&config, actuator_type, estop, clock.clone(), Arc::clone(&recorder), diagnostics, plants)
+        .with_dashboard(dashboard.clone());
+    let settings = stage.settings().clone();
+    let deadline = settings.deadline();
+    let mut workload = Workload::for_actuator(&config, actuator_type);
     let mut cycle_count = 0u64;
 
-    let init_time = start_time.elapsed().as_secs_f64();
+    let init_time = clock.elapsed().as_secs_f64();
     if config.enable_logging {
-        println!("[{:>8}] [SYSTEM] {:?} actuator initialized - Deadline: {:.1}ms",
-                 format!("{:.3}s", init_time), actuator_type, deadline.as_millis() as f64);
+        println!("[{:>8}] [SYSTEM] {:?} actuator initialized - Deadline: {:.1}ms, controller: {}",
+                 format!("{:.3}s", init_time), actuator_type, deadline.as_secs_f64() * 1000.0, stage.controller_kind());
     }
 
-    while !shutdown.load(Ordering::Relaxed) {
-        let data = match receiver.recv().await {
-            Some(d) => d,
-            None => continue,
-        };
-
-        cycle_count += 1;
-        let _timestamp_ns = start_time.elapsed().as_nanos() as u64;
-        let cycle_start = Instant::now();
-        let error = -data.position;
-        let control = pid.compute(error, config.sensor_period_ms as f64 / 1000.0);
-
-        // Determine actuator status based on error magnitude
-        let status = if error.abs() > 10.0 {
-            if config.enable_logging {
-                let elapsed = start_time.elapsed().as_secs_f64();
-                println!("[{:>8}] [EMERGENCY] {:?}: Entering emergency mode - Error: {:.2} (>10.0 threshold)",
-                         format!("{:.3}s", elapsed), actuator_type, error.abs());
+    // Runs until the dispatcher hangs up, so everything already queued is still handled
+    loop {
+        let mut data = tokio::select! {
+            biased;
+            _ = estop_wake.notified() => {
+                stage.react_to_estop();
+                continue;
             }
-            ActuatorStatus::Emergency
-        } else if error.abs() > error_threshold {
-            ActuatorStatus::Correcting
-        } else {
-            ActuatorStatus::Normal
+            data = receiver.recv() => match data {
+                Some(data) => data,
+                None => break,
+            },
         };
+        data.stamps.actuator_received_ns = clock.now_ns();
+        cycle_count += 1;
+
+        let step = stage.control(&data);
+        workload.run();
+
+        // Contention mode: read sensor state / publish the control output under the shared lock
+        let shared_lock_wait = shared_state.as_mut().map(|client| client.access_cycle(step.output));
 
         // Log actuator processing more frequently for demonstration
-        if config.enable_logging && cycle_count % 10 == 0 {
-            let elapsed = start_time.elapsed().as_secs_f64();
+        if config.enable_logging && cycle_count.is_multiple_of(10) {
+            let elapsed = clock.elapsed().as_secs_f64();
             println!("[{:>8}] {:?}: Processed cycle #{:<4} - Error: {:.2}, Control: {:.2} ({:?})",
-                     format!("{:.3}s", elapsed), actuator_type, data.id, error, control, status);
-        }
-
-        // Dynamic recalibration: adjust threshold based on recent performance
-        if error.abs() < 2.0 {
-            error_threshold = (error_threshold * 0.99).max(3.0); // Gradually lower threshold
-        } else if error.abs() > 8.0 {
-            error_threshold = (error_threshold * 1.01).min(7.0); // Gradually raise threshold
+                     format!("{:.3}s", elapsed), actuator_type, data.id, step.error, step.output, step.status);
         }
 
-        let processing_elapsed = cycle_start.elapsed();
-        let deadline_met = processing_elapsed <= deadline;
-        let processing_ms = processing_elapsed.as_nanos() as f64 / 1_000_000.0;
+        let verdict = stage.actuate(&mut data, &step);
+        let processing_ms = verdict.processing_time_ns as f64 / 1_000_000.0;
         let deadline_ms = deadline.as_nanos() as f64 / 1_000_000.0;
 
         // Log processing results more frequently
-        if config.enable_logging && cycle_count % 10 == 0 {
-            let elapsed = start_time.elapsed().as_secs_f64();
+        if config.enable_logging && cycle_count.is_multiple_of(10) {
+            let elapsed = clock.elapsed().as_secs_f64();
             println!("[{:>8}] {:?}: Processed {} (latency: {:.2}ms, deadline: {:.1}ms)",
-                     format!("{:.3}s", elapsed), actuator_type, if deadline_met { "✓" } else { "✗" }, processing_ms, deadline_ms);
+                     format!("{:.3}s", elapsed), actuator_type, if verdict.deadline_met { "✓" } else { "✗" }, processing_ms,
+                     deadline_ms);
         }
 
         // Measure lock wait time
         let lock_start = Instant::now();
-        let lateness_ns = if deadline_met { 0 } else {
-            processing_elapsed.as_nanos() as i64 - deadline.as_nanos() as i64
-        };
-        let processing_time_ns = processing_elapsed.as_nanos() as u64;
-        let lock_wait_ns = lock_start.elapsed().as_nanos() as u64;
-        let total_latency_ns = start_time.elapsed().as_nanos() as u64 - data.timestamp;
+        let lock_wait_ns = shared_lock_wait.map_or_else(
+            || lock_start.elapsed().as_nanos() as u64,
+            |wait| wait.as_nanos() as u64,
+        );
 
         // Log deadline misses with enhanced formatting
-        if config.enable_logging && !deadline_met {
-            let elapsed = start_time.elapsed().as_secs_f64();
+        if config.enable_logging && !verdict.deadline_met {
+            let elapsed = clock.elapsed().as_secs_f64();
             println!("[{:>8}] [DEADLINE] {:?}: Processing missed - {:.2}ms > {:.1}ms (cycle #{}) ✗",
                      format!("{:.3}s", elapsed), actuator_type, processing_ms, deadline_ms, data.id);
         }
 
         // Log shared resource access occasionally
-        if config.enable_logging && cycle_count % 50 == 0 {
-            let elapsed = start_time.elapsed().as_secs_f64();
+        if config.enable_logging && cycle_count.is_multiple_of(50) {
+            let elapsed = clock.elapsed().as_secs_f64();
             println!("[{:>8}] [SYNC] {:?} accessing shared recorder (performance metrics)",
                      format!("{:.3}s", elapsed), actuator_type);
         }
 
-        recorder.record(CycleResult {
-            cycle_id: data.id,
-            mode: config.mode.clone(),
-            actuator: Some(actuator_type),
-            total_latency_ns,
-            processing_time_ns,
-            lock_wait_ns,
-            deadline_met,
-            lateness_ns,
-        });
+        let result = stage.cycle_result(&data, &step, &verdict, lock_wait_ns);
+        let total_latency_ns = result.total_latency_ns;
+        recorder.record(result);
 
         // Send feedback within 0.5ms deadline
-        let feedback_start = Instant::now();
-        let feedback = ActuatorFeedback {
-            sensor_id: data.id,
-            status,
-            control_output: control,
-            error,
-            timestamp: start_time.elapsed().as_nanos() as u64,
-        };
+        let feedback = stage.feedback(&mut data, &step);
         let feedback_sent = feedback_tx.try_send(feedback).is_ok();
-        let feedback_time = feedback_start.elapsed();
-        let feedback_deadline_met = feedback_time.as_nanos() as u64 <= FEEDBACK_DEADLINE_NS;
+        let feedback_time = Duration::from_nanos(clock.now_ns() - data.stamps.feedback_sent_ns);
+        stage.feedback_sent(feedback, feedback_sent);
+        let feedback_deadline_met = feedback_time <= settings.feedback_deadline();
 
         // Log feedback transmission more frequently
-        if config.enable_logging && cycle_count % 10 == 0 {
-            let elapsed = start_time.elapsed().as_secs_f64();
+        if config.enable_logging && cycle_count.is_multiple_of(10) {
+            let elapsed = clock.elapsed().as_secs_f64();
             let feedback_us = feedback_time.as_nanos() as f64 / 1000.0;
-            println!("[{:>8}] {:?}: Feedback sent {} (latency: {:.2}μs, deadline: 500μs)",
-                     format!("{:.3}s", elapsed), actuator_type, if feedback_sent && feedback_deadline_met { "✓" } else { "✗" }, feedback_us);
+            println!("[{:>8}] {:?}: Feedback sent {} (latency: {:.2}μs, deadline: {}μs)",
+                     format!("{:.3}s", elapsed), actuator_type, if feedback_sent && feedback_deadline_met { "✓" } else { "✗" }, feedback_us,
+                     settings.feedback_deadline_us);
         }
 
         // Log feedback transmission failures
         if config.enable_logging && !feedback_sent {
-            let elapsed = start_time.elapsed().as_secs_f64();
+            let elapsed = clock.elapsed().as_secs_f64();
             println!("[{:>8}] [ERROR] {:?}: Feedback transmission failed - channel full (cycle #{})",
                      format!("{:.3}s", elapsed), actuator_type, data.id);
         }
@@ -152,16 +131,16 @@ pub async fn run_actuator_task(
         // Send to dashboard
         if let Some(dash) = &dashboard {
             dash.add(DashboardData {
-                timestamp: start_time.elapsed().as_nanos() as u64,
+                timestamp: clock.now_ns(),
                 sensor_data: None,
                 actuator_feedback: Some((actuator_type, feedback)),
                 metrics: Some(MetricsSnapshot {
                     cycle_id: data.id,
-                    processing_time_ns,
+                    processing_time_ns: verdict.processing_time_ns,
                     lock_wait_ns,
                     total_latency_ns,
-                    deadline_met,
-                    lateness_ns,
+                    deadline_met: verdict.deadline_met,
+                    lateness_ns: verdict.lateness_ns,
                 }),
             });
         }
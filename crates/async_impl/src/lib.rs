@@ -1,107 +1,157 @@
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
-use tokio::time::Instant;
-
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, Notify};
 use tokio::time::Duration;
+use tokio_util::sync::CancellationToken;
 
 use common::{
-    ActuatorFeedback, ActuatorType, BenchmarkRecorder,
+    ActuatorFeedback, ActuatorType, BenchmarkRecorder, CaptureFile, CaptureRecord,
     ExperimentConfig, SensorData, SharedDiagnostics,
-    DashboardBuffer,
+    DashboardBuffer, LoadGenerator, MonotonicClock, PlantLoop, SensorSource, SharedClock, TraceDrop, TraceRecord,
 };
 
+use common::estop::EStopCoordinator;
+use common::runtime::{print_final_summary, print_start_banner};
+use common::shared_resource::{SharedResourceClient, SharedStateTable};
+use common::RuntimeError;
+
 mod actuator;
+mod runtime;
 mod sensor;
+mod shutdown;
+
+pub use runtime::AsyncRuntime;
+use shutdown::Tasks;
 
-pub async fn run_experiment(config: ExperimentConfig) -> Arc<BenchmarkRecorder> {
-    run_experiment_with_dashboard(config, None).await
+/// Runs one experiment for `config.duration_secs` on the current tokio runtime.
+pub async fn run_experiment(config: ExperimentConfig) -> Result<Arc<BenchmarkRecorder>, RuntimeError> {
+    let source = SensorSource::from_config(&config).map_err(|e| RuntimeError::Failed(e.to_string()))?;
+    let capture = CaptureFile::create(&config, "async").map_err(|e| RuntimeError::Failed(e.to_string()))?;
+    run(config, None, source, capture, CancellationToken::new()).await
 }
 
-pub async fn run_experiment_with_dashboard(
+/// Orchestrates one run. It ends after `duration_secs`, once `stop` is cancelled, when
+/// a replay that doesn't loop runs out, or as soon as any task panics. `capture`, when
+/// given, records the run.
+///
+/// Every task lives in one `JoinSet`. Cancelling the run's token stops the sensor and
+/// the perf monitor; the sensor cancels it itself at the end of a replay; the dispatcher and actuators then drain what is queued and end
+/// when their input channels close. Tasks still running after `shutdown.deadline_ms`
+/// are aborted and reported, and the recorder is sealed before it is returned.
+async fn run(
     config: ExperimentConfig,
     dashboard: Option<DashboardBuffer>,
-) -> Arc<BenchmarkRecorder> {
+    source: SensorSource,
+    capture: Option<CaptureFile>,
+    stop: CancellationToken,
+) -> Result<Arc<BenchmarkRecorder>, RuntimeError> {
     if config.enable_logging {
-        println!("===========================================");
-        println!("Real-Time Sensor-Actuator System Starting");
-        println!("===========================================");
-        println!("Configuration: {}", config.experiment_name);
-        println!("Duration: {} seconds", config.duration_secs);
-        println!("Sensor period: {} ms", config.sensor_period_ms);
-        println!("Mode: {}", config.mode);
-        println!("Components: Sensor + Dispatcher + 3 Actuators");
-        println!("Deadlines: Sensor(0.2ms/0.1ms), Actuators(1-2ms), Feedback(0.5ms)");
-        println!("===========================================");
+        print_start_banner("Async", &config);
     }
 
-    let recorder = Arc::new(BenchmarkRecorder::new());
-    let shutdown_flag = Arc::new(AtomicBool::new(false));
+    let run_token = stop.child_token();
+    let mut tasks = Tasks::new();
     let diagnostics = Arc::new(SharedDiagnostics::default());
+    let estop = Arc::new(EStopCoordinator::new(config.estop.clone()));
+
+    // Background CPU load runs on OS threads so it competes with the tokio workers
+    let load_generator = LoadGenerator::start(config.cpu_load_threads, &config.cpu_load);
+
+    // Contention mode: sensor and all actuators share one lock-guarded state table
+    let shared_state = config
+        .is_contention_mode()
+        .then(|| Arc::new(SharedStateTable::new(&config.shared_resource)));
+    let shared_client = |stage: usize| shared_state.as_ref().map(|table| table.client(stage, config.workload.seed));
+
+    // Closed loop: actuator outputs drive the plants the sensor measures
+    let plants = PlantLoop::from_config(&config).map(Arc::new);
 
     // Sensor -> dispatcher
-    let (sensor_tx, mut dispatcher_rx) = mpsc::channel::<SensorData>(100);
+    let (sensor_tx, mut dispatcher_rx) = mpsc::channel::<SensorData>(config.sensor.channel_capacity);
 
     // Dispatcher -> actuators
-    let (gripper_tx, gripper_rx) = mpsc::channel::<SensorData>(100);
-    let (motor_tx, motor_rx) = mpsc::channel::<SensorData>(100);
-    let (stabilizer_tx, stabilizer_rx) = mpsc::channel::<SensorData>(100);
+    let (gripper_tx, gripper_rx) = mpsc::channel::<SensorData>(config.actuators.gripper.channel_capacity);
+    let (motor_tx, motor_rx) = mpsc::channel::<SensorData>(config.actuators.motor.channel_capacity);
+    let (stabilizer_tx, stabilizer_rx) = mpsc::channel::<SensorData>(config.actuators.stabilizer.channel_capacity);
 
     // Feedback channel
-    let (feedback_tx, feedback_rx) = mpsc::channel::<ActuatorFeedback>(100);
+    let (feedback_tx, feedback_rx) = mpsc::channel::<ActuatorFeedback>(config.sensor.feedback_channel_capacity);
 
-    let start_time = Instant::now();
+    // The recorder's clock starts here, so every task measures from the same epoch
+    let recorder = Arc::new(BenchmarkRecorder::for_run(Arc::new(MonotonicClock::start()), &config.recording));
+    let clock = recorder.clock();
+    if let Some(file) = capture {
+        recorder.start_capture(file);
+    }
+
+    // Unpaced replay waits for room in every queue instead of dropping, so each sample
+    // reaches every actuator
+    let paced = source.is_paced();
 
     // ---------------- SENSOR ----------------
     {
         let cfg = config.clone();
         let rec = Arc::clone(&recorder);
         let diag = Arc::clone(&diagnostics);
-        let shutdown = Arc::clone(&shutdown_flag);
+        let sensor_stop = run_token.clone(); // Not a child: the sensor can end the run
+        let clk = Arc::clone(&clock);
         let tx = sensor_tx;
         let feedback_recv = feedback_rx;
         let dash = dashboard.clone();
-
-        tokio::spawn(async move {
-            sensor::run_sensor_task(
-                cfg,
-                tx,
-                feedback_recv,
-                rec,
-                diag,
-                shutdown,
-                start_time,
-                dash,
-            ).await;
-        });
+        let shared = shared_client(0);
+        let plants = plants.clone();
+
+        tasks.spawn("sensor", sensor::run_sensor_task(
+            cfg,
+            tx,
+            feedback_recv,
+            rec,
+            diag,
+            sensor_stop,
+            source,
+            clk,
+            dash,
+            shared,
+            plants,
+        ));
     }
 
     // ---------------- DISPATCHER ----------------
     {
-        let tx1 = gripper_tx.clone();
-        let tx2 = motor_tx.clone();
-        let tx3 = stabilizer_tx.clone();
+        // Moved, not cloned, so the actuators see the channels close once this exits
+        let tx1 = gripper_tx;
+        let tx2 = motor_tx;
+        let tx3 = stabilizer_tx;
 
         let dispatcher_config = config.clone();
-        tokio::spawn(async move {
+        let clock = Arc::clone(&clock);
+        let recorder = Arc::clone(&recorder);
+        tasks.spawn("dispatcher", async move {
             let mut cycle_count = 0u64;
-            let dispatcher_start = Instant::now();
+            let dispatcher_start = clock.elapsed();
             if dispatcher_config.enable_logging {
                 println!("[{:>8}] [SYSTEM] Dispatcher initialized - routing sensor data to 3 actuators",
-                         format!("{:.3}s", dispatcher_start.duration_since(start_time).as_secs_f64()));
+                         format!("{:.3}s", dispatcher_start.as_secs_f64()));
             }
 
-            while let Some(data) = dispatcher_rx.recv().await {
+            while let Some(mut data) = dispatcher_rx.recv().await {
                 cycle_count += 1;
+                data.stamps.dispatched_ns = clock.now_ns();
                 // SensorData is Copy, so we can clone it cheaply for each actuator
-                let gripper_sent = tx1.try_send(data).is_ok();
-                let motor_sent = tx2.try_send(data).is_ok();
-                let stabilizer_sent = tx3.try_send(data).is_ok();
+                let gripper_sent = if paced { tx1.try_send(data).is_ok() } else { tx1.send(data).await.is_ok() };
+                let motor_sent = if paced { tx2.try_send(data).is_ok() } else { tx2.send(data).await.is_ok() };
+                let stabilizer_sent = if paced { tx3.try_send(data).is_ok() } else { tx3.send(data).await.is_ok() };
+                for (actuator, sent) in ActuatorType::ALL.into_iter().zip([gripper_sent, motor_sent, stabilizer_sent]) {
+                    recorder.capture(CaptureRecord::Dispatched { actuator, data, sent });
+                    if !sent {
+                        recorder.record_trace(TraceRecord::dropped(data.id, actuator, data.stamps, TraceDrop::Dispatch));
+                    }
+                }
 
                 // Log dispatcher activity more frequently for demonstration
-                if dispatcher_config.enable_logging && cycle_count % 5 == 0 {
-                    let elapsed = dispatcher_start.duration_since(start_time).as_secs_f64();
+                if dispatcher_config.enable_logging && cycle_count.is_multiple_of(5) {
+                    let elapsed = clock.elapsed().as_secs_f64();
                     if config.enable_logging {
                         println!("[{:>8}] DISPATCHER: Routed cycle #{:<4} to actuators (G:{:?}, M:{:?}, S:{:?})",
                                 format!("{:.3}s", elapsed), cycle_count, gripper_sent, motor_sent, stabilizer_sent);
@@ -110,7 +160,7 @@ pub async fn run_experiment_with_dashboard(
 
                 // Log transmission failures
                 if dispatcher_config.enable_logging && (!gripper_sent || !motor_sent || !stabilizer_sent) {
-                    let elapsed = dispatcher_start.duration_since(start_time).as_secs_f64();
+                    let elapsed = clock.elapsed().as_secs_f64();
                     if config.enable_logging {
                         println!("[{:>8}] [ERROR] DISPATCHER: Failed to route cycle #{} - channels full",
                                 format!("{:.3}s", elapsed), cycle_count);
@@ -118,7 +168,7 @@ pub async fn run_experiment_with_dashboard(
                 }
             }
 
-            let total_time = dispatcher_start.elapsed().as_secs_f64();
+            let total_time = (clock.elapsed() - dispatcher_start).as_secs_f64();
             if config.enable_logging {
                 println!("[{:>8}] [SYSTEM] Dispatcher shutdown - processed {} cycles in {:.2}s",
                          format!("{:.3}s", total_time), cycle_count, total_time);
@@ -128,39 +178,48 @@ pub async fn run_experiment_with_dashboard(
 
     // ---------------- ACTUATORS ----------------
     spawn_actuator(
+        &mut tasks,
         ActuatorType::Gripper,
-        Duration::from_millis(1),
         config.clone(),
         gripper_rx,
         feedback_tx.clone(),
         Arc::clone(&recorder),
-        Arc::clone(&shutdown_flag),
-        start_time,
+        Arc::clone(&diagnostics),
+        &estop,
+        Arc::clone(&clock),
         dashboard.clone(),
+        shared_client(ActuatorType::Gripper.stage_index()),
+        plants.clone(),
     );
 
     spawn_actuator(
+        &mut tasks,
         ActuatorType::Motor,
-        Duration::from_millis(2),
         config.clone(),
         motor_rx,
         feedback_tx.clone(),
         Arc::clone(&recorder),
-        Arc::clone(&shutdown_flag),
-        start_time,
+        Arc::clone(&diagnostics),
+        &estop,
+        Arc::clone(&clock),
         dashboard.clone(),
+        shared_client(ActuatorType::Motor.stage_index()),
+        plants.clone(),
     );
 
     spawn_actuator(
+        &mut tasks,
         ActuatorType::Stabilizer,
-        Duration::from_micros(1500),
         config.clone(),
         stabilizer_rx,
         feedback_tx,
         Arc::clone(&recorder),
-        Arc::clone(&shutdown_flag),
-        start_time,
+        Arc::clone(&diagnostics),
+        &estop,
+        Arc::clone(&clock),
         dashboard.clone(),
+        shared_client(ActuatorType::Stabilizer.stage_index()),
+        plants.clone(),
     );
 
     // ---------------- RUN ----------------
@@ -168,100 +227,114 @@ pub async fn run_experiment_with_dashboard(
         println!("[SYSTEM] Experiment running for {} seconds...", config.duration_secs);
     }
 
-    // Performance monitoring loop
+    // Performance monitoring loop, cancelled with the sensor rather than on its next tick
     let recorder_clone = Arc::clone(&recorder);
-    let shutdown_flag_clone = Arc::clone(&shutdown_flag);
+    let monitor_stop = run_token.child_token();
     let perf_config = config.clone();
-    let perf_monitor = tokio::spawn(async move {
+    tasks.spawn("perf_monitor", async move {
         let mut interval = tokio::time::interval(Duration::from_secs(2));
         let mut cycle_count = 0u64;
+        let mut missed_count = 0u64;
 
         loop {
-            interval.tick().await;
-            let results = recorder_clone.get_results();
-            let current_cycles = results.len() as u64;
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = monitor_stop.cancelled() => break,
+            }
+            let current_cycles = recorder_clone.recorded_results() as u64;
+            let current_missed = recorder_clone.missed_deadlines.load(Ordering::Relaxed) as u64;
 
             if current_cycles > cycle_count {
                 let new_cycles = current_cycles - cycle_count;
-                let missed = results.iter().rev().take(new_cycles as usize).filter(|r| !r.deadline_met).count();
+                let missed = (current_missed - missed_count).min(new_cycles) as usize;
                 let throughput = new_cycles as f64 / 2.0; // cycles per second over 2 second window
 
                 let compliance_rate = if new_cycles > 0 {
                     (new_cycles - missed as u64) as f64 / new_cycles as f64 * 100.0
                 } else { 100.0 };
-                if perf_config.enable_logging {
-                    if config.enable_logging {
-                        println!("[PERF] Throughput: {:.1} cycles/sec, Recent compliance: {:.1}% ({}/{} cycles met)",
-                                throughput, compliance_rate, new_cycles - missed as u64, new_cycles);
-                    }
+                if perf_config.enable_logging && config.enable_logging {
+                    println!("[PERF] Throughput: {:.1} cycles/sec, Recent compliance: {:.1}% ({}/{} cycles met)",
+                            throughput, compliance_rate, new_cycles - missed as u64, new_cycles);
                 }
                 cycle_count = current_cycles;
-            }
-
-            if shutdown_flag_clone.load(Ordering::Relaxed) {
-                break;
+                missed_count = current_missed;
             }
         }
     });
 
-    tokio::time::sleep(Duration::from_secs(config.duration_secs)).await;
-    shutdown_flag.store(true, Ordering::Relaxed);
+    tokio::select! {
+        _ = tokio::time::sleep(Duration::from_secs(config.duration_secs)) => {}
+        _ = run_token.cancelled() => {}
+        // No task ends on its own while the run is live, so this is a panic or a bug
+        _ = tasks.join_next() => {
+            if config.enable_logging {
+                println!("[ERROR] A pipeline task ended early - shutting down");
+            }
+        }
+    }
+    run_token.cancel(); // Stops the sensor and perf monitor; the rest drains behind them
 
     if config.enable_logging {
         println!("===========================================");
         println!("Experiment completed - initiating shutdown");
     }
-    tokio::time::sleep(Duration::from_millis(100)).await; // Brief pause for cleanup
-
-    // Wait for performance monitor to finish
-    let _ = perf_monitor.await;
 
-    let results = recorder.get_results();
-    let total_cycles = results.len();
-    let missed_deadlines = results.iter().filter(|r| !r.deadline_met).count();
-    let deadline_compliance = if total_cycles > 0 {
-        (total_cycles - missed_deadlines) as f64 / total_cycles as f64 * 100.0
-    } else { 0.0 };
+    let report = tasks.drain(config.shutdown.deadline()).await;
+    recorder.close();
+    if config.enable_logging && !report.is_clean() {
+        println!("[WARNING] Async shutdown incomplete: {}", report);
+    }
+    recorder.set_shutdown_report(report);
+    if let Some(plants) = &plants {
+        recorder.set_loop_performance(plants.performance());
+    }
+    recorder.set_status_transitions(diagnostics.transitions());
 
-    let anomalies = diagnostics.anomaly_count.load(Ordering::Relaxed);
-    let emergencies = diagnostics.emergency_stops.load(Ordering::Relaxed);
+    let load_profile = tokio::task::spawn_blocking(move || load_generator.stop())
+        .await
+        .unwrap_or_default();
+    recorder.set_load_profile(load_profile);
 
     if config.enable_logging {
-        println!("===========================================");
-        println!("FINAL SYSTEM RESULTS");
-        println!("===========================================");
-        println!("Total Cycles: {}", total_cycles);
-        println!("Deadline Compliance: {:.2}% ({} missed)", deadline_compliance, missed_deadlines);
-        println!("Anomalies Detected: {}", anomalies);
-        println!("Emergency Events: {}", emergencies);
-        println!("===========================================");
+        print_final_summary("Async", &recorder, &diagnostics, shared_state.as_ref().map(|table| table.stats()));
     }
 
-    recorder
+    match tasks.first_panic() {
+        Some(error) => Err(error),
+        None => Ok(recorder),
+    }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn spawn_actuator(
+    tasks: &mut Tasks,
     actuator_type: ActuatorType,
-    deadline: Duration,
     config: ExperimentConfig,
     receiver: mpsc::Receiver<SensorData>,
     feedback_tx: mpsc::Sender<ActuatorFeedback>,
     recorder: Arc<BenchmarkRecorder>,
-    shutdown: Arc<AtomicBool>,
-    start_time: Instant,
+    diagnostics: Arc<SharedDiagnostics>,
+    estop: &Arc<EStopCoordinator>,
+    clock: SharedClock,
     dashboard: Option<DashboardBuffer>,
+    shared_state: Option<SharedResourceClient>,
+    plants: Option<Arc<PlantLoop>>,
 ) {
-    tokio::spawn(async move {
-        actuator::run_actuator_task(
-            actuator_type,
-            deadline,
-            config,
-            receiver,
-            feedback_tx,
-            recorder,
-            shutdown,
-            start_time,
-            dashboard,
-        ).await;
-    });
+    let estop_wake = Arc::new(Notify::new());
+    let waker = Arc::clone(&estop_wake);
+    estop.on_trigger(move || waker.notify_one());
+    tasks.spawn(format!("{:?}", actuator_type).to_lowercase(), actuator::run_actuator_task(
+        actuator_type,
+        config,
+        receiver,
+        feedback_tx,
+        recorder,
+        diagnostics,
+        estop.handle(actuator_type),
+        estop_wake,
+        clock,
+        dashboard,
+        shared_state,
+        plants,
+    ));
 }
@@ -0,0 +1,114 @@
+use std::sync::Arc;
+
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use common::runtime::{Runtime, RuntimeError, RuntimeStatus};
+use common::{BenchmarkRecorder, CaptureFile, DashboardBuffer, ExperimentConfig, SensorSource};
+
+/// [`Runtime`] over tokio tasks. Owns a multi-threaded tokio runtime that is reused
+/// across runs, so `wait` must not be called from inside another tokio runtime.
+/// Each run's tasks are joined or aborted before its results are returned, so
+/// back-to-back runs never share a live task.
+pub struct AsyncRuntime {
+    runtime: tokio::runtime::Runtime,
+    dashboard: Option<DashboardBuffer>,
+    stop: CancellationToken,
+    handle: Option<JoinHandle<Result<Arc<BenchmarkRecorder>, RuntimeError>>>,
+}
+
+impl AsyncRuntime {
+    pub fn new() -> std::io::Result<Self> {
+        Ok(Self {
+            runtime: tokio::runtime::Runtime::new()?,
+            dashboard: None,
+            stop: CancellationToken::new(),
+            handle: None,
+        })
+    }
+}
+
+impl Runtime for AsyncRuntime {
+    fn name(&self) -> &'static str {
+        "async"
+    }
+
+    fn attach_dashboard(&mut self, dashboard: DashboardBuffer) {
+        self.dashboard = Some(dashboard);
+    }
+
+    fn start(&mut self, config: ExperimentConfig) -> Result<(), RuntimeError> {
+        if self.handle.is_some() {
+            return Err(RuntimeError::AlreadyRunning);
+        }
+        let source = SensorSource::from_config(&config).map_err(|e| RuntimeError::Failed(e.to_string()))?;
+        let capture = CaptureFile::create(&config, self.name()).map_err(|e| RuntimeError::Failed(e.to_string()))?;
+        // Fresh token so a stop aimed at an earlier run can't end this one
+        self.stop = CancellationToken::new();
+        let run = crate::run(config, self.dashboard.clone(), source, capture, self.stop.clone());
+        self.handle = Some(self.runtime.spawn(run));
+        Ok(())
+    }
+
+    fn stop(&self) {
+        if self.handle.is_some() {
+            self.stop.cancel();
+        }
+    }
+
+    fn status(&self) -> RuntimeStatus {
+        match &self.handle {
+            None => RuntimeStatus::Idle,
+            Some(handle) if handle.is_finished() => RuntimeStatus::Finished,
+            Some(_) if self.stop.is_cancelled() => RuntimeStatus::Stopping,
+            Some(_) => RuntimeStatus::Running,
+        }
+    }
+
+    fn wait(&mut self) -> Result<Arc<BenchmarkRecorder>, RuntimeError> {
+        let handle = self.handle.take().ok_or(RuntimeError::NotStarted)?;
+        self.runtime
+            .block_on(handle)
+            .map_err(|e| RuntimeError::Failed(e.to_string()))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use common::config::parse_config;
+    use common::runtime::{Runtime, RuntimeError, RuntimeStatus};
+
+    use super::AsyncRuntime;
+
+    #[test]
+    fn back_to_back_runs_do_not_interfere() {
+        let config = parse_config(
+            "test.toml",
+            "experiment_name = \"test\"\nduration_secs = 1\nsensor_period_ms = 10\ncpu_load_threads = 0\n\
+             mode = \"baseline\"\nprocessing_time_ns = 1000\nenable_logging = false\n",
+            false,
+        )
+        .unwrap();
+        let mut runtime = AsyncRuntime::new().unwrap();
+
+        runtime.start(config.clone()).unwrap();
+        assert!(matches!(runtime.start(config.clone()), Err(RuntimeError::AlreadyRunning)));
+        runtime.stop();
+        let stopped = runtime.wait().unwrap();
+        let stopped_results = stopped.recorded_results();
+        assert!(stopped.shutdown_report().unwrap().is_clean());
+        assert_eq!(runtime.status(), RuntimeStatus::Idle);
+
+        // The first run's stop must not end this one, nor its tasks record into it
+        let started = Instant::now();
+        runtime.start(config).unwrap();
+        assert_eq!(runtime.status(), RuntimeStatus::Running);
+        let full = runtime.wait().unwrap();
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        assert!(full.shutdown_report().unwrap().is_clean());
+        assert!(full.recorded_results() > stopped_results);
+        assert_eq!(stopped.recorded_results(), stopped_results);
+    }
+}
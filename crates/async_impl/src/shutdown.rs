@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+
+use tokio::task::{Id, JoinError, JoinSet};
+use tokio::time::Instant;
+
+use common::runtime::RuntimeError;
+use common::ShutdownReport;
+
+/// How one task ended.
+enum Exit {
+    Stopped,
+    Panicked(String),
+}
+
+/// Owns every pipeline task of one run. Dropping it aborts whatever is still running,
+/// so a straggler can't outlive its run and interfere with the next one.
+pub(crate) struct Tasks {
+    set: JoinSet<()>,
+    names: HashMap<Id, String>,
+    order: Vec<String>, // Pipeline order, for the report
+    exits: HashMap<String, Exit>,
+}
+
+impl Tasks {
+    pub(crate) fn new() -> Self {
+        Self {
+            set: JoinSet::new(),
+            names: HashMap::new(),
+            order: Vec::new(),
+            exits: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn spawn(&mut self, name: impl Into<String>, task: impl Future<Output = ()> + Send + 'static) {
+        let name = name.into();
+        let handle = self.set.spawn(task);
+        self.names.insert(handle.id(), name.clone());
+        self.order.push(name);
+    }
+
+    /// Waits for the next task to end and records how it ended. `None` once every
+    /// task has been collected.
+    pub(crate) async fn join_next(&mut self) -> Option<()> {
+        let (id, exit) = match self.set.join_next_with_id().await? {
+            Ok((id, ())) => (id, Exit::Stopped),
+            Err(error) if error.is_panic() => (error.id(), Exit::Panicked(panic_message(error))),
+            Err(error) => (error.id(), Exit::Stopped), // Cancelled
+        };
+        let name = self.names.remove(&id).unwrap_or_else(|| format!("task {}", id));
+        self.exits.insert(name, exit);
+        Some(())
+    }
+
+    /// First panicked task in pipeline order, as the run's error.
+    pub(crate) fn first_panic(&self) -> Option<RuntimeError> {
+        self.order.iter().find_map(|name| match self.exits.get(name) {
+            Some(Exit::Panicked(message)) => {
+                Some(RuntimeError::Panicked { component: name.clone(), message: message.clone() })
+            }
+            _ => None,
+        })
+    }
+
+    /// Collects tasks for up to `deadline`, then aborts the rest and reports them.
+    pub(crate) async fn drain(&mut self, deadline: Duration) -> ShutdownReport {
+        let start = Instant::now();
+        let _ = tokio::time::timeout(deadline, async {
+            while self.join_next().await.is_some() {}
+        })
+        .await;
+        self.set.abort_all();
+
+        let mut report = ShutdownReport {
+            elapsed: start.elapsed(),
+            deadline,
+            ..ShutdownReport::default()
+        };
+        for name in &self.order {
+            match self.exits.get(name) {
+                Some(Exit::Stopped) => report.stopped.push(name.clone()),
+                Some(Exit::Panicked(_)) => report.panicked.push(name.clone()),
+                None => report.failed_to_stop.push(name.clone()),
+            }
+        }
+        report
+    }
+}
+
+fn panic_message(error: JoinError) -> String {
+    let payload = error.into_panic();
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "non-string panic payload".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::sync::oneshot;
+
+    use super::Tasks;
+    use common::runtime::RuntimeError;
+
+    #[tokio::test]
+    async fn drain_aborts_stragglers_and_reports_panics_in_pipeline_order() {
+        let (straggler_alive, straggler_aborted) = oneshot::channel::<()>();
+        let mut tasks = Tasks::new();
+        tasks.spawn("sensor", tokio::time::sleep(Duration::from_millis(20)));
+        tasks.spawn("dispatcher", async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            panic!("dispatcher failed");
+        });
+        tasks.spawn("gripper", async { panic!("gripper failed") });
+        tasks.spawn("motor", async move {
+            let _alive = straggler_alive;
+            std::future::pending::<()>().await;
+        });
+        tasks.spawn("perf_monitor", async {});
+
+        let report = tasks.drain(Duration::from_millis(100)).await;
+        assert_eq!(report.stopped, ["sensor", "perf_monitor"]);
+        assert_eq!(report.panicked, ["dispatcher", "gripper"]);
+        assert_eq!(report.failed_to_stop, ["motor"]);
+        assert!(report.elapsed >= Duration::from_millis(100));
+        assert!(straggler_aborted.await.is_err(), "the straggler was aborted");
+
+        // The gripper panicked first, but the dispatcher comes first in the pipeline
+        match tasks.first_panic() {
+            Some(RuntimeError::Panicked { component, message }) => {
+                assert_eq!((component.as_str(), message.as_str()), ("dispatcher", "dispatcher failed"));
+            }
+            other => panic!("expected the dispatcher's panic, got {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn a_panic_ends_the_wait_for_a_live_run() {
+        let mut tasks = Tasks::new();
+        tasks.spawn("sensor", std::future::pending::<()>());
+        tasks.spawn("stabilizer", async { panic!("{} failed", "stabilizer") });
+
+        tokio::time::timeout(Duration::from_secs(1), tasks.join_next()).await.expect("the panic is collected at once");
+        assert!(matches!(tasks.first_panic(), Some(RuntimeError::Panicked { component, message })
+            if component == "stabilizer" && message == "stabilizer failed"));
+
+        let report = tasks.drain(Duration::from_millis(10)).await;
+        assert_eq!(report.failed_to_stop, ["sensor"]);
+    }
+}
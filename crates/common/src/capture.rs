@@ -0,0 +1,616 @@
+//! Binary capture of a run: every sensor sample, every message the dispatcher routed,
+//! every control output and every piece of actuator feedback, stamped on the run's
+//! clock. Written while the run goes when `[capture]` is set; read back with
+//! [`CaptureReader`], or fed through the pipeline again as a `[replay]` trace.
+//!
+//! Layout, with every number little-endian:
+//!
+//! ```text
+//! header   magic "RTSACAP\0", version u16,
+//!          runtime name (u16 length + UTF-8), ExperimentConfig as TOML (u32 length + UTF-8)
+//! record   kind u8, at_ns u64, then the kind's fixed-size payload (see CaptureRecord)
+//! ```
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+
+use crate::clock::SharedClock;
+use crate::config::{parse_config, ConfigError, ExperimentConfig};
+use crate::{ActuatorFeedback, ActuatorStatus, ActuatorType, SensorData, StageTimestamps};
+
+pub const CAPTURE_MAGIC: [u8; 8] = *b"RTSACAP\0";
+/// Bumped whenever the record layout changes, or `ExperimentConfig` changes in a way the
+/// lenient parse of the embedded config can't absorb (a renamed, removed or retyped
+/// field), so an older capture fails on its version rather than partway through.
+pub const CAPTURE_VERSION: u16 = 2; // 2: actuator status machine
+
+// Record kinds
+const SENSOR: u8 = 1;
+const DISPATCHED: u8 = 2;
+const CONTROL: u8 = 3;
+const FEEDBACK: u8 = 4;
+
+// Payload sizes
+const STAMPS_LEN: usize = 8 * 8;
+const DATA_LEN: usize = 5 * 8 + STAMPS_LEN;
+const SENSOR_LEN: usize = 8 + 1 + DATA_LEN;
+const DISPATCHED_LEN: usize = 1 + 1 + DATA_LEN;
+const CONTROL_LEN: usize = 1 + 1 + 3 * 8;
+const FEEDBACK_LEN: usize = 1 + 8 + 1 + 1 + 3 * 8 + STAMPS_LEN;
+
+/// Something a pipeline stage saw or did.
+#[derive(Debug, Clone, Copy)]
+pub enum CaptureRecord {
+    /// A filtered sample as the sensor sent it, with the unfiltered force it read.
+    Sensor { data: SensorData, raw_force: f64, sent: bool },
+    /// A sample the dispatcher routed to one actuator.
+    Dispatched { actuator: ActuatorType, data: SensorData, sent: bool },
+    /// An actuator's control output for one sample.
+    Control { actuator: ActuatorType, sensor_id: u64, error: f64, output: f64, status: ActuatorStatus },
+    /// Feedback an actuator sent back to the sensor.
+    Feedback { feedback: ActuatorFeedback, sent: bool },
+}
+
+/// A record and when it was taken, in ns on the run's clock.
+#[derive(Debug, Clone, Copy)]
+pub struct CaptureEntry {
+    pub at_ns: u64,
+    pub record: CaptureRecord,
+}
+
+#[derive(Debug)]
+pub enum CaptureError {
+    Io(io::Error),
+    NotACapture,
+    UnsupportedVersion(u16),
+    /// The file ends partway through a record, e.g. because the run was killed.
+    Truncated,
+    UnknownRecord(u8),
+    Invalid(&'static str),
+}
+
+impl fmt::Display for CaptureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaptureError::Io(err) => write!(f, "{}", err),
+            CaptureError::NotACapture => write!(f, "not a capture file"),
+            CaptureError::UnsupportedVersion(version) => write!(
+                f,
+                "capture format v{} is not supported; this build reads v{} only, so re-record the capture with it",
+                version, CAPTURE_VERSION
+            ),
+            CaptureError::Truncated => write!(f, "capture ends partway through a record"),
+            CaptureError::UnknownRecord(kind) => write!(f, "unknown capture record kind {}", kind),
+            CaptureError::Invalid(what) => write!(f, "invalid {} in capture", what),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
+
+impl From<io::Error> for CaptureError {
+    fn from(err: io::Error) -> Self {
+        match err.kind() {
+            io::ErrorKind::UnexpectedEof => CaptureError::Truncated,
+            _ => CaptureError::Io(err),
+        }
+    }
+}
+
+/// What a capture says about the run it came from.
+#[derive(Debug, Clone)]
+pub struct CaptureHeader {
+    pub version: u16,
+    pub runtime: String,
+    pub config_toml: String,
+}
+
+impl CaptureHeader {
+    /// The run's config, parsed and validated as if loaded from a file. Unknown fields are
+    /// ignored, so fields added since the capture was written don't make it unreadable.
+    pub fn config(&self) -> Result<ExperimentConfig, ConfigError> {
+        parse_config("<capture header>", &self.config_toml, false)
+    }
+}
+
+/// Encodes a capture onto any byte sink.
+pub struct CaptureWriter<W: Write> {
+    out: W,
+    records: u64,
+    buf: Vec<u8>,
+}
+
+impl<W: Write> CaptureWriter<W> {
+    /// Writes the header describing `runtime`'s run of `config`.
+    pub fn new(mut out: W, runtime: &str, config: &ExperimentConfig) -> io::Result<Self> {
+        let config_toml = toml::to_string(config).map_err(io::Error::other)?;
+        out.write_all(&CAPTURE_MAGIC)?;
+        out.write_all(&CAPTURE_VERSION.to_le_bytes())?;
+        out.write_all(&(runtime.len() as u16).to_le_bytes())?;
+        out.write_all(runtime.as_bytes())?;
+        out.write_all(&(config_toml.len() as u32).to_le_bytes())?;
+        out.write_all(config_toml.as_bytes())?;
+        Ok(Self { out, records: 0, buf: Vec::with_capacity(1 + 8 + SENSOR_LEN) })
+    }
+
+    pub fn write(&mut self, entry: &CaptureEntry) -> io::Result<()> {
+        let buf = &mut self.buf;
+        buf.clear();
+        match entry.record {
+            CaptureRecord::Sensor { data, raw_force, sent } => {
+                buf.push(SENSOR);
+                buf.extend(entry.at_ns.to_le_bytes());
+                buf.extend(raw_force.to_le_bytes());
+                buf.push(sent as u8);
+                put_data(buf, &data);
+            }
+            CaptureRecord::Dispatched { actuator, data, sent } => {
+                buf.push(DISPATCHED);
+                buf.extend(entry.at_ns.to_le_bytes());
+                buf.push(actuator_code(actuator));
+                buf.push(sent as u8);
+                put_data(buf, &data);
+            }
+            CaptureRecord::Control { actuator, sensor_id, error, output, status } => {
+                buf.push(CONTROL);
+                buf.extend(entry.at_ns.to_le_bytes());
+                buf.push(actuator_code(actuator));
+                buf.push(status_code(status));
+                buf.extend(sensor_id.to_le_bytes());
+                buf.extend(error.to_le_bytes());
+                buf.extend(output.to_le_bytes());
+            }
+            CaptureRecord::Feedback { feedback, sent } => {
+                buf.push(FEEDBACK);
+                buf.extend(entry.at_ns.to_le_bytes());
+                buf.push(sent as u8);
+                buf.extend(feedback.sensor_id.to_le_bytes());
+                buf.push(actuator_code(feedback.actuator));
+                buf.push(status_code(feedback.status));
+                buf.extend(feedback.control_output.to_le_bytes());
+                buf.extend(feedback.error.to_le_bytes());
+                buf.extend(feedback.timestamp.to_le_bytes());
+                put_stamps(buf, &feedback.stamps);
+            }
+        }
+        self.out.write_all(&self.buf)?;
+        self.records += 1;
+        Ok(())
+    }
+
+    pub fn records(&self) -> u64 {
+        self.records
+    }
+
+    /// Flushes and hands back the sink.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// One runtime's capture file, created with its header before the run starts so a bad
+/// path fails the start rather than the run.
+pub struct CaptureFile {
+    path: String,
+    writer: CaptureWriter<BufWriter<File>>,
+}
+
+impl CaptureFile {
+    /// `runtime`'s capture file per `[capture]`, or `None` when capturing is off.
+    pub fn create(config: &ExperimentConfig, runtime: &str) -> io::Result<Option<Self>> {
+        let Some(path) = config.capture.path_for(runtime) else {
+            return Ok(None);
+        };
+        let file = File::create(&path).map_err(|err| io::Error::new(err.kind(), format!("{}: {}", path, err)))?;
+        let writer = CaptureWriter::new(BufWriter::new(file), runtime, config)?;
+        Ok(Some(Self { path, writer }))
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
+
+/// How a capture ended.
+#[derive(Debug, Clone)]
+pub struct CaptureSummary {
+    pub path: String,
+    pub records: u64,
+    pub error: Option<String>,
+}
+
+impl fmt::Display for CaptureSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.error {
+            None => write!(f, "{} records written to {}", self.records, self.path),
+            Some(error) => write!(f, "writing {} failed: {}", self.path, error),
+        }
+    }
+}
+
+/// A capture being recorded. Entries are encoded and written on a thread of its own,
+/// so recording costs a stage one unbounded channel send.
+pub struct Capture {
+    path: String,
+    clock: SharedClock,
+    tx: Sender<Option<CaptureEntry>>, // `None` asks the writer to finish
+    writer: Mutex<Option<JoinHandle<io::Result<u64>>>>,
+}
+
+impl Capture {
+    pub fn start(file: CaptureFile, clock: SharedClock) -> Self {
+        let (tx, rx) = mpsc::channel::<Option<CaptureEntry>>();
+        let mut writer = file.writer;
+        let handle = thread::spawn(move || {
+            while let Ok(Some(entry)) = rx.recv() {
+                writer.write(&entry)?;
+            }
+            let records = writer.records();
+            writer.finish()?;
+            Ok(records)
+        });
+        Self { path: file.path, clock, tx, writer: Mutex::new(Some(handle)) }
+    }
+
+    /// Stamps `record` with the current run time and queues it for writing.
+    pub fn record(&self, record: CaptureRecord) {
+        // Fails only once the capture is finished or its writer has failed
+        let _ = self.tx.send(Some(CaptureEntry { at_ns: self.clock.now_ns(), record }));
+    }
+
+    /// Writes out everything recorded so far and closes the file; later records are
+    /// dropped. `None` if the capture was already finished.
+    pub fn finish(&self) -> Option<CaptureSummary> {
+        let handle = self.writer.lock().unwrap().take()?;
+        let _ = self.tx.send(None);
+        let (records, error) = match handle.join() {
+            Ok(Ok(records)) => (records, None),
+            Ok(Err(err)) => (0, Some(err.to_string())),
+            Err(_) => (0, Some("capture writer panicked".to_string())),
+        };
+        Some(CaptureSummary { path: self.path.clone(), records, error })
+    }
+}
+
+/// Decodes a capture, yielding its entries in the order they were recorded.
+pub struct CaptureReader<R: Read> {
+    input: R,
+    header: CaptureHeader,
+    done: bool,
+}
+
+impl CaptureReader<BufReader<File>> {
+    pub fn open(path: &str) -> Result<Self, CaptureError> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> CaptureReader<R> {
+    /// Reads and checks the header.
+    pub fn new(mut input: R) -> Result<Self, CaptureError> {
+        let mut magic = [0u8; 8];
+        input.read_exact(&mut magic).map_err(|_| CaptureError::NotACapture)?;
+        if magic != CAPTURE_MAGIC {
+            return Err(CaptureError::NotACapture);
+        }
+        let version = u16::from_le_bytes(read_array(&mut input)?);
+        if version != CAPTURE_VERSION {
+            return Err(CaptureError::UnsupportedVersion(version));
+        }
+        let runtime_len = u16::from_le_bytes(read_array(&mut input)?) as usize;
+        let runtime = read_string(&mut input, runtime_len)?;
+        let config_len = u32::from_le_bytes(read_array(&mut input)?) as usize;
+        let config_toml = read_string(&mut input, config_len)?;
+        Ok(Self { input, header: CaptureHeader { version, runtime, config_toml }, done: false })
+    }
+
+    pub fn header(&self) -> &CaptureHeader {
+        &self.header
+    }
+
+    fn read_entry(&mut self) -> Result<Option<CaptureEntry>, CaptureError> {
+        let mut kind = [0u8; 1];
+        if self.input.read(&mut kind)? == 0 {
+            return Ok(None);
+        }
+        let len = match kind[0] {
+            SENSOR => SENSOR_LEN,
+            DISPATCHED => DISPATCHED_LEN,
+            CONTROL => CONTROL_LEN,
+            FEEDBACK => FEEDBACK_LEN,
+            other => return Err(CaptureError::UnknownRecord(other)),
+        };
+        let mut bytes = [0u8; 8 + SENSOR_LEN];
+        self.input.read_exact(&mut bytes[..8 + len])?;
+        let mut fields = Fields { bytes: &bytes, pos: 0 };
+
+        let at_ns = fields.u64();
+        let record = match kind[0] {
+            SENSOR => {
+                let raw_force = fields.f64();
+                let sent = fields.bool();
+                CaptureRecord::Sensor { data: fields.data(), raw_force, sent }
+            }
+            DISPATCHED => {
+                let actuator = fields.actuator()?;
+                let sent = fields.bool();
+                CaptureRecord::Dispatched { actuator, data: fields.data(), sent }
+            }
+            CONTROL => CaptureRecord::Control {
+                actuator: fields.actuator()?,
+                status: fields.status()?,
+                sensor_id: fields.u64(),
+                error: fields.f64(),
+                output: fields.f64(),
+            },
+            _ => {
+                let sent = fields.bool();
+                let feedback = ActuatorFeedback {
+                    sensor_id: fields.u64(),
+                    actuator: fields.actuator()?,
+                    status: fields.status()?,
+                    control_output: fields.f64(),
+                    error: fields.f64(),
+                    timestamp: fields.u64(),
+                    stamps: fields.stamps(),
+                };
+                CaptureRecord::Feedback { feedback, sent }
+            }
+        };
+        Ok(Some(CaptureEntry { at_ns, record }))
+    }
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+    type Item = Result<CaptureEntry, CaptureError>;
+
+    /// Stops after the first error, since nothing past it can be trusted.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let entry = self.read_entry().transpose();
+        self.done = !matches!(entry, Some(Ok(_)));
+        entry
+    }
+}
+
+fn actuator_code(actuator: ActuatorType) -> u8 {
+    actuator.stage_index() as u8
+}
+
+fn status_code(status: ActuatorStatus) -> u8 {
+    match status {
+        ActuatorStatus::Normal => 0,
+        ActuatorStatus::Correcting => 1,
+        ActuatorStatus::Emergency => 2,
+        ActuatorStatus::Init => 3,
+        ActuatorStatus::Degraded => 4,
+        ActuatorStatus::SafeStop => 5,
+        ActuatorStatus::Recovering => 6,
+    }
+}
+
+fn put_data(buf: &mut Vec<u8>, data: &SensorData) {
+    buf.extend(data.id.to_le_bytes());
+    buf.extend(data.timestamp.to_le_bytes());
+    buf.extend(data.force.to_le_bytes());
+    buf.extend(data.position.to_le_bytes());
+    buf.extend(data.temperature.to_le_bytes());
+    put_stamps(buf, &data.stamps);
+}
+
+fn put_stamps(buf: &mut Vec<u8>, stamps: &StageTimestamps) {
+    for stamp in [
+        stamps.generated_ns,
+        stamps.filtered_ns,
+        stamps.sent_ns,
+        stamps.dispatched_ns,
+        stamps.actuator_received_ns,
+        stamps.control_computed_ns,
+        stamps.feedback_sent_ns,
+        stamps.feedback_consumed_ns,
+    ] {
+        buf.extend(stamp.to_le_bytes());
+    }
+}
+
+fn read_array<const N: usize>(input: &mut impl Read) -> Result<[u8; N], CaptureError> {
+    let mut bytes = [0u8; N];
+    input.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_string(input: &mut impl Read, len: usize) -> Result<String, CaptureError> {
+    let mut bytes = vec![0u8; len];
+    input.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|_| CaptureError::Invalid("header text"))
+}
+
+/// Cursor over one record's bytes, which are already known to be long enough.
+struct Fields<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Fields<'_> {
+    fn take<const N: usize>(&mut self) -> [u8; N] {
+        let bytes = self.bytes[self.pos..self.pos + N].try_into().expect("record length checked");
+        self.pos += N;
+        bytes
+    }
+
+    fn u64(&mut self) -> u64 {
+        u64::from_le_bytes(self.take())
+    }
+
+    fn f64(&mut self) -> f64 {
+        f64::from_le_bytes(self.take())
+    }
+
+    fn bool(&mut self) -> bool {
+        self.take::<1>()[0] != 0
+    }
+
+    fn actuator(&mut self) -> Result<ActuatorType, CaptureError> {
+        let code = self.take::<1>()[0] as usize;
+        ActuatorType::ALL
+            .into_iter()
+            .find(|actuator| actuator.stage_index() == code)
+            .ok_or(CaptureError::Invalid("actuator"))
+    }
+
+    fn status(&mut self) -> Result<ActuatorStatus, CaptureError> {
+        match self.take::<1>()[0] {
+            0 => Ok(ActuatorStatus::Normal),
+            1 => Ok(ActuatorStatus::Correcting),
+            2 => Ok(ActuatorStatus::Emergency),
+            3 => Ok(ActuatorStatus::Init),
+            4 => Ok(ActuatorStatus::Degraded),
+            5 => Ok(ActuatorStatus::SafeStop),
+            6 => Ok(ActuatorStatus::Recovering),
+            _ => Err(CaptureError::Invalid("actuator status")),
+        }
+    }
+
+    fn stamps(&mut self) -> StageTimestamps {
+        StageTimestamps {
+            generated_ns: self.u64(),
+            filtered_ns: self.u64(),
+            sent_ns: self.u64(),
+            dispatched_ns: self.u64(),
+            actuator_received_ns: self.u64(),
+            control_computed_ns: self.u64(),
+            feedback_sent_ns: self.u64(),
+            feedback_consumed_ns: self.u64(),
+        }
+    }
+
+    fn data(&mut self) -> SensorData {
+        SensorData {
+            id: self.u64(),
+            timestamp: self.u64(),
+            force: self.f64(),
+            position: self.f64(),
+            temperature: self.f64(),
+            stamps: self.stamps(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+
+    const BASELINE: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/../../configs/experiment_baseline.toml"));
+
+    fn stamps(base: u64) -> StageTimestamps {
+        StageTimestamps {
+            generated_ns: base,
+            filtered_ns: base + 1,
+            sent_ns: base + 2,
+            dispatched_ns: base + 3,
+            actuator_received_ns: base + 4,
+            control_computed_ns: base + 5,
+            feedback_sent_ns: base + 6,
+            feedback_consumed_ns: base + 7,
+        }
+    }
+
+    fn data(id: u64) -> SensorData {
+        SensorData {
+            id,
+            timestamp: 1_000 * id,
+            force: 50.0 + id as f64 / 3.0,
+            position: -0.125,
+            temperature: 25.5,
+            stamps: stamps(id * 10),
+        }
+    }
+
+    /// One record of every kind, with every actuator and status.
+    fn entries() -> Vec<CaptureEntry> {
+        let mut records = vec![
+            CaptureRecord::Sensor { data: data(1), raw_force: 51.75, sent: true },
+            CaptureRecord::Sensor { data: data(2), raw_force: f64::MIN_POSITIVE, sent: false },
+        ];
+        let statuses = [
+            ActuatorStatus::Normal,
+            ActuatorStatus::Correcting,
+            ActuatorStatus::Emergency,
+            ActuatorStatus::Init,
+            ActuatorStatus::Degraded,
+            ActuatorStatus::SafeStop,
+            ActuatorStatus::Recovering,
+        ];
+        for (index, status) in statuses.into_iter().enumerate() {
+            let actuator = ActuatorType::ALL[index % 3];
+            let sensor_id = index as u64;
+            records.push(CaptureRecord::Dispatched { actuator, data: data(sensor_id), sent: index % 2 == 0 });
+            records.push(CaptureRecord::Control { actuator, sensor_id, error: -1.5 * index as f64, output: 0.1, status });
+            let feedback = ActuatorFeedback {
+                sensor_id,
+                actuator,
+                status,
+                control_output: 0.1,
+                error: -1.5 * index as f64,
+                timestamp: 7_000 + sensor_id,
+                stamps: stamps(100 + sensor_id),
+            };
+            records.push(CaptureRecord::Feedback { feedback, sent: index % 3 != 0 });
+        }
+        records.into_iter().enumerate().map(|(index, record)| CaptureEntry { at_ns: 500 * index as u64, record }).collect()
+    }
+
+    fn write(config: &ExperimentConfig, entries: &[CaptureEntry]) -> Vec<u8> {
+        let mut writer = CaptureWriter::new(Vec::new(), "threaded", config).unwrap();
+        for entry in entries {
+            writer.write(entry).unwrap();
+        }
+        assert_eq!(writer.records(), entries.len() as u64);
+        writer.finish().unwrap()
+    }
+
+    #[test]
+    fn a_capture_reads_back_as_written() {
+        let config = parse_config("experiment_baseline.toml", BASELINE, false).unwrap();
+        let written = entries();
+        let bytes = write(&config, &written);
+
+        let reader = CaptureReader::new(bytes.as_slice()).unwrap();
+        let header = reader.header().clone();
+        assert_eq!((header.version, header.runtime.as_str()), (CAPTURE_VERSION, "threaded"));
+        assert_eq!(header.config_toml, toml::to_string(&config).unwrap());
+        // The embedded config parses back to the same config
+        assert_eq!(toml::to_string(&header.config().unwrap()).unwrap(), header.config_toml);
+
+        let read: Vec<CaptureEntry> = reader.collect::<Result<_, _>>().unwrap();
+        // Debug output prints every field, floats to the last bit
+        assert_eq!(format!("{:?}", read), format!("{:?}", written));
+    }
+
+    #[test]
+    fn unreadable_captures_say_why() {
+        let config = parse_config("experiment_baseline.toml", BASELINE, false).unwrap();
+        let bytes = write(&config, &entries());
+
+        let mut older = bytes.clone();
+        older[8..10].copy_from_slice(&(CAPTURE_VERSION - 1).to_le_bytes());
+        let err = CaptureReader::new(older.as_slice()).err().expect("older version rejected");
+        assert!(matches!(err, CaptureError::UnsupportedVersion(version) if version == CAPTURE_VERSION - 1));
+        assert!(err.to_string().contains(&format!("reads v{} only", CAPTURE_VERSION)));
+
+        assert!(matches!(CaptureReader::new(&b"not a capture"[..]), Err(CaptureError::NotACapture)));
+
+        // A run killed partway through a record leaves every earlier record readable
+        let results: Vec<_> = CaptureReader::new(&bytes[..bytes.len() - 3]).unwrap().collect();
+        assert_eq!(results.len(), entries().len());
+        assert!(matches!(results.last(), Some(Err(CaptureError::Truncated))));
+    }
+}
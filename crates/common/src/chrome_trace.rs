@@ -0,0 +1,332 @@
+//! Chrome Trace Event export, for opening a run in Perfetto or chrome://tracing.
+//!
+//! Each task gets its own track: the sensor (which also consumes feedback), the
+//! dispatcher and one per actuator. Samples are drawn from the recorder's end-to-end
+//! traces, with a flow arrow from each sensor slice to the actuator slices that
+//! handled it. A sample dropped at a full queue is drawn as far as it got, ending in
+//! a "DROPPED" instant on the task whose hand-off failed.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use crate::config::ExperimentConfig;
+use crate::metrics::CycleResult;
+use crate::trace::{TraceDrop, TraceRecord};
+use crate::ActuatorType;
+
+const PID: u32 = 1;
+const SENSOR_TID: u32 = 1;
+const DISPATCHER_TID: u32 = 2;
+
+fn actuator_tid(actuator: ActuatorType) -> u32 {
+    10 + actuator.stage_index() as u32
+}
+
+/// One entry of the `traceEvents` array. Times are in μs, as the format expects.
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceEvent {
+    pub name: String,
+    pub cat: &'static str,
+    pub ph: &'static str,
+    pub ts: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dur: Option<f64>,
+    pub pid: u32,
+    pub tid: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub s: Option<&'static str>, // Instant scope
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bp: Option<&'static str>, // Flow binding point
+    #[serde(skip_serializing_if = "Value::is_null")]
+    pub args: Value,
+}
+
+impl TraceEvent {
+    fn new(name: impl Into<String>, cat: &'static str, ph: &'static str, tid: u32, ts_ns: u64) -> Self {
+        Self {
+            name: name.into(),
+            cat,
+            ph,
+            ts: us(ts_ns),
+            dur: None,
+            pid: PID,
+            tid,
+            id: None,
+            s: None,
+            bp: None,
+            args: Value::Null,
+        }
+    }
+
+    fn slice(name: impl Into<String>, cat: &'static str, tid: u32, start_ns: u64, end_ns: u64) -> Self {
+        Self {
+            dur: Some(us(end_ns.saturating_sub(start_ns))),
+            ..Self::new(name, cat, "X", tid, start_ns)
+        }
+    }
+
+    fn instant(name: impl Into<String>, cat: &'static str, tid: u32, ts_ns: u64) -> Self {
+        Self {
+            s: Some("t"),
+            ..Self::new(name, cat, "i", tid, ts_ns)
+        }
+    }
+
+    fn metadata(name: &'static str, tid: u32, args: Value) -> Self {
+        Self {
+            args,
+            ..Self::new(name, "__metadata", "M", tid, 0)
+        }
+    }
+
+    fn with_args(mut self, args: Value) -> Self {
+        self.args = args;
+        self
+    }
+}
+
+fn us(ns: u64) -> f64 {
+    ns as f64 / 1000.0
+}
+
+/// A complete trace file: `{"traceEvents": [...], "displayTimeUnit": "ns"}`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChromeTrace {
+    pub trace_events: Vec<TraceEvent>,
+    pub display_time_unit: &'static str,
+}
+
+impl ChromeTrace {
+    /// Builds the trace for one run. `results` supplies the deadline verdicts and
+    /// lateness that annotate each slice; `config` supplies the deadlines themselves.
+    pub fn from_run(process_name: &str, config: &ExperimentConfig, traces: &[TraceRecord], results: &[CycleResult]) -> Self {
+        let verdicts: HashMap<(u64, Option<ActuatorType>), &CycleResult> = results
+            .iter()
+            .map(|result| ((result.cycle_id, result.actuator), result))
+            .collect();
+        // Sensor budget: processing plus transmission, measured from release
+        let sensor_deadline_ns = (config.sensor.processing_deadline() + config.sensor.transmission_deadline()).as_nanos() as u64;
+
+        let mut events = vec![TraceEvent::metadata("process_name", 0, json!({ "name": process_name }))];
+        let mut tracks = vec![(SENSOR_TID, "sensor".to_string()), (DISPATCHER_TID, "dispatcher".to_string())];
+        tracks.extend(ActuatorType::ALL.map(|actuator| (actuator_tid(actuator), format!("actuator {:?}", actuator))));
+        for (sort_index, (tid, name)) in tracks.into_iter().enumerate() {
+            events.push(TraceEvent::metadata("thread_name", tid, json!({ "name": name })));
+            events.push(TraceEvent::metadata("thread_sort_index", tid, json!({ "sort_index": sort_index })));
+        }
+
+        let mut sampled = std::collections::HashSet::new();
+        for trace in traces {
+            let id = trace.trace_id;
+
+            // Sensor and dispatcher events are shared by every actuator's trace of a sample
+            if sampled.insert(id) {
+                let verdict = verdicts.get(&(id, None));
+                let missed = verdict.is_some_and(|result| !result.deadline_met);
+                events.push(
+                    TraceEvent::slice(slice_name("sample", id, missed), slice_cat(missed), SENSOR_TID, trace.generated_ns, trace.sent_ns)
+                        .with_args(json!({
+                            "filter_ns": trace.filtered_ns.saturating_sub(trace.generated_ns),
+                            "deadline_met": verdict.map(|result| result.deadline_met),
+                            "lateness_ns": verdict.map(|result| result.lateness_ns),
+                            "release_jitter_ns": verdict.and_then(|result| result.release_jitter_ns),
+                        })),
+                );
+                events.push(TraceEvent::instant(format!("deadline #{}", id), "deadline", SENSOR_TID,
+                    trace.generated_ns + sensor_deadline_ns));
+                if missed {
+                    events.push(miss_marker(id, SENSOR_TID, trace.generated_ns + sensor_deadline_ns, verdict));
+                }
+                if trace.dropped == Some(TraceDrop::Send) {
+                    events.push(drop_marker(trace, SENSOR_TID));
+                } else {
+                    events.push(TraceEvent::instant(format!("dispatch #{}", id), "dispatch", DISPATCHER_TID, trace.dispatched_ns)
+                        .with_args(json!({ "queue_wait_ns": trace.dispatched_ns.saturating_sub(trace.sent_ns) })));
+                }
+            }
+
+            match trace.dropped {
+                Some(TraceDrop::Send) => continue,
+                Some(TraceDrop::Dispatch) => {
+                    events.push(drop_marker(trace, DISPATCHER_TID));
+                    continue;
+                }
+                _ => {}
+            }
+
+            let tid = actuator_tid(trace.actuator);
+            let verdict = verdicts.get(&(id, Some(trace.actuator)));
+            let missed = verdict.is_some_and(|result| !result.deadline_met);
+            let deadline_ns = trace.actuator_received_ns + config.actuators.get(trace.actuator).deadline().as_nanos() as u64;
+            events.push(
+                TraceEvent::slice(slice_name("control", id, missed), slice_cat(missed), tid, trace.actuator_received_ns, trace.feedback_sent_ns)
+                    .with_args(json!({
+                        "queue_wait_ns": trace.actuator_received_ns.saturating_sub(trace.dispatched_ns),
+                        "compute_ns": trace.control_computed_ns.saturating_sub(trace.actuator_received_ns),
+                        "sensor_to_actuation_ns": trace.sensor_to_actuation_ns,
+                        "deadline_met": verdict.map(|result| result.deadline_met),
+                        "lateness_ns": verdict.map(|result| result.lateness_ns),
+                    })),
+            );
+            events.push(TraceEvent::instant(format!("deadline #{}", id), "deadline", tid, deadline_ns));
+            if missed {
+                events.push(miss_marker(id, tid, deadline_ns, verdict));
+            }
+
+            // Flow arrow from the sensor slice to this actuator's slice
+            let flow_id = id * 4 + trace.actuator.stage_index() as u64;
+            events.push(TraceEvent { id: Some(flow_id), ..TraceEvent::new("sample", "flow", "s", SENSOR_TID, trace.generated_ns) });
+            events.push(TraceEvent {
+                id: Some(flow_id),
+                bp: Some("e"),
+                ..TraceEvent::new("sample", "flow", "f", tid, trace.actuator_received_ns)
+            });
+
+            if trace.dropped == Some(TraceDrop::Feedback) {
+                events.push(drop_marker(trace, tid));
+            } else {
+                // The sensor task consumes feedback, so it lands on the sensor's track
+                events.push(TraceEvent::instant(format!("feedback #{} {:?}", id, trace.actuator), "feedback", SENSOR_TID,
+                    trace.feedback_consumed_ns)
+                    .with_args(json!({ "round_trip_ns": trace.round_trip_ns })));
+            }
+        }
+
+        Self {
+            trace_events: events,
+            display_time_unit: "ns",
+        }
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn std::error::Error>> {
+        let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+        serde_json::to_writer(file, self)?;
+        Ok(())
+    }
+}
+
+fn slice_name(stage: &str, id: u64, missed: bool) -> String {
+    if missed {
+        format!("{} #{} (missed)", stage, id)
+    } else {
+        format!("{} #{}", stage, id)
+    }
+}
+
+fn slice_cat(missed: bool) -> &'static str {
+    if missed { "deadline_miss" } else { "cycle" }
+}
+
+fn drop_marker(trace: &TraceRecord, tid: u32) -> TraceEvent {
+    TraceEvent::instant(format!("DROPPED #{}", trace.trace_id), "dropped", tid, trace.dropped_ns().unwrap_or_default())
+        .with_args(json!({ "at": trace.dropped, "actuator": trace.actuator }))
+}
+
+fn miss_marker(id: u64, tid: u32, deadline_ns: u64, verdict: Option<&&CycleResult>) -> TraceEvent {
+    TraceEvent::instant(format!("MISS #{}", id), "deadline_miss", tid, deadline_ns)
+        .with_args(json!({ "lateness_ns": verdict.map(|result| result.lateness_ns) }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+    use crate::StageTimestamps;
+
+    const MINIMAL: &str = r#"
+experiment_name = "test"
+duration_secs = 1
+sensor_period_ms = 10
+cpu_load_threads = 0
+mode = "baseline"
+processing_time_ns = 1000
+enable_logging = false
+"#;
+
+    fn stamps(stages: usize) -> StageTimestamps {
+        let mut at = [0u64; 8];
+        for (stage, stamp) in at.iter_mut().enumerate().take(stages) {
+            *stamp = 1_000 * (stage as u64 + 1);
+        }
+        StageTimestamps {
+            generated_ns: at[0],
+            filtered_ns: at[1],
+            sent_ns: at[2],
+            dispatched_ns: at[3],
+            actuator_received_ns: at[4],
+            control_computed_ns: at[5],
+            feedback_sent_ns: at[6],
+            feedback_consumed_ns: at[7],
+        }
+    }
+
+    fn sensor_miss(cycle_id: u64) -> CycleResult {
+        CycleResult {
+            cycle_id,
+            mode: "baseline".to_string(),
+            actuator: None,
+            total_latency_ns: 3_000,
+            processing_time_ns: 2_000,
+            lock_wait_ns: 0,
+            deadline_met: false,
+            lateness_ns: 500,
+            release_jitter_ns: Some(0),
+            control_ns: None,
+        }
+    }
+
+    fn named<'a>(trace: &'a ChromeTrace, prefix: &str) -> Vec<&'a TraceEvent> {
+        trace.trace_events.iter().filter(|event| event.name.starts_with(prefix)).collect()
+    }
+
+    #[test]
+    fn dropped_samples_end_in_a_drop_marker_on_the_task_that_dropped_them() {
+        let config = parse_config("test.toml", MINIMAL, false).unwrap();
+        let traces = [
+            TraceRecord::new(1, ActuatorType::Motor, stamps(8)),
+            TraceRecord::dropped(2, ActuatorType::Motor, stamps(4), TraceDrop::Dispatch),
+            TraceRecord::dropped(3, ActuatorType::Gripper, stamps(7), TraceDrop::Feedback),
+            TraceRecord::dropped(4, ActuatorType::Gripper, stamps(3), TraceDrop::Send),
+            TraceRecord::dropped(4, ActuatorType::Motor, stamps(3), TraceDrop::Send),
+        ];
+        let trace = ChromeTrace::from_run("test", &config, &traces, &[sensor_miss(4)]);
+
+        let drops: Vec<(String, u32, f64)> = named(&trace, "DROPPED")
+            .into_iter()
+            .map(|event| (event.name.clone(), event.tid, event.ts))
+            .collect();
+        assert_eq!(drops, vec![
+            ("DROPPED #2".to_string(), DISPATCHER_TID, 4.0),
+            ("DROPPED #3".to_string(), actuator_tid(ActuatorType::Gripper), 7.0),
+            ("DROPPED #4".to_string(), SENSOR_TID, 3.0),
+        ]);
+        // The sample dropped before dispatch still shows its deadline miss
+        assert_eq!(named(&trace, "MISS").iter().map(|event| event.name.as_str()).collect::<Vec<_>>(), vec!["MISS #4"]);
+        assert_eq!(named(&trace, "sample #4 (missed)").len(), 1);
+        assert_eq!(named(&trace, "control").len(), 2); // Samples 1 and 3 reached an actuator
+        assert_eq!(named(&trace, "feedback").len(), 1);
+    }
+
+    #[test]
+    fn every_track_is_a_pipeline_task() {
+        let config = parse_config("test.toml", MINIMAL, false).unwrap();
+        let trace = ChromeTrace::from_run("test", &config, &[TraceRecord::new(1, ActuatorType::Stabilizer, stamps(8))], &[]);
+
+        let mut tracks: Vec<String> = trace
+            .trace_events
+            .iter()
+            .filter(|event| event.name == "thread_name")
+            .map(|event| event.args["name"].as_str().unwrap().to_string())
+            .collect();
+        tracks.sort();
+        assert_eq!(tracks, ["actuator Gripper", "actuator Motor", "actuator Stabilizer", "dispatcher", "sensor"]);
+        let feedback = named(&trace, "feedback #1");
+        assert_eq!((feedback.len(), feedback[0].tid), (1, SENSOR_TID));
+    }
+}
@@ -0,0 +1,65 @@
+//! Run-relative time source. Every timestamp a pipeline stage or the recorder takes
+//! goes through a [`Clock`], so the same code can run on wall time or on the virtual
+//! time of the deterministic simulation.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+pub trait Clock: Send + Sync {
+    /// Nanoseconds since the start of the run.
+    fn now_ns(&self) -> u64;
+
+    fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.now_ns())
+    }
+}
+
+/// Clock shared by every component of one run.
+pub type SharedClock = Arc<dyn Clock>;
+
+/// Wall time from a monotonic `Instant`, used by the threaded and async runtimes.
+#[derive(Debug, Clone, Copy)]
+pub struct MonotonicClock {
+    epoch: Instant,
+}
+
+impl MonotonicClock {
+    /// Starts counting from now.
+    pub fn start() -> Self {
+        Self { epoch: Instant::now() }
+    }
+}
+
+impl Clock for MonotonicClock {
+    fn now_ns(&self) -> u64 {
+        self.epoch.elapsed().as_nanos() as u64
+    }
+}
+
+/// Time that only moves when the simulation advances it.
+#[derive(Debug, Default)]
+pub struct VirtualClock {
+    now_ns: AtomicU64,
+}
+
+impl VirtualClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Moves time forward to `ns`. Time never runs backwards, so an earlier `ns` is ignored.
+    pub fn advance_to(&self, ns: u64) {
+        self.now_ns.fetch_max(ns, Ordering::AcqRel);
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.now_ns.fetch_add(by.as_nanos() as u64, Ordering::AcqRel);
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now_ns(&self) -> u64 {
+        self.now_ns.load(Ordering::Acquire)
+    }
+}
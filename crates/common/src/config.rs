@@ -1,38 +1,924 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::fs;
+use toml::{Table, Value};
+use std::time::Duration;
+use crate::ActuatorType;
+use crate::config_validation::{locate_issues, unknown_field_issues};
+pub use crate::config_validation::{ConfigError, ConfigIssue, KNOWN_MODES};
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 pub struct ExperimentConfig {
     pub experiment_name: String,
     pub duration_secs: u64,
     pub sensor_period_ms: u64,  // Changed to ms for clarity
     pub cpu_load_threads: usize,
     pub mode: String,
-    pub processing_time_ns: u64, // NEW: Configurable busy-wait time
+    pub processing_time_ns: u64, // Mean synthetic busy-wait per stage, shaped by [workload]
     pub enable_logging: bool, // Controls println! statements for benchmark safety
+    #[serde(default)]
+    pub cpu_load: CpuLoadConfig, // Shape of the cpu_load_threads background workers
+    #[serde(default)]
+    pub workload: WorkloadConfig,
+    #[serde(default)]
+    pub shared_resource: SharedResourceConfig, // Only used when mode = "contention"
+    #[serde(default)]
+    pub sensor: SensorConfig,
+    #[serde(default)]
+    pub actuators: ActuatorsConfig,
+    #[serde(default)]
+    pub shutdown: ShutdownConfig,
+    #[serde(default)]
+    pub recording: RecordingConfig,
+    #[serde(default)]
+    pub simulation: SimulationConfig, // Only used by the sim runtime
+    #[serde(default)]
+    pub sensor_model: SensorModelConfig,
+    #[serde(default)]
+    pub replay: ReplayConfig, // Replaces sensor_model when a path is set
+    #[serde(default)]
+    pub capture: CaptureConfig,
+    #[serde(default)]
+    pub plants: PlantsConfig, // Closes the loop from actuator outputs back to the sensor
+    #[serde(default)]
+    pub estop: EStopConfig, // Stops every actuator when one enters Emergency
 }
 
-pub fn load_config(path: &str) -> Result<ExperimentConfig, Box<dyn std::error::Error>> {
-    let content = fs::read_to_string(path)?;
-    let config: ExperimentConfig = toml::from_str(&content)?;
-    Ok(config)
+/// Reads, parses and validates an experiment config. Every invalid setting is reported
+/// with its field path and TOML line. Unknown keys are ignored unless loaded strictly
+/// through `load_config_with_overlays`.
+pub fn load_config(path: &str) -> Result<ExperimentConfig, ConfigError> {
+    load_config_with_overlays(path, &[], false)
 }
 
-#[derive(Debug, Deserialize)]
+/// Loads `path` with each overlay merged over it in turn, such as the gains written by
+/// `pid::tuning`. Overlays may set any subset of fields. With `strict`, keys that no
+/// setting reads are rejected, in the overlays as well as the base config.
+pub fn load_config_with_overlays(path: &str, overlays: &[String], strict: bool) -> Result<ExperimentConfig, ConfigError> {
+    if overlays.is_empty() {
+        let content = fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_string(),
+            source,
+        })?;
+        return parse_config(path, &content, strict);
+    }
+    let mut table = load_table(path)?;
+    for overlay in overlays {
+        merge_tables(&mut table, &load_table(overlay)?);
+    }
+
+    let config: ExperimentConfig = Value::Table(table.clone()).try_into().map_err(|err: toml::de::Error| ConfigError::Parse {
+        path: format!("{} + {}", path, overlays.join(" + ")),
+        line: None,
+        message: err.message().to_string(),
+    })?;
+    let mut issues = config.validation_issues();
+    if strict {
+        issues.extend(unknown_field_issues(&table, &config));
+    }
+    if issues.is_empty() {
+        Ok(config)
+    } else {
+        Err(ConfigError::Invalid { path: format!("{} + {}", path, overlays.join(" + ")), issues })
+    }
+}
+
+/// A TOML file as an untyped table, for merging before it is read as a config.
+pub fn load_table(path: &str) -> Result<Table, ConfigError> {
+    let content = fs::read_to_string(path).map_err(|source| ConfigError::Io { path: path.to_string(), source })?;
+    toml::from_str(&content).map_err(|err| ConfigError::from_toml(path, &content, err))
+}
+
+/// Recursively overlays `overlay` onto `base`, replacing leaves and merging sub-tables.
+pub(crate) fn merge_tables(base: &mut Table, overlay: &Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(key), value) {
+            (Some(Value::Table(existing)), Value::Table(inner)) => merge_tables(existing, inner),
+            _ => {
+                base.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+/// Parses and validates config TOML read from `path`; `strict` as for `load_config_with_overlays`.
+pub fn parse_config(path: &str, content: &str, strict: bool) -> Result<ExperimentConfig, ConfigError> {
+    let config: ExperimentConfig =
+        toml::from_str(content).map_err(|err| ConfigError::from_toml(path, content, err))?;
+
+    let mut issues = config.validation_issues();
+    if strict {
+        let table: Table = toml::from_str(content).map_err(|err| ConfigError::from_toml(path, content, err))?;
+        issues.extend(unknown_field_issues(&table, &config));
+    }
+    if issues.is_empty() {
+        Ok(config)
+    } else {
+        locate_issues(content, &mut issues);
+        Err(ConfigError::Invalid { path: path.to_string(), issues })
+    }
+}
+
+/// Kind of background work a load generator thread performs.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadWorkerKind {
+    /// Tight arithmetic loop that keeps one core fully busy
+    Spin,
+    /// Strided writes over a buffer larger than the LLC to evict the pipeline's working set
+    CacheThrash,
+    /// Cheap syscalls in a loop to stress the scheduler and kernel entry paths
+    Syscall,
+}
+
+/// Background load applied for the lifetime of a run.
+/// Worker kinds are assigned round-robin across `cpu_load_threads` threads.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
 pub struct CpuLoadConfig {
-    pub enabled: bool,
-    pub threads: usize,
+    pub workers: Vec<LoadWorkerKind>,
+    pub cache_buffer_kb: usize,
+    pub duty_cycle_percent: u8, // Busy share of every 10ms window, 100 = never sleep
+}
+
+impl Default for CpuLoadConfig {
+    fn default() -> Self {
+        Self {
+            workers: vec![LoadWorkerKind::Spin],
+            cache_buffer_kb: 8 * 1024,
+            duty_cycle_percent: 100,
+        }
+    }
 }
 
-#[derive(Debug, Deserialize)]
+/// Distribution the per-cycle synthetic workload is drawn from.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkloadDistribution {
+    /// Always exactly `processing_time_ns`
+    #[default]
+    Fixed,
+    /// Uniform over `processing_time_ns ± spread_ns`
+    Uniform,
+    /// Normal with mean `processing_time_ns` and standard deviation `spread_ns`
+    Normal,
+    /// Exponential with mean `processing_time_ns`
+    Exponential,
+    /// `processing_time_ns` most of the time, `tail_ns` with probability `tail_probability`
+    Bimodal,
+}
+
+/// Synthetic computation each sensor filter and actuator stage performs per cycle.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct WorkloadConfig {
+    pub distribution: WorkloadDistribution,
+    pub spread_ns: u64,
+    pub tail_ns: u64,
+    pub tail_probability: f64,
+    pub sensor_scale: f64,   // Multiplier on processing_time_ns for the sensor filter stage
+    pub actuator_scale: f64, // Multiplier on processing_time_ns for each actuator stage
+    pub seed: u64,
+}
+
+impl Default for WorkloadConfig {
+    fn default() -> Self {
+        Self {
+            distribution: WorkloadDistribution::Fixed,
+            spread_ns: 0,
+            tail_ns: 0,
+            tail_probability: 0.0,
+            sensor_scale: 1.0,
+            actuator_scale: 1.0,
+            seed: 0,
+        }
+    }
+}
+
+/// Lock guarding the shared state table in contention mode.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LockKind {
+    #[default]
+    Mutex,
+    RwLock,
+    /// std Mutex acquired by spinning on try_lock instead of parking
+    Spin,
+}
+
+/// Shared state table that the sensor and every actuator access each cycle.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
 pub struct SharedResourceConfig {
-    pub high_contention: bool,
+    pub high_contention: bool, // true = one lock for the whole table, false = one lock per stage
+    pub lock: LockKind,
+    pub critical_section_ns: u64, // Busy-wait while holding the lock
+    pub accesses_per_cycle: u32,
+    pub read_ratio: f64, // Fraction of actuator accesses that only read the sensor's entry
+    pub table_size: usize,
+}
+
+impl Default for SharedResourceConfig {
+    fn default() -> Self {
+        Self {
+            high_contention: true,
+            lock: LockKind::Mutex,
+            critical_section_ns: 20_000,
+            accesses_per_cycle: 1,
+            read_ratio: 0.0,
+            table_size: 64,
+        }
+    }
+}
+
+/// Sensor stage deadlines and the capacities of the channels it owns.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct SensorConfig {
+    pub processing_deadline_us: u64,
+    pub transmission_deadline_us: u64,
+    pub channel_capacity: usize,          // Sensor -> dispatcher
+    pub feedback_channel_capacity: usize, // Actuators -> sensor
+}
+
+impl Default for SensorConfig {
+    fn default() -> Self {
+        Self {
+            processing_deadline_us: 200,
+            transmission_deadline_us: 100,
+            channel_capacity: 100,
+            feedback_channel_capacity: 100,
+        }
+    }
+}
+
+impl SensorConfig {
+    pub fn processing_deadline(&self) -> Duration {
+        Duration::from_micros(self.processing_deadline_us)
+    }
+
+    pub fn transmission_deadline(&self) -> Duration {
+        Duration::from_micros(self.transmission_deadline_us)
+    }
+}
+
+/// How long a run may take to wind down once its duration is up.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct ShutdownConfig {
+    pub deadline_ms: u64, // Components still running after this are reported and abandoned
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self { deadline_ms: 1_000 }
+    }
+}
+
+impl ShutdownConfig {
+    pub fn deadline(&self) -> Duration {
+        Duration::from_millis(self.deadline_ms)
+    }
+}
+
+/// `[recording]`: how much per-cycle detail a run keeps in memory. Summaries and
+/// percentiles always cover every cycle; only the retained rows are capped.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct RecordingConfig {
+    pub max_results: usize, // Most recent CycleResults kept for the per-cycle CSV and trace export
+    pub max_traces: usize,  // Most recent end-to-end TraceRecords kept, likewise
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        Self { max_results: 100_000, max_traces: 100_000 }
+    }
+}
+
+/// `[estop]`: the emergency stop one actuator's Emergency broadcasts to all of them.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct EStopConfig {
+    pub enabled: bool,
+    pub reaction_deadline_us: u64, // Trigger to each actuator holding its safe output
+    pub auto_acknowledge_ms: Option<u64>, // Acknowledge a stop after this long; off by default
+    pub acknowledge_at_secs: Vec<f64>,    // Operator acknowledgements, in nominal sample time
+}
+
+impl Default for EStopConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            reaction_deadline_us: 1_000,
+            auto_acknowledge_ms: None,
+            acknowledge_at_secs: Vec::new(),
+        }
+    }
+}
+
+impl EStopConfig {
+    pub fn reaction_deadline(&self) -> Duration {
+        Duration::from_micros(self.reaction_deadline_us)
+    }
+}
+
+/// Base waveform of one sensor input channel, as a function of nominal sample time.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(tag = "model", rename_all = "snake_case")]
+pub enum SignalConfig {
+    Constant { value: f64 },
+    Sine {
+        offset: f64,
+        amplitude: f64,
+        frequency_hz: f64,
+        #[serde(default)]
+        phase_deg: f64,
+    },
+    /// `from` until `at_secs`, then `to`
+    Step { from: f64, to: f64, at_secs: f64 },
+    /// `from` until `at_secs`, then changing by `slope_per_sec`
+    Ramp {
+        from: f64,
+        slope_per_sec: f64,
+        #[serde(default)]
+        at_secs: f64,
+    },
+    /// Sine whose frequency sweeps linearly from `start_hz` to `end_hz` every `sweep_secs`
+    Chirp {
+        offset: f64,
+        amplitude: f64,
+        start_hz: f64,
+        end_hz: f64,
+        sweep_secs: f64,
+    },
+    /// Starts at `start` and moves by a normal step with `step_std_dev` every sample
+    RandomWalk { start: f64, step_std_dev: f64 },
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NoiseKind {
+    Gaussian,
+    /// 1/f noise, so neighbouring samples are correlated
+    Pink,
+}
+
+/// Additive noise on top of a channel's signal.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct NoiseConfig {
+    pub kind: NoiseKind,
+    pub std_dev: f64,
+}
+
+/// Occasional single-sample outliers of `±magnitude`.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct SpikeConfig {
+    pub probability: f64, // Per sample
+    pub magnitude: f64,
+}
+
+/// Occasional runs of samples where the sensor delivers no fresh reading.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct DropoutConfig {
+    pub probability: f64, // Per sample, of a dropout starting
+    pub length_cycles: u32,
+    pub value: Option<f64>, // Reported during a dropout; unset holds the last good reading
+}
+
+/// One sensor input channel: a signal plus optional noise, spikes and dropouts,
+/// applied in that order.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ChannelConfig {
+    pub signal: SignalConfig,
+    pub noise: Option<NoiseConfig>,
+    pub spikes: Option<SpikeConfig>,
+    pub dropouts: Option<DropoutConfig>,
+}
+
+impl ChannelConfig {
+    pub fn constant(value: f64) -> Self {
+        Self { signal: SignalConfig::Constant { value }, noise: None, spikes: None, dropouts: None }
+    }
+}
+
+/// What the sensor measures. Every implementation reads its inputs from these models,
+/// seeded from `workload.seed`, so all of them see the same samples.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct SensorModelConfig {
+    pub force: ChannelConfig,
+    pub position: ChannelConfig,
+    pub temperature: ChannelConfig,
+}
+
+impl Default for SensorModelConfig {
+    fn default() -> Self {
+        Self {
+            force: ChannelConfig {
+                signal: SignalConfig::Sine { offset: 50.0, amplitude: 10.0, frequency_hz: 1.5, phase_deg: 0.0 },
+                noise: None,
+                spikes: None,
+                dropouts: None,
+            },
+            position: ChannelConfig::constant(0.0),
+            temperature: ChannelConfig::constant(25.0),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayFormat {
+    /// Header row with `timestamp`, `force`, `position` and `temperature` columns
+    Csv,
+    /// One JSON object per line with the same fields, e.g. a serialized `SensorData`
+    Jsonl,
+    /// Raw sensor readings of a run capture (see [`crate::capture`])
+    Capture,
+}
+
+impl ReplayFormat {
+    /// Format implied by a file extension (`.csv`, `.jsonl`, `.ndjson` or `.cap`).
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = std::path::Path::new(path).extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "csv" => Some(ReplayFormat::Csv),
+            "jsonl" | "ndjson" => Some(ReplayFormat::Jsonl),
+            "cap" => Some(ReplayFormat::Capture),
+            _ => None,
+        }
+    }
+}
+
+/// When a replayed sample is released into the pipeline.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayPacing {
+    /// At the recorded spacing
+    #[default]
+    RealTime,
+    /// At the recorded spacing divided by `speed`
+    Accelerated,
+    /// Back to back, as fast as the sensor stage can send
+    AsFastAsPossible,
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayEnd {
+    /// End the run after the last sample
+    #[default]
+    Stop,
+    /// Start over from the first sample
+    Loop,
+}
+
+/// Recorded sensor trace fed through the pipeline instead of the sensor models.
+/// `duration_secs` still bounds the run.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct ReplayConfig {
+    pub path: Option<String>,
+    pub format: Option<ReplayFormat>, // Unset: inferred from the file extension
+    pub pacing: ReplayPacing,
+    pub speed: f64, // Accelerated pacing only
+    pub at_end: ReplayEnd,
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            format: None,
+            pacing: ReplayPacing::RealTime,
+            speed: 1.0,
+            at_end: ReplayEnd::Stop,
+        }
+    }
+}
+
+impl ReplayConfig {
+    pub fn format(&self) -> Option<ReplayFormat> {
+        self.format.or_else(|| self.path.as_deref().and_then(ReplayFormat::from_path))
+    }
+}
+
+/// Binary capture of everything a run's pipeline saw and did, written while it runs.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct CaptureConfig {
+    pub path: Option<String>, // `{runtime}` is replaced by the runtime's name
+}
+
+impl CaptureConfig {
+    /// Where `runtime` writes its capture, if capturing is on.
+    pub fn path_for(&self, runtime: &str) -> Option<String> {
+        self.path.as_ref().map(|path| path.replace("{runtime}", runtime))
+    }
+}
+
+/// Sensor input channel a plant drives and its actuator measures.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SensorChannel {
+    Force,
+    Position,
+    Temperature,
+}
+
+/// Dynamics of a simulated plant. The actuator's control output is the input `u`.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(tag = "model", rename_all = "snake_case")]
+pub enum PlantDynamics {
+    /// `τ·y' = gain·u − y`
+    FirstOrderLag { gain: f64, time_constant_secs: f64 },
+    /// `m·x'' + c·x' + k·x = u`, measuring `x`
+    MassSpringDamper { mass_kg: f64, stiffness: f64, damping: f64 },
+    /// Armature `L·i' = u − R·i − kt·ω` driving `J·ω' = kt·i − b·ω`, measuring shaft angle
+    DcMotor { resistance_ohm: f64, inductance_h: f64, torque_constant: f64, inertia: f64, friction: f64 },
+    /// `C·T' = heater_gain·u − (T − ambient)/R`, measuring `T`
+    Thermal { heat_capacity: f64, thermal_resistance: f64, ambient: f64, heater_gain: f64 },
+}
+
+/// One actuator's plant.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct PlantConfig {
+    pub dynamics: PlantDynamics,
+    pub channel: SensorChannel, // Replaces the sensor model's (or replay's) value for this channel
+    #[serde(default)]
+    pub initial: f64, // Measured output at the start of the run; the loop regulates it to the setpoint
+}
+
+/// `[plants.<name>]` tables. An actuator without a plant leaves the loop open and
+/// measures position, as before.
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+#[serde(default)]
+pub struct PlantsConfig {
+    pub gripper: Option<PlantConfig>,
+    pub motor: Option<PlantConfig>,
+    pub stabilizer: Option<PlantConfig>,
+}
+
+impl PlantsConfig {
+    pub fn get(&self, actuator_type: ActuatorType) -> Option<&PlantConfig> {
+        match actuator_type {
+            ActuatorType::Gripper => self.gripper.as_ref(),
+            ActuatorType::Motor => self.motor.as_ref(),
+            ActuatorType::Stabilizer => self.stabilizer.as_ref(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        ActuatorType::ALL.iter().all(|&actuator_type| self.get(actuator_type).is_none())
+    }
+
+    /// The sensor channel `actuator_type` computes its error from.
+    pub fn measured_channel(&self, actuator_type: ActuatorType) -> SensorChannel {
+        self.get(actuator_type).map_or(SensorChannel::Position, |plant| plant.channel)
+    }
+}
+
+/// Cost model of the deterministic simulation runtime. Stage execution costs are drawn
+/// from `[workload]`; these cover the time the real runtimes spend in the OS.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct SimulationConfig {
+    pub seed: Option<u64>,        // Replaces workload.seed, so one number reproduces the whole run
+    pub release_jitter_ns: u64,   // Mean of the exponential delay between a release and the sensor waking
+    pub channel_cost_ns: u64,     // One channel send, charged to the sender
+    pub dispatch_cost_ns: u64,    // Dispatcher time per sample, including its three sends
+    pub control_cost_ns: u64,     // One controller update, charged to the actuator on top of its workload
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            seed: None,
+            release_jitter_ns: 20_000,
+            channel_cost_ns: 5_000,
+            dispatch_cost_ns: 10_000,
+            control_cost_ns: 1_000,
+        }
+    }
+}
+
+impl SimulationConfig {
+    /// Seed for this run: `seed` if set, otherwise the workload's.
+    pub fn effective_seed(&self, workload: &WorkloadConfig) -> u64 {
+        self.seed.unwrap_or(workload.seed)
+    }
+}
+
+/// Everything that differs between the Gripper, Motor and Stabilizer stages.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct ActuatorConfig {
+    pub deadline_us: u64,
+    pub feedback_deadline_us: u64,
+    pub channel_capacity: usize, // Dispatcher -> actuator
+    pub kp: f64,
+    pub ki: f64,
+    pub kd: f64,
+    pub correcting_threshold: f64, // |error| above this -> Correcting (adapts at runtime)
+    pub emergency_threshold: f64,  // |error| above this -> Emergency, latched until acknowledged
+    pub setpoint: SignalConfig,    // Over nominal sample time; random_walk is not allowed
+    pub controller: ControllerConfig,
+    pub pid: PidConfig,
+    pub status: StatusConfig,
+}
+
+/// Control law an actuator runs, chosen by `controller = { kind = "..." }`. Every
+/// kind tracks the actuator's `setpoint`.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ControllerConfig {
+    /// `kp`/`ki`/`kd` with the `[pid]` table
+    Pid,
+    /// `high` while the error exceeds `hysteresis`, `low` once it falls below −`hysteresis`
+    BangBang {
+        high: f64,
+        low: f64,
+        #[serde(default)]
+        hysteresis: f64,
+    },
+    /// `gain·(lead_secs·s + 1)/(lag_secs·s + 1)` on the error, discretized by Tustin's method
+    LeadLag { gain: f64, lead_secs: f64, lag_secs: f64 },
+    /// `u = u_ss − K·(x̂ − x_ss)` on the plant's linear model, with `K` given or solved
+    /// by LQR from `state_weights` (default: the measured output) and `input_weight`.
+    /// States come from a steady-state observer. Needs `[plants.<name>]`.
+    StateFeedback {
+        #[serde(default)]
+        gain: Option<Vec<f64>>,
+        #[serde(default)]
+        state_weights: Option<Vec<f64>>,
+        #[serde(default = "default_input_weight")]
+        input_weight: f64,
+    },
+    /// Linear MPC over `horizon` samples of the plant's model, solved by projected
+    /// gradient with a warm start, at most `iterations` per cycle. Needs `[plants.<name>]`.
+    Mpc {
+        horizon: usize,
+        #[serde(default = "default_input_weight")]
+        input_weight: f64, // Against the input's distance from its steady-state value
+        #[serde(default)]
+        output_min: Option<f64>,
+        #[serde(default)]
+        output_max: Option<f64>,
+        #[serde(default = "default_mpc_iterations")]
+        iterations: usize,
+    },
+}
+
+fn default_input_weight() -> f64 {
+    0.01
+}
+
+fn default_mpc_iterations() -> usize {
+    50
+}
+
+impl ControllerConfig {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ControllerConfig::Pid => "pid",
+            ControllerConfig::BangBang { .. } => "bang_bang",
+            ControllerConfig::LeadLag { .. } => "lead_lag",
+            ControllerConfig::StateFeedback { .. } => "state_feedback",
+            ControllerConfig::Mpc { .. } => "mpc",
+        }
+    }
+
+    /// Whether it's built on a linear model of the actuator's plant.
+    pub fn needs_plant(&self) -> bool {
+        matches!(self, ControllerConfig::StateFeedback { .. } | ControllerConfig::Mpc { .. })
+    }
+}
+
+/// Where the derivative term takes its slope from.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DerivativeSource {
+    /// The negated measurement, so setpoint changes don't kick the output
+    Measurement,
+    Error,
+}
+
+/// How the integral is kept from winding up.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AntiWindup {
+    /// Only the `integral_limit` clamp
+    Clamp,
+    /// Bleeds the integral by the amount the output was saturated, over `tracking_time_secs`
+    BackCalculation,
+    /// Stops integrating while the output is saturated and the error would push it further
+    ConditionalIntegration,
+}
+
+/// Gains that take over at `at_secs` of nominal sample time, without a bump in the output.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+pub struct GainChange {
+    pub at_secs: f64,
+    pub kp: f64,
+    pub ki: f64,
+    pub kd: f64,
+}
+
+/// `[actuators.<name>.pid]`: everything about the PID controller beyond its gains.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct PidConfig {
+    pub derivative_on: DerivativeSource,
+    pub derivative_filter_secs: f64, // Time constant of the derivative's low-pass filter; 0 = unfiltered
+    pub output_min: Option<f64>,
+    pub output_max: Option<f64>,
+    pub anti_windup: AntiWindup,
+    pub integral_limit: f64, // Bound on the integral of the error, in every anti-windup mode
+    pub tracking_time_secs: Option<f64>, // Back-calculation only; defaults to √(Ti·Td), or Ti without kd
+    pub feedforward_gain: f64, // Times the setpoint, added to the output
+    pub feedforward_bias: f64,
+    pub gain_schedule: Vec<GainChange>,
+}
+
+impl Default for PidConfig {
+    fn default() -> Self {
+        Self {
+            derivative_on: DerivativeSource::Measurement,
+            derivative_filter_secs: 0.0,
+            output_min: None,
+            output_max: None,
+            anti_windup: AntiWindup::Clamp,
+            integral_limit: 100.0,
+            tracking_time_secs: None,
+            feedforward_gain: 0.0,
+            feedforward_bias: 0.0,
+            gain_schedule: Vec::new(),
+        }
+    }
+}
+
+/// Operator command to an actuator's status machine.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusCommand {
+    /// Releases a latched Emergency or SafeStop into Recovering once the error has cleared
+    Acknowledge,
+    /// Back to Init from any state, resetting the controller
+    Reset,
+}
+
+/// A command issued at `at_secs` of nominal sample time.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy)]
+pub struct ScheduledCommand {
+    pub at_secs: f64,
+    pub command: StatusCommand,
+}
+
+/// `[actuators.<name>.status]`: how the actuator moves between statuses. The error
+/// thresholds are `correcting_threshold` and `emergency_threshold`.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct StatusConfig {
+    pub hysteresis: f64,           // A status entered above a threshold is left below threshold·(1 − hysteresis)
+    pub min_dwell_ms: u64,         // Least time in a status before easing off; escalation is immediate
+    pub degraded_after_misses: u32, // Consecutive deadline misses that degrade, and met deadlines that restore
+    pub safe_stop_after_ms: u64,   // Unacknowledged Emergency this long -> SafeStop
+    pub safe_output: f64,          // Control output held in SafeStop
+    pub auto_acknowledge_ms: Option<u64>, // Acknowledge latched statuses after this long; off by default
+    pub commands: Vec<ScheduledCommand>,
+}
+
+impl Default for StatusConfig {
+    fn default() -> Self {
+        Self {
+            hysteresis: 0.2,
+            min_dwell_ms: 20,
+            degraded_after_misses: 3,
+            safe_stop_after_ms: 100,
+            safe_output: 0.0,
+            auto_acknowledge_ms: None,
+            commands: Vec::new(),
+        }
+    }
+}
+
+impl ActuatorConfig {
+    pub fn default_for(actuator_type: ActuatorType) -> Self {
+        let deadline_us = match actuator_type {
+            ActuatorType::Gripper => 1_000,
+            ActuatorType::Motor => 2_000,
+            ActuatorType::Stabilizer => 1_500,
+        };
+        Self {
+            deadline_us,
+            feedback_deadline_us: 500,
+            channel_capacity: 100,
+            kp: 1.0,
+            ki: 0.1,
+            kd: 0.01,
+            correcting_threshold: 5.0,
+            emergency_threshold: 10.0,
+            setpoint: SignalConfig::Constant { value: 0.0 },
+            controller: ControllerConfig::Pid,
+            pid: PidConfig::default(),
+            status: StatusConfig::default(),
+        }
+    }
+
+    pub fn deadline(&self) -> Duration {
+        Duration::from_micros(self.deadline_us)
+    }
+
+    pub fn feedback_deadline(&self) -> Duration {
+        Duration::from_micros(self.feedback_deadline_us)
+    }
+}
+
+/// `[actuators.<name>]` tables. Any field left out keeps that actuator's own default.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(from = "RawActuatorsConfig")]
+pub struct ActuatorsConfig {
+    pub gripper: ActuatorConfig,
+    pub motor: ActuatorConfig,
+    pub stabilizer: ActuatorConfig,
+}
+
+impl Default for ActuatorsConfig {
+    fn default() -> Self {
+        RawActuatorsConfig::default().into()
+    }
+}
+
+impl ActuatorsConfig {
+    pub fn get(&self, actuator_type: ActuatorType) -> &ActuatorConfig {
+        match actuator_type {
+            ActuatorType::Gripper => &self.gripper,
+            ActuatorType::Motor => &self.motor,
+            ActuatorType::Stabilizer => &self.stabilizer,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+struct RawActuatorsConfig {
+    gripper: RawActuatorConfig,
+    motor: RawActuatorConfig,
+    stabilizer: RawActuatorConfig,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(default)]
+struct RawActuatorConfig {
+    deadline_us: Option<u64>,
+    feedback_deadline_us: Option<u64>,
+    channel_capacity: Option<usize>,
+    kp: Option<f64>,
+    ki: Option<f64>,
+    kd: Option<f64>,
+    correcting_threshold: Option<f64>,
+    emergency_threshold: Option<f64>,
+    setpoint: Option<SignalConfig>,
+    controller: Option<ControllerConfig>,
+    pid: Option<PidConfig>,
+    status: Option<StatusConfig>,
+}
+
+impl RawActuatorConfig {
+    fn resolve(self, actuator_type: ActuatorType) -> ActuatorConfig {
+        let d = ActuatorConfig::default_for(actuator_type);
+        ActuatorConfig {
+            deadline_us: self.deadline_us.unwrap_or(d.deadline_us),
+            feedback_deadline_us: self.feedback_deadline_us.unwrap_or(d.feedback_deadline_us),
+            channel_capacity: self.channel_capacity.unwrap_or(d.channel_capacity),
+            kp: self.kp.unwrap_or(d.kp),
+            ki: self.ki.unwrap_or(d.ki),
+            kd: self.kd.unwrap_or(d.kd),
+            correcting_threshold: self.correcting_threshold.unwrap_or(d.correcting_threshold),
+            emergency_threshold: self.emergency_threshold.unwrap_or(d.emergency_threshold),
+            setpoint: self.setpoint.unwrap_or(d.setpoint),
+            controller: self.controller.unwrap_or(d.controller),
+            pid: self.pid.unwrap_or(d.pid),
+            status: self.status.unwrap_or(d.status),
+        }
+    }
+}
+
+impl From<RawActuatorsConfig> for ActuatorsConfig {
+    fn from(raw: RawActuatorsConfig) -> Self {
+        Self {
+            gripper: raw.gripper.resolve(ActuatorType::Gripper),
+            motor: raw.motor.resolve(ActuatorType::Motor),
+            stabilizer: raw.stabilizer.resolve(ActuatorType::Stabilizer),
+        }
+    }
 }
 
 impl ExperimentConfig {
-    pub fn from_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let contents = std::fs::read_to_string(path)?;
-        let config: ExperimentConfig = toml::from_str(&contents)?;
-        Ok(config)
+    pub fn from_file(path: &str) -> Result<Self, ConfigError> {
+        load_config(path)
+    }
+
+    pub fn is_contention_mode(&self) -> bool {
+        self.mode == "contention"
     }
 }
@@ -0,0 +1,616 @@
+use std::fmt;
+use crate::config::{
+    AntiWindup, ChannelConfig, ControllerConfig, ExperimentConfig, LoadWorkerKind, PidConfig, PlantConfig, PlantDynamics,
+    ReplayPacing, SignalConfig, StatusConfig, WorkloadDistribution,
+};
+use crate::ActuatorType;
+use toml::{Table, Value};
+
+pub const KNOWN_MODES: [&str; 3] = ["baseline", "stress", "contention"];
+
+/// Longest MPC horizon, in samples; each cycle's solve grows with its square.
+const MAX_MPC_HORIZON: usize = 200;
+
+/// A single problem found in a configuration, located by field path and (when
+/// the config came from a file) the TOML line it was found on.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigIssue {
+    pub field: String,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {}: {}: {}", line, self.field, self.message),
+            None => write!(f, "{}: {}", self.field, self.message),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io { path: String, source: std::io::Error },
+    Parse { path: String, line: Option<usize>, message: String },
+    Invalid { path: String, issues: Vec<ConfigIssue> },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io { path, source } => write!(f, "{}: {}", path, source),
+            ConfigError::Parse { path, line: Some(line), message } => {
+                write!(f, "{}:{}: {}", path, line, message)
+            }
+            ConfigError::Parse { path, line: None, message } => write!(f, "{}: {}", path, message),
+            ConfigError::Invalid { path, issues } => {
+                write!(f, "{}: {} invalid setting(s)", path, issues.len())?;
+                for issue in issues {
+                    write!(f, "\n  {}", issue)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl ConfigError {
+    pub(crate) fn from_toml(path: &str, source: &str, err: toml::de::Error) -> Self {
+        ConfigError::Parse {
+            path: path.to_string(),
+            line: err.span().map(|span| line_of(source, span.start)),
+            message: err.message().to_string(),
+        }
+    }
+}
+
+/// Collects issues without failing fast so every problem is reported at once.
+struct Issues(Vec<ConfigIssue>);
+
+impl Issues {
+    fn check(&mut self, ok: bool, field: impl Into<String>, message: impl Into<String>) {
+        if !ok {
+            self.0.push(ConfigIssue { field: field.into(), line: None, message: message.into() });
+        }
+    }
+
+    fn check_channel(&mut self, prefix: &str, channel: &ChannelConfig) {
+        self.check_signal(format!("{}.signal", prefix), &channel.signal);
+        if let Some(noise) = &channel.noise {
+            self.check(noise.std_dev >= 0.0, format!("{}.noise.std_dev", prefix), "must not be negative");
+        }
+        if let Some(spikes) = &channel.spikes {
+            self.check(
+                (0.0..=1.0).contains(&spikes.probability),
+                format!("{}.spikes.probability", prefix),
+                "must be between 0.0 and 1.0",
+            );
+        }
+        if let Some(dropouts) = &channel.dropouts {
+            self.check(
+                (0.0..=1.0).contains(&dropouts.probability),
+                format!("{}.dropouts.probability", prefix),
+                "must be between 0.0 and 1.0",
+            );
+            self.check(dropouts.length_cycles > 0, format!("{}.dropouts.length_cycles", prefix), "must be greater than 0");
+        }
+    }
+
+    fn check_controller(&mut self, prefix: &str, controller: &ControllerConfig, plant: Option<&PlantConfig>) {
+        let field = format!("{}.controller", prefix);
+        if controller.needs_plant() {
+            self.check(
+                plant.is_some(),
+                field.clone(),
+                format!("{} needs a plant model; add [plants.{}]", controller.kind(), prefix.trim_start_matches("actuators.")),
+            );
+        }
+        let order = plant.map(|plant| plant.dynamics.linear_model().order());
+        match controller {
+            ControllerConfig::Pid => {}
+            ControllerConfig::BangBang { high, low, hysteresis } => {
+                self.check(high > low, field.clone(), format!("high must be greater than low ({})", low));
+                self.check(*hysteresis >= 0.0, field, "hysteresis must not be negative");
+            }
+            ControllerConfig::LeadLag { gain, lead_secs, lag_secs } => {
+                self.check(gain.is_finite(), field.clone(), "gain must be a finite number");
+                self.check(*lead_secs >= 0.0, field.clone(), "lead_secs must not be negative");
+                self.check(*lag_secs > 0.0, field, "lag_secs must be greater than 0");
+            }
+            ControllerConfig::StateFeedback { gain, state_weights, input_weight } => {
+                for (name, values) in [("gain", gain), ("state_weights", state_weights)] {
+                    let (Some(values), Some(order)) = (values, order) else { continue };
+                    self.check(
+                        values.len() == order,
+                        field.clone(),
+                        format!("{} needs one entry per plant state ({}), got {}", name, order, values.len()),
+                    );
+                }
+                if let Some(gain) = gain {
+                    self.check(gain.iter().all(|k| k.is_finite()), field.clone(), "gain must be finite numbers");
+                }
+                if let Some(weights) = state_weights {
+                    self.check(weights.iter().all(|&w| w >= 0.0), field.clone(), "state_weights must not be negative");
+                }
+                self.check(*input_weight > 0.0, field, "input_weight must be greater than 0");
+            }
+            ControllerConfig::Mpc { horizon, input_weight, output_min, output_max, iterations } => {
+                self.check((1..=MAX_MPC_HORIZON).contains(horizon), field.clone(),
+                           format!("horizon must be between 1 and {}", MAX_MPC_HORIZON));
+                self.check(*input_weight >= 0.0, field.clone(), "input_weight must not be negative");
+                if let (Some(min), Some(max)) = (output_min, output_max) {
+                    self.check(min < max, field.clone(), format!("output_max must be greater than output_min ({})", min));
+                }
+                self.check(*iterations > 0, field, "iterations must be greater than 0");
+            }
+        }
+    }
+
+    fn check_status(&mut self, prefix: &str, status: &StatusConfig) {
+        self.check(
+            (0.0..1.0).contains(&status.hysteresis),
+            format!("{}.hysteresis", prefix),
+            "must be at least 0.0 and below 1.0",
+        );
+        self.check(
+            status.degraded_after_misses > 0,
+            format!("{}.degraded_after_misses", prefix),
+            "must be greater than 0",
+        );
+        self.check(status.safe_stop_after_ms > 0, format!("{}.safe_stop_after_ms", prefix), "must be greater than 0");
+        self.check(status.safe_output.is_finite(), format!("{}.safe_output", prefix), "must be a finite number");
+        self.check(
+            status.auto_acknowledge_ms != Some(0),
+            format!("{}.auto_acknowledge_ms", prefix),
+            "must be greater than 0 when set",
+        );
+        self.check(
+            status.commands.windows(2).all(|pair| pair[0].at_secs <= pair[1].at_secs)
+                && status.commands.iter().all(|scheduled| scheduled.at_secs >= 0.0),
+            format!("{}.commands", prefix),
+            "at_secs must not be negative and must not go backwards",
+        );
+    }
+
+    fn check_pid(&mut self, prefix: &str, pid: &PidConfig, ki: f64) {
+        self.check(
+            pid.derivative_filter_secs >= 0.0,
+            format!("{}.derivative_filter_secs", prefix),
+            "must not be negative",
+        );
+        if let (Some(min), Some(max)) = (pid.output_min, pid.output_max) {
+            self.check(min < max, format!("{}.output_max", prefix), format!("must be greater than output_min ({})", min));
+        }
+        self.check(pid.integral_limit > 0.0, format!("{}.integral_limit", prefix), "must be greater than 0");
+        self.check(
+            pid.anti_windup == AntiWindup::Clamp || pid.output_min.is_some() || pid.output_max.is_some(),
+            format!("{}.anti_windup", prefix),
+            "needs output_min or output_max to saturate against",
+        );
+        self.check(
+            pid.anti_windup != AntiWindup::BackCalculation || ki > 0.0,
+            format!("{}.anti_windup", prefix),
+            "back_calculation needs a positive ki",
+        );
+        if let Some(tracking_time_secs) = pid.tracking_time_secs {
+            self.check(tracking_time_secs > 0.0, format!("{}.tracking_time_secs", prefix), "must be greater than 0");
+        }
+        self.check(
+            pid.feedforward_gain.is_finite() && pid.feedforward_bias.is_finite(),
+            format!("{}.feedforward_gain", prefix),
+            "feedforward_gain and feedforward_bias must be finite numbers",
+        );
+        let schedule = format!("{}.gain_schedule", prefix);
+        for (index, change) in pid.gain_schedule.iter().enumerate() {
+            self.check(
+                change.kp.is_finite() && change.ki.is_finite() && change.kd.is_finite(),
+                schedule.clone(),
+                format!("entry {}: gains must be finite numbers", index + 1),
+            );
+            self.check(
+                pid.anti_windup != AntiWindup::BackCalculation || change.ki > 0.0,
+                schedule.clone(),
+                format!("entry {}: back_calculation needs a positive ki", index + 1),
+            );
+        }
+        self.check(
+            pid.gain_schedule.windows(2).all(|pair| pair[0].at_secs <= pair[1].at_secs)
+                && pid.gain_schedule.iter().all(|change| change.at_secs >= 0.0),
+            schedule,
+            "at_secs must not be negative and must not go backwards",
+        );
+    }
+
+    fn check_signal(&mut self, signal: String, config: &SignalConfig) {
+        match *config {
+            SignalConfig::Sine { frequency_hz, .. } => {
+                self.check(frequency_hz >= 0.0, signal, "frequency_hz must not be negative");
+            }
+            SignalConfig::Chirp { start_hz, end_hz, sweep_secs, .. } => {
+                self.check(start_hz >= 0.0 && end_hz >= 0.0, signal.clone(), "start_hz and end_hz must not be negative");
+                self.check(sweep_secs > 0.0, signal, "sweep_secs must be greater than 0");
+            }
+            SignalConfig::RandomWalk { step_std_dev, .. } => {
+                self.check(step_std_dev >= 0.0, signal, "step_std_dev must not be negative");
+            }
+            SignalConfig::Constant { .. } | SignalConfig::Step { .. } | SignalConfig::Ramp { .. } => {}
+        }
+    }
+}
+
+impl ExperimentConfig {
+    /// Every semantic problem with this configuration, independent of where it came from.
+    pub fn validation_issues(&self) -> Vec<ConfigIssue> {
+        let mut issues = Issues(Vec::new());
+        // Runtimes work in nanoseconds, so the period has to fit in a u64 of them
+        let period_ns = self.sensor_period_ms.checked_mul(1_000_000);
+        let period_us = period_ns.map_or(0, |ns| ns / 1000); // 0 skips the deadline checks below
+
+        issues.check(!self.experiment_name.trim().is_empty(), "experiment_name", "must not be empty");
+        issues.check(self.duration_secs > 0, "duration_secs", "must be greater than 0");
+        issues.check(self.sensor_period_ms > 0, "sensor_period_ms", "must be greater than 0");
+        issues.check(period_ns.is_some(), "sensor_period_ms", "is too large to express in nanoseconds");
+        issues.check(
+            KNOWN_MODES.contains(&self.mode.as_str()),
+            "mode",
+            format!("unknown mode \"{}\" (expected one of {:?})", self.mode, KNOWN_MODES),
+        );
+
+        // Sensor stage
+        let sensor = &self.sensor;
+        issues.check(sensor.processing_deadline_us > 0, "sensor.processing_deadline_us", "must be greater than 0");
+        issues.check(sensor.transmission_deadline_us > 0, "sensor.transmission_deadline_us", "must be greater than 0");
+        issues.check(sensor.channel_capacity > 0, "sensor.channel_capacity", "must be greater than 0");
+        issues.check(sensor.feedback_channel_capacity > 0, "sensor.feedback_channel_capacity", "must be greater than 0");
+        for (field, deadline_us) in [
+            ("sensor.processing_deadline_us", sensor.processing_deadline_us),
+            ("sensor.transmission_deadline_us", sensor.transmission_deadline_us),
+        ] {
+            issues.check(
+                period_us == 0 || deadline_us <= period_us,
+                field,
+                format!("{}μs deadline is longer than the {}ms sensor period", deadline_us, self.sensor_period_ms),
+            );
+        }
+
+        // Actuator stages
+        for actuator_type in ActuatorType::ALL {
+            let actuator = self.actuators.get(actuator_type);
+            let prefix = format!("actuators.{}", format!("{:?}", actuator_type).to_lowercase());
+
+            issues.check(actuator.deadline_us > 0, format!("{}.deadline_us", prefix), "must be greater than 0");
+            issues.check(
+                period_us == 0 || actuator.deadline_us <= period_us,
+                format!("{}.deadline_us", prefix),
+                format!("{}μs deadline is longer than the {}ms sensor period", actuator.deadline_us, self.sensor_period_ms),
+            );
+            issues.check(actuator.feedback_deadline_us > 0, format!("{}.feedback_deadline_us", prefix), "must be greater than 0");
+            issues.check(actuator.channel_capacity > 0, format!("{}.channel_capacity", prefix), "must be greater than 0");
+            for (name, gain) in [("kp", actuator.kp), ("ki", actuator.ki), ("kd", actuator.kd)] {
+                issues.check(gain.is_finite(), format!("{}.{}", prefix, name), "must be a finite number");
+            }
+            issues.check(
+                actuator.correcting_threshold > 0.0,
+                format!("{}.correcting_threshold", prefix),
+                "must be greater than 0",
+            );
+            issues.check(
+                actuator.emergency_threshold > actuator.correcting_threshold,
+                format!("{}.emergency_threshold", prefix),
+                format!("must be greater than correcting_threshold ({})", actuator.correcting_threshold),
+            );
+            let setpoint = format!("{}.setpoint", prefix);
+            issues.check(
+                !matches!(actuator.setpoint, SignalConfig::RandomWalk { .. }),
+                setpoint.clone(),
+                "must be a function of time; random_walk is not allowed",
+            );
+            issues.check_signal(setpoint, &actuator.setpoint);
+            issues.check_controller(&prefix, &actuator.controller, self.plants.get(actuator_type));
+            issues.check_pid(&format!("{}.pid", prefix), &actuator.pid, actuator.ki);
+            issues.check_status(&format!("{}.status", prefix), &actuator.status);
+
+            // Critical path for one sample: filter -> transmit -> actuate -> feedback
+            let deadlines_us = [
+                sensor.processing_deadline_us,
+                sensor.transmission_deadline_us,
+                actuator.deadline_us,
+                actuator.feedback_deadline_us,
+            ];
+            let path_us = deadlines_us.into_iter().try_fold(0u64, u64::checked_add);
+            issues.check(
+                path_us.is_some_and(|path_us| period_us == 0 || path_us <= period_us),
+                format!("{}.deadline_us", prefix),
+                format!(
+                    "sensor ({}μs + {}μs) + actuator ({}μs) + feedback ({}μs) deadlines sum to {}, exceeding the {}ms sensor period",
+                    sensor.processing_deadline_us, sensor.transmission_deadline_us,
+                    actuator.deadline_us, actuator.feedback_deadline_us,
+                    path_us.map_or_else(|| "more than u64::MAX μs".to_string(), |path_us| format!("{}μs", path_us)),
+                    self.sensor_period_ms
+                ),
+            );
+        }
+
+        // Workload
+        let workload = &self.workload;
+        issues.check(workload.sensor_scale >= 0.0, "workload.sensor_scale", "must not be negative");
+        issues.check(workload.actuator_scale >= 0.0, "workload.actuator_scale", "must not be negative");
+        issues.check(
+            (0.0..=1.0).contains(&workload.tail_probability),
+            "workload.tail_probability",
+            "must be between 0.0 and 1.0",
+        );
+        issues.check(
+            workload.distribution != WorkloadDistribution::Bimodal || workload.tail_ns > 0,
+            "workload.tail_ns",
+            "must be greater than 0 for the bimodal distribution",
+        );
+        issues.check(
+            !matches!(workload.distribution, WorkloadDistribution::Uniform | WorkloadDistribution::Normal)
+                || workload.spread_ns > 0,
+            "workload.spread_ns",
+            "must be greater than 0 for uniform and normal distributions",
+        );
+
+        // Background load
+        let cpu_load = &self.cpu_load;
+        issues.check(
+            (1..=100).contains(&cpu_load.duty_cycle_percent),
+            "cpu_load.duty_cycle_percent",
+            "must be between 1 and 100",
+        );
+        issues.check(
+            !cpu_load.workers.contains(&LoadWorkerKind::CacheThrash) || cpu_load.cache_buffer_kb > 0,
+            "cpu_load.cache_buffer_kb",
+            "must be greater than 0 when cache_thrash workers are used",
+        );
+
+        issues.check(self.shutdown.deadline_ms > 0, "shutdown.deadline_ms", "must be greater than 0");
+        issues.check(self.recording.max_results > 0, "recording.max_results", "must be greater than 0");
+        issues.check(self.recording.max_traces > 0, "recording.max_traces", "must be greater than 0");
+
+        // Emergency stop
+        let estop = &self.estop;
+        issues.check(estop.reaction_deadline_us > 0, "estop.reaction_deadline_us", "must be greater than 0");
+        issues.check(estop.auto_acknowledge_ms != Some(0), "estop.auto_acknowledge_ms", "must be greater than 0 when set");
+        issues.check(
+            estop.acknowledge_at_secs.windows(2).all(|pair| pair[0] <= pair[1])
+                && estop.acknowledge_at_secs.iter().all(|&at_secs| at_secs >= 0.0),
+            "estop.acknowledge_at_secs",
+            "must not be negative and must not go backwards",
+        );
+
+        // Sensor input models
+        let models = &self.sensor_model;
+        for (name, channel) in [("force", &models.force), ("position", &models.position), ("temperature", &models.temperature)] {
+            issues.check_channel(&format!("sensor_model.{}", name), channel);
+        }
+
+        // Replay
+        let replay = &self.replay;
+        if replay.path.is_some() {
+            issues.check(
+                replay.format().is_some(),
+                "replay.format",
+                "can't be inferred from the file extension; set \"csv\", \"jsonl\" or \"capture\"",
+            );
+            issues.check(
+                replay.pacing != ReplayPacing::Accelerated || replay.speed > 0.0,
+                "replay.speed",
+                "must be greater than 0 for accelerated pacing",
+            );
+        }
+
+        // Plants
+        let mut driven_channels = Vec::new();
+        for actuator_type in ActuatorType::ALL {
+            let Some(plant) = self.plants.get(actuator_type) else { continue };
+            let prefix = format!("plants.{}", format!("{:?}", actuator_type).to_lowercase());
+            let positive = match plant.dynamics {
+                PlantDynamics::FirstOrderLag { time_constant_secs, .. } => time_constant_secs > 0.0,
+                PlantDynamics::MassSpringDamper { mass_kg, stiffness, damping } => {
+                    mass_kg > 0.0 && stiffness >= 0.0 && damping >= 0.0
+                }
+                PlantDynamics::DcMotor { resistance_ohm, inductance_h, torque_constant, inertia, friction } => {
+                    resistance_ohm > 0.0 && inductance_h > 0.0 && torque_constant > 0.0 && inertia > 0.0 && friction >= 0.0
+                }
+                PlantDynamics::Thermal { heat_capacity, thermal_resistance, .. } => {
+                    heat_capacity > 0.0 && thermal_resistance > 0.0
+                }
+            };
+            issues.check(
+                positive,
+                format!("{}.dynamics", prefix),
+                "time constants, masses, inertias, resistances and capacities must be greater than 0, other coefficients not negative",
+            );
+            issues.check(
+                !driven_channels.contains(&plant.channel),
+                format!("{}.channel", prefix),
+                format!("{:?} is already driven by another plant", plant.channel).to_lowercase(),
+            );
+            driven_channels.push(plant.channel);
+        }
+
+        if let Some(path) = &self.capture.path {
+            issues.check(!path.trim().is_empty(), "capture.path", "must not be empty");
+        }
+
+        // Shared resource (only consulted in contention mode)
+        if self.is_contention_mode() {
+            let shared = &self.shared_resource;
+            issues.check(shared.accesses_per_cycle > 0, "shared_resource.accesses_per_cycle", "must be greater than 0");
+            issues.check(shared.table_size > 0, "shared_resource.table_size", "must be greater than 0");
+            issues.check(
+                (0.0..=1.0).contains(&shared.read_ratio),
+                "shared_resource.read_ratio",
+                "must be between 0.0 and 1.0",
+            );
+        }
+
+        issues.0
+    }
+
+    /// Validates a configuration that was built or modified in code.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let issues = self.validation_issues();
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid { path: self.experiment_name.clone(), issues })
+        }
+    }
+}
+
+/// Field paths in `given` that no setting of `config` reads, such as misspelt keys.
+/// `config` is serialised back to TOML for the comparison, so defaults, tagged enums
+/// and arrays of tables are all matched the way they were parsed.
+fn unknown_fields(given: &Table, config: &ExperimentConfig) -> Vec<String> {
+    let mut unknown = Vec::new();
+    if let Ok(known) = Value::try_from(config) {
+        collect_unknown(&Value::Table(given.clone()), &known, "", &mut unknown);
+    }
+    unknown
+}
+
+fn collect_unknown(given: &Value, known: &Value, field: &str, unknown: &mut Vec<String>) {
+    match (given, known) {
+        (Value::Table(given), Value::Table(known)) => {
+            for (key, value) in given {
+                let path = if field.is_empty() { key.clone() } else { format!("{}.{}", field, key) };
+                match known.get(key) {
+                    Some(known) => collect_unknown(value, known, &path, unknown),
+                    None => unknown.push(path),
+                }
+            }
+        }
+        // Array elements are reported under the array's own path
+        (Value::Array(given), Value::Array(known)) => {
+            for (given, known) in given.iter().zip(known) {
+                collect_unknown(given, known, field, unknown);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// One issue per unknown field, for strict parsing.
+pub(crate) fn unknown_field_issues(given: &Table, config: &ExperimentConfig) -> Vec<ConfigIssue> {
+    unknown_fields(given, config)
+        .into_iter()
+        .map(|field| ConfigIssue { field, line: None, message: "unknown field".to_string() })
+        .collect()
+}
+
+/// Attaches TOML line numbers to issues by looking each field path up in the source.
+pub(crate) fn locate_issues(source: &str, issues: &mut [ConfigIssue]) {
+    let Ok(document) = toml_edit::ImDocument::parse(source) else {
+        return;
+    };
+    for issue in issues.iter_mut() {
+        issue.line = field_offset(&document, &issue.field).map(|offset| line_of(source, offset));
+    }
+}
+
+/// Byte offset of the key for `field`, falling back to the closest enclosing table
+/// when the key itself was left at its default.
+fn field_offset(document: &toml_edit::ImDocument<&str>, field: &str) -> Option<usize> {
+    let mut table = document.as_table();
+    let mut best = None;
+    for part in field.split('.') {
+        let Some((key, item)) = table.get_key_value(part) else {
+            break;
+        };
+        best = key.span().map(|span| span.start).or(best);
+        match item.as_table() {
+            Some(inner) => table = inner,
+            None => break,
+        }
+    }
+    best
+}
+
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset.min(source.len())].matches('\n').count() + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::{parse_config, ConfigError};
+
+    const MINIMAL: &str = r#"
+experiment_name = "test"
+duration_secs = 1
+sensor_period_ms = 10
+cpu_load_threads = 0
+mode = "baseline"
+processing_time_ns = 1000
+enable_logging = false
+"#;
+
+    fn invalid_fields(result: Result<crate::ExperimentConfig, ConfigError>) -> Vec<(String, Option<usize>)> {
+        match result {
+            Err(ConfigError::Invalid { issues, .. }) => issues.into_iter().map(|issue| (issue.field, issue.line)).collect(),
+            other => panic!("expected invalid settings, got {:?}", other.map(|config| config.experiment_name)),
+        }
+    }
+
+    #[test]
+    fn unknown_fields_are_only_rejected_when_strict() {
+        let content = format!("{}\n[actuators.motor]\nkpp = 3.0\n\n[estop]\nenabld = true\n", MINIMAL);
+        assert!(parse_config("test.toml", &content, false).is_ok());
+        assert_eq!(
+            invalid_fields(parse_config("test.toml", &content, true)),
+            vec![("actuators.motor.kpp".to_string(), Some(11)), ("estop.enabld".to_string(), Some(14))],
+        );
+    }
+
+    #[test]
+    fn strict_parsing_accepts_tagged_and_defaulted_sections() {
+        let content = format!(
+            "{}\n[actuators.motor.controller]\nkind = \"bang_bang\"\nhigh = 1.0\nlow = -1.0\n\n[actuators.motor.status]\ncommands = []\n",
+            MINIMAL
+        );
+        assert!(parse_config("test.toml", &content, true).is_ok());
+    }
+
+    #[test]
+    fn sensor_period_that_overflows_nanoseconds_is_rejected() {
+        let content = MINIMAL.replace("sensor_period_ms = 10", "sensor_period_ms = 18446744073709551");
+        assert_eq!(invalid_fields(parse_config("test.toml", &content, false)), vec![("sensor_period_ms".to_string(), Some(4))]);
+    }
+
+    #[test]
+    fn deadlines_that_overflow_when_summed_are_rejected() {
+        let huge = i64::MAX;
+        let content = format!(
+            "{}\n[sensor]\nprocessing_deadline_us = {}\ntransmission_deadline_us = {}\n\n[actuators.motor]\ndeadline_us = {}\n",
+            MINIMAL, huge, huge, huge
+        );
+        let Err(ConfigError::Invalid { issues, .. }) = parse_config("test.toml", &content, false) else {
+            panic!("expected invalid settings");
+        };
+        let path = issues.iter().find(|issue| issue.field == "actuators.motor.deadline_us" && issue.message.contains("sum to"));
+        assert!(path.expect("the critical path is checked").message.contains("more than u64::MAX"));
+    }
+
+    #[test]
+    fn back_calculation_needs_a_positive_ki_in_every_scheduled_gain() {
+        let content = format!(
+            "{}\n[actuators.motor.pid]\noutput_max = 1.0\nanti_windup = \"back_calculation\"\n\
+             gain_schedule = [{{ at_secs = 1.0, kp = 1.0, ki = 0.5, kd = 0.0 }}, {{ at_secs = 2.0, kp = 1.0, ki = 0.0, kd = 0.0 }}]\n",
+            MINIMAL
+        );
+        let fields = invalid_fields(parse_config("test.toml", &content, false));
+        assert_eq!(fields.iter().map(|(field, _)| field.as_str()).collect::<Vec<_>>(), vec!["actuators.motor.pid.gain_schedule"]);
+    }
+}
@@ -0,0 +1,675 @@
+//! Control laws behind one `Controller` interface, chosen per actuator by
+//! `controller = { kind = "..." }` in `[actuators.<name>]`.
+//!
+//! PID, bang-bang and lead-lag work on the error alone. State feedback and MPC are
+//! built on the linear model of the actuator's plant, discretized at the sensor period,
+//! and estimate its states with a steady-state observer.
+
+use crate::config::{ControllerConfig, ExperimentConfig};
+use crate::pid::PidController;
+use crate::plant::LinearModel;
+use crate::sensor_model::{build_signal, SensorModel};
+use crate::ActuatorType;
+
+type Matrix = Vec<Vec<f64>>;
+
+/// Riccati iterations before giving up on convergence and using the last one.
+const MAX_RICCATI_ITERATIONS: usize = 10_000;
+
+/// Observer design weights: process noise on every state, against measurement noise.
+/// Small measurement noise makes the estimate follow the measurement closely.
+const PROCESS_NOISE: f64 = 1.0;
+const MEASUREMENT_NOISE: f64 = 1e-3;
+
+/// One control step.
+#[derive(Debug, Clone, Copy)]
+pub struct ControlOutput {
+    pub setpoint: f64,
+    pub error: f64,  // Setpoint − measurement
+    pub output: f64, // After saturation
+    pub saturated: bool,
+}
+
+pub trait Controller: Send {
+    /// One control step towards `setpoint` on `measurement`, taken at `t_secs` of
+    /// nominal sample time and `dt` after the last.
+    fn update(&mut self, t_secs: f64, setpoint: f64, measurement: f64, dt: f64) -> ControlOutput;
+
+    /// Forgets everything learned from earlier steps.
+    fn reset(&mut self);
+}
+
+/// An actuator's configured controller with its setpoint.
+pub struct ActuatorController {
+    kind: &'static str,
+    setpoint: Box<dyn SensorModel>,
+    controller: Box<dyn Controller>,
+}
+
+impl ActuatorController {
+    pub fn from_config(config: &ExperimentConfig, actuator: ActuatorType) -> Self {
+        let settings = config.actuators.get(actuator);
+        Self {
+            kind: settings.controller.kind(),
+            setpoint: build_signal(&settings.setpoint, 0),
+            controller: build_controller(config, actuator),
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        self.kind
+    }
+
+    /// One control step on `measurement`, taken at `t_secs` of nominal sample time.
+    pub fn update(&mut self, t_secs: f64, measurement: f64, dt: f64) -> ControlOutput {
+        let setpoint = self.setpoint.sample(t_secs);
+        self.controller.update(t_secs, setpoint, measurement, dt)
+    }
+
+    pub fn reset(&mut self) {
+        self.controller.reset();
+    }
+}
+
+/// `actuator`'s controller, designed for the sensor period. State feedback and MPC
+/// need the actuator's plant, which validation guarantees.
+pub fn build_controller(config: &ExperimentConfig, actuator: ActuatorType) -> Box<dyn Controller> {
+    let settings = config.actuators.get(actuator);
+    let dt = config.sensor_period_ms as f64 / 1000.0;
+    let model = || {
+        let plant = config.plants.get(actuator).expect("model-based controllers need a plant");
+        DiscreteModel::new(&plant.dynamics.linear_model(), dt)
+    };
+    match settings.controller.clone() {
+        ControllerConfig::Pid => Box::new(PidController::from_config(settings)),
+        ControllerConfig::BangBang { high, low, hysteresis } => Box::new(BangBang::new(high, low, hysteresis)),
+        ControllerConfig::LeadLag { gain, lead_secs, lag_secs } => Box::new(LeadLag::new(gain, lead_secs, lag_secs)),
+        ControllerConfig::StateFeedback { gain, state_weights, input_weight } => {
+            Box::new(StateFeedback::new(model(), gain, state_weights, input_weight))
+        }
+        ControllerConfig::Mpc { horizon, input_weight, output_min, output_max, iterations } => {
+            let bounds = (output_min.unwrap_or(f64::NEG_INFINITY), output_max.unwrap_or(f64::INFINITY));
+            Box::new(Mpc::new(model(), horizon, input_weight, bounds, iterations))
+        }
+    }
+}
+
+/// Two-level output with a hysteresis band around the setpoint.
+pub struct BangBang {
+    high: f64,
+    low: f64,
+    hysteresis: f64,
+    on: Option<bool>, // None before the first step
+}
+
+impl BangBang {
+    pub fn new(high: f64, low: f64, hysteresis: f64) -> Self {
+        Self { high, low, hysteresis, on: None }
+    }
+}
+
+impl Controller for BangBang {
+    fn update(&mut self, _t_secs: f64, setpoint: f64, measurement: f64, _dt: f64) -> ControlOutput {
+        let error = setpoint - measurement;
+        let on = if error > self.hysteresis {
+            true
+        } else if error < -self.hysteresis {
+            false
+        } else {
+            // Inside the band the output holds; the first step starts on the error's side
+            self.on.unwrap_or(error > 0.0)
+        };
+        self.on = Some(on);
+        let output = if on { self.high } else { self.low };
+        ControlOutput { setpoint, error, output, saturated: false }
+    }
+
+    fn reset(&mut self) {
+        self.on = None;
+    }
+}
+
+/// Lead-lag compensator on the error, by Tustin's method at each step's `dt`.
+pub struct LeadLag {
+    gain: f64,
+    lead_secs: f64,
+    lag_secs: f64,
+    prev: Option<(f64, f64)>, // Last error and output
+}
+
+impl LeadLag {
+    pub fn new(gain: f64, lead_secs: f64, lag_secs: f64) -> Self {
+        Self { gain, lead_secs, lag_secs, prev: None }
+    }
+}
+
+impl Controller for LeadLag {
+    fn update(&mut self, _t_secs: f64, setpoint: f64, measurement: f64, dt: f64) -> ControlOutput {
+        let error = setpoint - measurement;
+        // Starts as if the error had always been there, so the first step doesn't kick
+        let (prev_error, prev_output) = self.prev.unwrap_or((error, self.gain * error));
+        let output = if dt > 0.0 {
+            let a = 2.0 / dt;
+            (self.gain * ((self.lead_secs * a + 1.0) * error + (1.0 - self.lead_secs * a) * prev_error)
+                - (1.0 - self.lag_secs * a) * prev_output)
+                / (self.lag_secs * a + 1.0)
+        } else {
+            prev_output
+        };
+        self.prev = Some((error, output));
+        ControlOutput { setpoint, error, output, saturated: false }
+    }
+
+    fn reset(&mut self) {
+        self.prev = None;
+    }
+}
+
+/// `LinearModel` under a zero-order hold over one sample, with the steady state that
+/// holds its output at each reference.
+#[derive(Debug, Clone)]
+pub struct DiscreteModel {
+    pub a: Matrix,
+    pub b: Vec<f64>,
+    pub output_state: usize,
+    pub offset: f64,
+    target: Option<(Vec<f64>, f64)>, // State and input holding the output one above `offset`
+}
+
+impl DiscreteModel {
+    pub fn new(model: &LinearModel, dt: f64) -> Self {
+        let n = model.order();
+        // exp([[A, B], [0, 0]]·dt) = [[Ad, Bd], [0, 1]]
+        let mut augmented: Matrix =
+            model.a.iter().zip(&model.b).map(|(row, b)| row.iter().chain([b]).map(|x| x * dt).collect()).collect();
+        augmented.push(vec![0.0; n + 1]);
+        let exponential = expm(&augmented);
+        let a: Matrix = (0..n).map(|i| exponential[i][..n].to_vec()).collect();
+        let b: Vec<f64> = (0..n).map(|i| exponential[i][n]).collect();
+
+        // Steady state for a unit reference: (A − I)·x + B·u = 0, x[output] = 1
+        let mut system = vec![vec![0.0; n + 1]; n + 1];
+        let mut rhs = vec![0.0; n + 1];
+        for i in 0..n {
+            for j in 0..n {
+                system[i][j] = a[i][j] - if i == j { 1.0 } else { 0.0 };
+            }
+            system[i][n] = b[i];
+        }
+        system[n][model.output_state] = 1.0;
+        rhs[n] = 1.0;
+        let target = solve(system, rhs).map(|solution| (solution[..n].to_vec(), solution[n]));
+
+        Self { a, b, output_state: model.output_state, offset: model.offset, target }
+    }
+
+    pub fn order(&self) -> usize {
+        self.b.len()
+    }
+
+    fn output(&self, state: &[f64]) -> f64 {
+        self.offset + state[self.output_state]
+    }
+
+    fn next(&self, state: &[f64], input: f64) -> Vec<f64> {
+        let mut next = mat_vec(&self.a, state);
+        for (x, b) in next.iter_mut().zip(&self.b) {
+            *x += b * input;
+        }
+        next
+    }
+
+    /// State and input that hold the output at `setpoint`. Zero for a plant without
+    /// steady-state gain.
+    fn steady_state(&self, setpoint: f64) -> (Vec<f64>, f64) {
+        let deviation = setpoint - self.offset;
+        match &self.target {
+            Some((state, input)) => (state.iter().map(|x| x * deviation).collect(), input * deviation),
+            None => (vec![0.0; self.order()], 0.0),
+        }
+    }
+}
+
+/// Steady-state Kalman filter on a `DiscreteModel`.
+struct Observer {
+    model: DiscreteModel,
+    gain: Vec<f64>,
+    predicted: Option<Vec<f64>>, // None before the first measurement
+}
+
+impl Observer {
+    fn new(model: DiscreteModel) -> Self {
+        let n = model.order();
+        // The dual of LQR: Riccati on (Aᵀ, Cᵀ) gives the prediction covariance
+        let measured: Vec<f64> = (0..n).map(|i| if i == model.output_state { 1.0 } else { 0.0 }).collect();
+        let covariance = dare(&transpose(&model.a), &measured, &scaled_identity(n, PROCESS_NOISE), MEASUREMENT_NOISE);
+        let innovation = covariance[model.output_state][model.output_state] + MEASUREMENT_NOISE;
+        let gain = (0..n).map(|i| covariance[i][model.output_state] / innovation).collect();
+        Self { model, gain, predicted: None }
+    }
+
+    /// The state estimate once `measurement` is taken into account. The first one
+    /// starts the plant at rest at the measurement.
+    fn correct(&mut self, measurement: f64) -> Vec<f64> {
+        let mut estimate = self.predicted.take().unwrap_or_else(|| {
+            let mut rest = vec![0.0; self.model.order()];
+            rest[self.model.output_state] = measurement - self.model.offset;
+            rest
+        });
+        let innovation = measurement - self.model.output(&estimate);
+        for (x, l) in estimate.iter_mut().zip(&self.gain) {
+            *x += l * innovation;
+        }
+        estimate
+    }
+
+    fn predict(&mut self, estimate: &[f64], input: f64) {
+        self.predicted = Some(self.model.next(estimate, input));
+    }
+}
+
+/// Full state feedback around the steady state for the setpoint, with a gain fixed
+/// when the controller is built.
+pub struct StateFeedback {
+    observer: Observer,
+    gain: Vec<f64>,
+}
+
+impl StateFeedback {
+    /// `gain` if given, else the infinite-horizon LQR gain for `state_weights`
+    /// (default: the measured output only) and `input_weight`.
+    pub fn new(model: DiscreteModel, gain: Option<Vec<f64>>, state_weights: Option<Vec<f64>>, input_weight: f64) -> Self {
+        let n = model.order();
+        let gain = gain.unwrap_or_else(|| {
+            let mut weights = vec![vec![0.0; n]; n];
+            match state_weights {
+                Some(diagonal) => (0..n).for_each(|i| weights[i][i] = diagonal[i]),
+                None => weights[model.output_state][model.output_state] = 1.0,
+            }
+            lqr_gain(&model.a, &model.b, &weights, input_weight)
+        });
+        Self { observer: Observer::new(model), gain }
+    }
+}
+
+impl Controller for StateFeedback {
+    fn update(&mut self, _t_secs: f64, setpoint: f64, measurement: f64, _dt: f64) -> ControlOutput {
+        let estimate = self.observer.correct(measurement);
+        let (target_state, target_input) = self.observer.model.steady_state(setpoint);
+        let output = target_input
+            - self.gain.iter().zip(estimate.iter().zip(&target_state)).map(|(k, (x, t))| k * (x - t)).sum::<f64>();
+        self.observer.predict(&estimate, output);
+        ControlOutput { setpoint, error: setpoint - measurement, output, saturated: false }
+    }
+
+    fn reset(&mut self) {
+        self.observer.predicted = None;
+    }
+}
+
+/// Linear MPC: over `horizon` samples, minimizes Σ (y − setpoint)² + input_weight·Σ (u − u_ss)²
+/// subject to the input bounds. The prediction matrices are built once; each cycle runs
+/// a fixed budget of accelerated projected-gradient iterations from the last plan.
+pub struct Mpc {
+    observer: Observer,
+    input_weight: f64,
+    bounds: (f64, f64),
+    iterations: usize,
+    free_response: Matrix, // Row j: C·A^(j+1), the output j+1 samples ahead from the state
+    forced_response: Matrix, // [j][i]: C·A^(j−i)·B, the effect of input i on output j+1
+    hessian: Matrix,
+    step: f64,
+    plan: Option<Vec<f64>>, // Last cycle's inputs, to warm-start from
+}
+
+impl Mpc {
+    pub fn new(model: DiscreteModel, horizon: usize, input_weight: f64, bounds: (f64, f64), iterations: usize) -> Self {
+        let n = model.order();
+        let mut free_response = Vec::with_capacity(horizon);
+        let mut row: Vec<f64> = (0..n).map(|i| if i == model.output_state { 1.0 } else { 0.0 }).collect();
+        let mut markov = Vec::with_capacity(horizon); // C·A^j·B
+        for _ in 0..horizon {
+            markov.push(row.iter().zip(&model.b).map(|(c, b)| c * b).sum::<f64>());
+            row = (0..n).map(|j| (0..n).map(|i| row[i] * model.a[i][j]).sum()).collect();
+            free_response.push(row.clone());
+        }
+        let forced_response: Matrix =
+            (0..horizon).map(|j| (0..horizon).map(|i| if i <= j { markov[j - i] } else { 0.0 }).collect()).collect();
+
+        let mut hessian = vec![vec![0.0; horizon]; horizon];
+        for (i, hessian_row) in hessian.iter_mut().enumerate() {
+            for (k, entry) in hessian_row.iter_mut().enumerate() {
+                *entry = (0..horizon).map(|j| forced_response[j][i] * forced_response[j][k]).sum::<f64>();
+            }
+            hessian_row[i] += input_weight;
+        }
+        // 1/L, with L the largest row sum bounding the Hessian's largest eigenvalue
+        let lipschitz = hessian.iter().map(|row| row.iter().map(|h| h.abs()).sum::<f64>()).fold(0.0, f64::max);
+        let step = if lipschitz > 0.0 { 1.0 / lipschitz } else { 0.0 };
+
+        Self {
+            observer: Observer::new(model),
+            input_weight,
+            bounds,
+            iterations,
+            free_response,
+            forced_response,
+            hessian,
+            step,
+            plan: None,
+        }
+    }
+}
+
+impl Controller for Mpc {
+    fn update(&mut self, _t_secs: f64, setpoint: f64, measurement: f64, _dt: f64) -> ControlOutput {
+        let estimate = self.observer.correct(measurement);
+        let model = &self.observer.model;
+        let (_, target_input) = model.steady_state(setpoint);
+        let (min, max) = self.bounds;
+        let horizon = self.hessian.len();
+
+        // Linear term of the cost: Gᵀ·(free response − reference) − ρ·u_ss
+        let free_error: Vec<f64> =
+            self.free_response.iter().map(|row| model.offset + dot(row, &estimate) - setpoint).collect();
+        let linear: Vec<f64> = (0..horizon)
+            .map(|i| {
+                (0..horizon).map(|j| self.forced_response[j][i] * free_error[j]).sum::<f64>()
+                    - self.input_weight * target_input
+            })
+            .collect();
+
+        // Warm start: last plan shifted by one sample
+        let mut plan = match self.plan.take() {
+            Some(mut last) => {
+                last.rotate_left(1);
+                if horizon > 1 {
+                    last[horizon - 1] = last[horizon - 2];
+                }
+                last
+            }
+            None => vec![target_input.clamp(min, max); horizon],
+        };
+        let mut previous = plan.clone();
+        let mut extrapolated = vec![0.0; horizon];
+        let mut momentum: f64 = 1.0;
+        for _ in 0..self.iterations {
+            let next_momentum = (1.0 + (1.0 + 4.0 * momentum * momentum).sqrt()) / 2.0;
+            let blend = (momentum - 1.0) / next_momentum;
+            for i in 0..horizon {
+                extrapolated[i] = plan[i] + blend * (plan[i] - previous[i]);
+            }
+            std::mem::swap(&mut previous, &mut plan);
+            for (i, row) in self.hessian.iter().enumerate() {
+                let gradient = dot(row, &extrapolated) + linear[i];
+                plan[i] = (extrapolated[i] - self.step * gradient).clamp(min, max);
+            }
+            momentum = next_momentum;
+        }
+
+        let output = plan[0];
+        self.observer.predict(&estimate, output);
+        self.plan = Some(plan);
+        ControlOutput { setpoint, error: setpoint - measurement, output, saturated: output <= min || output >= max }
+    }
+
+    fn reset(&mut self) {
+        self.observer.predicted = None;
+        self.plan = None;
+    }
+}
+
+/// Infinite-horizon LQR gain `K` for `u = −K·x`.
+pub fn lqr_gain(a: &Matrix, b: &[f64], state_weights: &Matrix, input_weight: f64) -> Vec<f64> {
+    let cost = dare(a, b, state_weights, input_weight);
+    let cost_b = mat_vec(&cost, b);
+    let denominator = input_weight + dot(b, &cost_b);
+    // Bᵀ·P·A, with P symmetric
+    (0..b.len()).map(|j| (0..b.len()).map(|i| cost_b[i] * a[i][j]).sum::<f64>() / denominator).collect()
+}
+
+/// Solves the discrete algebraic Riccati equation for one input by iterating it to a
+/// fixed point from `q`.
+fn dare(a: &Matrix, b: &[f64], q: &Matrix, r: f64) -> Matrix {
+    let n = b.len();
+    let at = transpose(a);
+    let mut p = q.clone();
+    for _ in 0..MAX_RICCATI_ITERATIONS {
+        let pa = mat_mul(&p, a);
+        let atpa = mat_mul(&at, &pa);
+        let atpb = mat_vec(&at, &mat_vec(&p, b));
+        let btpb = dot(b, &mat_vec(&p, b));
+        let mut next = vec![vec![0.0; n]; n];
+        let mut change: f64 = 0.0;
+        let mut size: f64 = 0.0;
+        for i in 0..n {
+            for j in 0..n {
+                next[i][j] = q[i][j] + atpa[i][j] - atpb[i] * atpb[j] / (r + btpb);
+                change = change.max((next[i][j] - p[i][j]).abs());
+                size = size.max(next[i][j].abs());
+            }
+        }
+        p = next;
+        if change <= 1e-12 * (1.0 + size) {
+            break;
+        }
+    }
+    p
+}
+
+/// Matrix exponential by scaling and squaring a Taylor series.
+fn expm(m: &Matrix) -> Matrix {
+    let n = m.len();
+    let norm = m.iter().map(|row| row.iter().map(|x| x.abs()).sum::<f64>()).fold(0.0, f64::max);
+    let squarings = if norm > 0.5 { (norm / 0.5).log2().ceil() as i32 } else { 0 };
+    let scale = 0.5f64.powi(squarings);
+    let scaled: Matrix = m.iter().map(|row| row.iter().map(|x| x * scale).collect()).collect();
+
+    let mut result = scaled_identity(n, 1.0);
+    let mut term = scaled_identity(n, 1.0);
+    for k in 1..=16 {
+        term = mat_mul(&term, &scaled);
+        term.iter_mut().flatten().for_each(|x| *x /= k as f64);
+        result.iter_mut().flatten().zip(term.iter().flatten()).for_each(|(r, t)| *r += t);
+    }
+    for _ in 0..squarings {
+        result = mat_mul(&result, &result);
+    }
+    result
+}
+
+/// Gaussian elimination with partial pivoting; `None` when `a` is singular.
+fn solve(mut a: Matrix, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+        if a[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        a.swap(col, pivot);
+        b.swap(col, pivot);
+        let pivot_row = a[col].clone();
+        for row in col + 1..n {
+            let factor = a[row][col] / pivot_row[col];
+            for (entry, above) in a[row][col..].iter_mut().zip(&pivot_row[col..]) {
+                *entry -= factor * above;
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+    let mut x = vec![0.0; n];
+    for row in (0..n).rev() {
+        let known: f64 = (row + 1..n).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - known) / a[row][row];
+    }
+    Some(x)
+}
+
+fn scaled_identity(n: usize, value: f64) -> Matrix {
+    (0..n).map(|i| (0..n).map(|j| if i == j { value } else { 0.0 }).collect()).collect()
+}
+
+fn transpose(m: &Matrix) -> Matrix {
+    (0..m[0].len()).map(|j| m.iter().map(|row| row[j]).collect()).collect()
+}
+
+fn mat_mul(a: &Matrix, b: &Matrix) -> Matrix {
+    a.iter().map(|row| (0..b[0].len()).map(|j| row.iter().zip(b).map(|(x, b_row)| x * b_row[j]).sum()).collect()).collect()
+}
+
+fn mat_vec(a: &Matrix, v: &[f64]) -> Vec<f64> {
+    a.iter().map(|row| dot(row, v)).collect()
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PlantDynamics;
+
+    fn assert_matrix(actual: &Matrix, expected: &Matrix, tolerance: f64) {
+        for (actual_row, expected_row) in actual.iter().zip(expected) {
+            for (x, y) in actual_row.iter().zip(expected_row) {
+                assert!((x - y).abs() <= tolerance * (1.0 + y.abs()), "{:?} != {:?}", actual, expected);
+            }
+        }
+    }
+
+    /// Runs `controller` against `model` from rest for `steps` samples; returns every
+    /// control output and the final plant output.
+    fn closed_loop(
+        controller: &mut dyn Controller,
+        model: &DiscreteModel,
+        setpoint: f64,
+        steps: usize,
+    ) -> (Vec<ControlOutput>, f64) {
+        let mut state = vec![0.0; model.order()];
+        let outputs: Vec<ControlOutput> = (0..steps)
+            .map(|step| {
+                let out = controller.update(step as f64 * 0.01, setpoint, model.output(&state), 0.01);
+                state = model.next(&state, out.output);
+                out
+            })
+            .collect();
+        (outputs, model.output(&state))
+    }
+
+    #[test]
+    fn expm_matches_closed_forms() {
+        assert_matrix(&expm(&vec![vec![2.0]]), &vec![vec![2.0f64.exp()]], 1e-12);
+        assert_matrix(&expm(&vec![vec![-7.5]]), &vec![vec![(-7.5f64).exp()]], 1e-12);
+        // A rotation, scaled down and squared back up
+        let theta = 3.0f64;
+        let rotation = expm(&vec![vec![0.0, -theta], vec![theta, 0.0]]);
+        assert_matrix(&rotation, &vec![vec![theta.cos(), -theta.sin()], vec![theta.sin(), theta.cos()]], 1e-12);
+        // Nilpotent: the series ends after the linear term
+        assert_matrix(&expm(&vec![vec![0.0, 0.5], vec![0.0, 0.0]]), &vec![vec![1.0, 0.5], vec![0.0, 1.0]], 1e-15);
+    }
+
+    #[test]
+    fn a_first_order_lag_discretizes_under_a_zero_order_hold() {
+        let (gain, tau, dt) = (2.0, 0.5, 0.01);
+        let model = DiscreteModel::new(&PlantDynamics::FirstOrderLag { gain, time_constant_secs: tau }.linear_model(), dt);
+        let decay = (-dt / tau).exp();
+        assert_matrix(&model.a, &vec![vec![decay]], 1e-12);
+        assert!((model.b[0] - gain * (1.0 - decay)).abs() < 1e-12);
+        let (state, input) = model.steady_state(3.0);
+        assert!((state[0] - 3.0).abs() < 1e-9 && (input - 1.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn scalar_dare_and_lqr_match_the_quadratic_solution() {
+        // p = q + a²p − (abp)²/(r + b²p), an unstable plant
+        let (a, b, q, r) = (1.2f64, 0.5f64, 1.0f64, 2.0f64);
+        let linear = r * (1.0 - a * a) - q * b * b;
+        let p = (-linear + (linear * linear + 4.0 * b * b * q * r).sqrt()) / (2.0 * b * b);
+        assert_matrix(&dare(&vec![vec![a]], &[b], &vec![vec![q]], r), &vec![vec![p]], 1e-9);
+
+        let k = lqr_gain(&vec![vec![a]], &[b], &vec![vec![q]], r);
+        assert!((k[0] - a * b * p / (r + b * b * p)).abs() < 1e-9);
+        assert!((a - b * k[0]).abs() < 1.0, "closed loop unstable: {}", a - b * k[0]);
+    }
+
+    #[test]
+    fn dare_solves_the_riccati_equation_for_a_double_integrator() {
+        let dt = 0.1;
+        let a = vec![vec![1.0, dt], vec![0.0, 1.0]];
+        let b = [dt * dt / 2.0, dt];
+        let q = scaled_identity(2, 1.0);
+        let r = 0.5;
+        let p = dare(&a, &b, &q, r);
+        // Q + AᵀPA − AᵀPB·BᵀPA/(R + BᵀPB) − P = 0
+        let atpa = mat_mul(&transpose(&a), &mat_mul(&p, &a));
+        let atpb = mat_vec(&transpose(&a), &mat_vec(&p, &b));
+        let btpb = dot(&b, &mat_vec(&p, &b));
+        let residual: Matrix = (0..2)
+            .map(|i| (0..2).map(|j| q[i][j] + atpa[i][j] - atpb[i] * atpb[j] / (r + btpb) - p[i][j]).collect())
+            .collect();
+        assert_matrix(&residual, &vec![vec![0.0; 2]; 2], 1e-9);
+
+        // A − BK has its eigenvalues inside the unit circle: |det| < 1 and |trace| < 1 + det
+        let k = lqr_gain(&a, &b, &q, r);
+        let closed: Matrix = (0..2).map(|i| (0..2).map(|j| a[i][j] - b[i] * k[j]).collect()).collect();
+        let det = closed[0][0] * closed[1][1] - closed[0][1] * closed[1][0];
+        let trace = closed[0][0] + closed[1][1];
+        assert!(det.abs() < 1.0 && trace.abs() < 1.0 + det, "closed loop unstable: {:?}", closed);
+    }
+
+    #[test]
+    fn lead_lag_steps_like_its_continuous_transfer_function() {
+        // gain/(τs + 1): Tustin averages the error over the sample, so the step lands half a sample late
+        let (gain, tau, dt) = (2.0, 0.1, 0.001);
+        let mut lag = LeadLag::new(gain, 0.0, tau);
+        assert_eq!(lag.update(0.0, 0.0, 0.0, dt).output, 0.0);
+        for step in 1..=500 {
+            let t = step as f64 * dt;
+            let output = lag.update(t, 1.0, 0.0, dt).output;
+            let expected = gain * (1.0 - (-(t - dt / 2.0) / tau).exp());
+            assert!((output - expected).abs() < 1e-4, "t = {}: {} != {}", t, output, expected);
+        }
+
+        // Matched lead and lag cancel to a pure gain; every compensator settles at gain·error
+        let mut flat = LeadLag::new(gain, tau, tau);
+        let mut lead = LeadLag::new(gain, 0.3, 0.05);
+        flat.update(0.0, 0.0, 0.0, dt);
+        lead.update(0.0, 0.0, 0.0, dt);
+        assert!((flat.update(dt, 1.0, 0.0, dt).output - gain).abs() < 1e-12);
+        let kick = lead.update(dt, 1.0, 0.0, dt).output;
+        assert!(kick > gain, "a lead kicks above its DC gain, got {}", kick);
+        let settled = (2..5_000).map(|step| lead.update(step as f64 * dt, 1.0, 0.0, dt).output).last().unwrap();
+        assert!((settled - gain).abs() < 1e-6);
+    }
+
+    #[test]
+    fn mpc_keeps_its_input_within_bounds() {
+        let dt = 0.01;
+        let model = DiscreteModel::new(&PlantDynamics::FirstOrderLag { gain: 1.0, time_constant_secs: 0.1 }.linear_model(), dt);
+        let bounds = (-1.0, 2.0);
+
+        // Reachable: saturates at first, then settles on the steady input
+        let mut mpc = Mpc::new(model.clone(), 20, 0.01, bounds, 50);
+        let (outputs, y) = closed_loop(&mut mpc, &model, 1.5, 300);
+        assert!(outputs.iter().all(|out| (bounds.0..=bounds.1).contains(&out.output)));
+        assert!(outputs[0].saturated && outputs[0].output == bounds.1);
+        assert!((y - 1.5).abs() < 0.01, "y = {}", y);
+
+        // Out of reach in either direction: held at the bound
+        for (setpoint, bound) in [(5.0, bounds.1), (-5.0, bounds.0)] {
+            let mut mpc = Mpc::new(model.clone(), 20, 0.01, bounds, 50);
+            let (outputs, _) = closed_loop(&mut mpc, &model, setpoint, 300);
+            assert!(outputs.iter().all(|out| out.output == bound && out.saturated));
+        }
+    }
+
+    #[test]
+    fn lqr_state_feedback_settles_a_mass_spring_damper_on_the_setpoint() {
+        let dynamics = PlantDynamics::MassSpringDamper { mass_kg: 1.0, stiffness: 4.0, damping: 0.4 };
+        let model = DiscreteModel::new(&dynamics.linear_model(), 0.01);
+        let mut controller = StateFeedback::new(model.clone(), None, Some(vec![100.0, 1.0]), 0.1);
+        let (_, y) = closed_loop(&mut controller, &model, 0.5, 1_000);
+        assert!((y - 0.5).abs() < 1e-3, "y = {}", y);
+    }
+}
@@ -1,5 +1,6 @@
 use std::sync::{Arc, Mutex};
 use serde::{Deserialize, Serialize};
+use crate::config::StatusCommand;
 use crate::{SensorData, ActuatorFeedback, ActuatorType};
 
 /// Real-time data point for dashboard visualization
@@ -22,11 +23,12 @@ pub struct MetricsSnapshot {
     pub lateness_ns: i64,
 }
 
-/// Thread-safe dashboard data buffer
+/// Thread-safe dashboard data buffer, and the operator's way back into the run
 #[derive(Clone)]
 pub struct DashboardBuffer {
     data: Arc<Mutex<Vec<DashboardData>>>,
     max_size: usize,
+    commands: Arc<Mutex<Vec<(ActuatorType, StatusCommand)>>>, // Not yet taken by their actuator
 }
 
 impl DashboardBuffer {
@@ -34,6 +36,7 @@ impl DashboardBuffer {
         Self {
             data: Arc::new(Mutex::new(Vec::with_capacity(max_size))),
             max_size,
+            commands: Arc::new(Mutex::new(Vec::new())),
         }
     }
 
@@ -57,13 +60,37 @@ impl DashboardBuffer {
         self.data.lock().unwrap().clone()
     }
 
+    /// Drops the data and any commands not yet taken.
     pub fn clear(&self) {
         self.data.lock().unwrap().clear();
+        self.commands.lock().unwrap().clear();
+    }
+
+    /// Issues `command` to `actuator`'s status machine; the actuator applies it on its next cycle.
+    pub fn send_command(&self, actuator: ActuatorType, command: StatusCommand) {
+        self.commands.lock().unwrap().push((actuator, command));
+    }
+
+    /// Commands issued to `actuator` since it last took them, oldest first.
+    pub fn take_commands(&self, actuator: ActuatorType) -> Vec<StatusCommand> {
+        let mut commands = self.commands.lock().unwrap();
+        let mut taken = Vec::new();
+        commands.retain(|&(target, command)| {
+            if target == actuator {
+                taken.push(command);
+            }
+            target != actuator
+        });
+        taken
     }
 
     pub fn len(&self) -> usize {
         self.data.lock().unwrap().len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.lock().unwrap().is_empty()
+    }
 }
 
 
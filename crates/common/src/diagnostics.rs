@@ -1,9 +1,14 @@
 use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::status::StatusTransition;
+use crate::ActuatorStatus;
 
 #[derive(Default)]
 pub struct SharedDiagnostics {
     pub anomaly_count: AtomicU64,
-    pub emergency_stops: AtomicU64,
+    pub emergency_stops: AtomicU64, // Entries into Emergency, by any actuator
+    transitions: Mutex<Vec<StatusTransition>>, // Every actuator's, in the order recorded
 }
 
 impl SharedDiagnostics {
@@ -11,7 +16,14 @@ impl SharedDiagnostics {
         self.anomaly_count.fetch_add(1, Ordering::Relaxed);
     }
 
-    pub fn record_emergency(&self) {
-        self.emergency_stops.fetch_add(1, Ordering::Relaxed);
+    pub fn record_transition(&self, transition: StatusTransition) {
+        if transition.to == ActuatorStatus::Emergency {
+            self.emergency_stops.fetch_add(1, Ordering::Relaxed);
+        }
+        self.transitions.lock().unwrap().push(transition);
+    }
+
+    pub fn transitions(&self) -> Vec<StatusTransition> {
+        self.transitions.lock().unwrap().clone()
     }
 }
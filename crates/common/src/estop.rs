@@ -0,0 +1,360 @@
+//! Emergency stop coordinator, configured by `[estop]`.
+//!
+//! The first actuator to enter Emergency triggers a stop. Every actuator, the one that
+//! triggered it included, reacts by holding its status machine in SafeStop and driving
+//! its output to `safe_output`; the time from the trigger to each reaction is measured
+//! against `reaction_deadline_us`. The stop stays latched, and no actuator leaves
+//! SafeStop, until the stop itself is acknowledged.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+
+use crate::config::EStopConfig;
+use crate::metrics::{LatencyHistograms, Metric, Stage};
+use crate::ActuatorType;
+
+/// One emergency stop, numbered from 1 in the order they were triggered.
+#[derive(Debug, Clone, Copy)]
+pub struct EStop {
+    pub id: u64,
+    pub origin: ActuatorType, // The actuator whose Emergency triggered it
+    pub sensor_id: u64,       // Sample it was handling at the time
+    pub triggered_ns: u64,
+}
+
+/// How long one actuator took to hold its safe output after a stop was triggered.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct EStopReaction {
+    pub estop_id: u64,
+    pub origin: ActuatorType,
+    pub actuator: ActuatorType,
+    pub triggered_ns: u64,
+    pub reacted_ns: u64,
+    pub reaction_ns: u64,
+    pub deadline_met: bool,
+}
+
+/// Reaction figures for one run, against `reaction_deadline_us`.
+#[derive(Debug, Clone, Default)]
+pub struct EStopSummary {
+    pub stops: usize,
+    pub reactions: usize,
+    pub missed_deadlines: usize,
+    pub p99_us: f64,
+    pub max_us: f64,
+}
+
+impl EStopSummary {
+    pub fn from_reactions(reactions: &[EStopReaction]) -> Self {
+        let mut histograms = LatencyHistograms::new();
+        let mut stops = Vec::new();
+        for reaction in reactions {
+            histograms.record_estop_reaction(reaction);
+            if !stops.contains(&reaction.estop_id) {
+                stops.push(reaction.estop_id);
+            }
+        }
+        let histogram = histograms.get(Stage::All, Metric::EStopReaction);
+        EStopSummary {
+            stops: stops.len(),
+            reactions: reactions.len(),
+            missed_deadlines: reactions.iter().filter(|reaction| !reaction.deadline_met).count(),
+            p99_us: histogram.value_at_percentile(99.0) as f64 / 1000.0,
+            max_us: histogram.max() as f64 / 1000.0,
+        }
+    }
+}
+
+type Waker = Box<dyn Fn() + Send + Sync>;
+
+/// The run's e-stop latch, shared by every actuator through its `EStopHandle`.
+pub struct EStopCoordinator {
+    config: EStopConfig,
+    triggered: AtomicU64,         // Id of the last stop triggered; 0 before the first
+    acknowledged: AtomicU64,      // Id of the last stop acknowledged
+    last: Mutex<Option<EStop>>,   // The stop `triggered` refers to
+    next_acknowledgement: Mutex<usize>, // Into `acknowledge_at_secs`
+    wakers: Mutex<Vec<Waker>>,
+}
+
+impl EStopCoordinator {
+    pub fn new(config: EStopConfig) -> Self {
+        Self {
+            config,
+            triggered: AtomicU64::new(0),
+            acknowledged: AtomicU64::new(0),
+            last: Mutex::new(None),
+            next_acknowledgement: Mutex::new(0),
+            wakers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Adds `waker`, run on the triggering actuator's thread for every stop, so runtimes
+    /// can wake actuators that are waiting for their next sample.
+    pub fn on_trigger(&self, waker: impl Fn() + Send + Sync + 'static) {
+        self.wakers.lock().unwrap().push(Box::new(waker));
+    }
+
+    /// `actuator`'s end of the coordinator.
+    pub fn handle(self: &Arc<Self>, actuator: ActuatorType) -> EStopHandle {
+        EStopHandle { coordinator: Arc::clone(self), actuator, seen: 0, held: false }
+    }
+
+    pub fn config(&self) -> &EStopConfig {
+        &self.config
+    }
+
+    pub fn is_latched(&self) -> bool {
+        self.acknowledged.load(Ordering::Acquire) < self.triggered.load(Ordering::Acquire)
+    }
+
+    fn trigger(&self, origin: ActuatorType, sensor_id: u64, now_ns: u64) -> Option<EStop> {
+        if !self.config.enabled {
+            return None;
+        }
+        let stop = {
+            let mut last = self.last.lock().unwrap();
+            if self.is_latched() {
+                return None;
+            }
+            let stop = EStop { id: self.triggered.load(Ordering::Acquire) + 1, origin, sensor_id, triggered_ns: now_ns };
+            *last = Some(stop);
+            self.triggered.store(stop.id, Ordering::Release);
+            stop
+        };
+        for waker in self.wakers.lock().unwrap().iter() {
+            waker();
+        }
+        Some(stop)
+    }
+
+    /// The latest stop, when one was triggered after stop `seen`.
+    fn since(&self, seen: u64) -> Option<EStop> {
+        if self.triggered.load(Ordering::Acquire) <= seen {
+            return None;
+        }
+        *self.last.lock().unwrap()
+    }
+
+    fn acknowledge(&self) -> Option<EStop> {
+        let last = self.last.lock().unwrap();
+        let stop = (*last)?;
+        if !self.is_latched() {
+            return None;
+        }
+        self.acknowledged.store(stop.id, Ordering::Release);
+        Some(stop)
+    }
+
+    fn acknowledge_due(&self, t_secs: f64, now_ns: u64) -> Option<EStop> {
+        // Scheduled acknowledgements are used up when due, latched or not
+        let mut scheduled = false;
+        {
+            let mut next = self.next_acknowledgement.lock().unwrap();
+            while self.config.acknowledge_at_secs.get(*next).is_some_and(|&at_secs| at_secs <= t_secs) {
+                *next += 1;
+                scheduled = true;
+            }
+        }
+
+        let last = self.last.lock().unwrap();
+        let stop = (*last)?;
+        let held_ns = now_ns.saturating_sub(stop.triggered_ns);
+        let auto = self.config.auto_acknowledge_ms.is_some_and(|ms| held_ns >= ms.saturating_mul(1_000_000));
+        if !self.is_latched() || !(scheduled || auto) {
+            return None;
+        }
+        self.acknowledged.store(stop.id, Ordering::Release);
+        Some(stop)
+    }
+}
+
+/// One actuator's view of the coordinator: which stops it has reacted to, and whether
+/// one is still holding it.
+pub struct EStopHandle {
+    coordinator: Arc<EStopCoordinator>,
+    actuator: ActuatorType,
+    seen: u64, // Last stop reacted to
+    held: bool,
+}
+
+impl EStopHandle {
+    /// Triggers a stop unless one is already latched; returns it when this call did.
+    pub fn trigger(&self, sensor_id: u64, now_ns: u64) -> Option<EStop> {
+        self.coordinator.trigger(self.actuator, sensor_id, now_ns)
+    }
+
+    /// A stop this actuator hasn't reacted to yet. Taking it marks it seen and holds the
+    /// actuator until the stop is acknowledged.
+    pub fn take_pending(&mut self) -> Option<EStop> {
+        let stop = self.coordinator.since(self.seen)?;
+        self.seen = stop.id;
+        self.held = true;
+        Some(stop)
+    }
+
+    /// The reaction to `stop`, for a safe output applied at `now_ns`.
+    pub fn reacted(&self, stop: &EStop, now_ns: u64) -> EStopReaction {
+        let reaction_ns = now_ns.saturating_sub(stop.triggered_ns);
+        EStopReaction {
+            estop_id: stop.id,
+            origin: stop.origin,
+            actuator: self.actuator,
+            triggered_ns: stop.triggered_ns,
+            reacted_ns: now_ns,
+            reaction_ns,
+            deadline_met: reaction_ns <= self.coordinator.config.reaction_deadline().as_nanos() as u64,
+        }
+    }
+
+    /// Whether the stop holding this actuator has just been acknowledged; true once per stop.
+    pub fn take_release(&mut self) -> bool {
+        let released = self.held && self.coordinator.acknowledged.load(Ordering::Acquire) >= self.seen;
+        if released {
+            self.held = false;
+        }
+        released
+    }
+
+    /// Acknowledges the latched stop at once, as an operator does; returns it when this
+    /// call did.
+    pub fn acknowledge(&self) -> Option<EStop> {
+        self.coordinator.acknowledge()
+    }
+
+    /// Applies any acknowledgement due by `t_secs` of nominal sample time, or by
+    /// `auto_acknowledge_ms`, to the latched stop; returns the stop when this call did.
+    pub fn acknowledge_due(&self, t_secs: f64, now_ns: u64) -> Option<EStop> {
+        self.coordinator.acknowledge_due(t_secs, now_ns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    fn coordinator(config: EStopConfig) -> Arc<EStopCoordinator> {
+        Arc::new(EStopCoordinator::new(config))
+    }
+
+    #[test]
+    fn a_stop_holds_every_actuator_until_it_is_acknowledged() {
+        let estop = coordinator(EStopConfig { acknowledge_at_secs: vec![1.0], ..EStopConfig::default() });
+        let mut handles = ActuatorType::ALL.map(|actuator| estop.handle(actuator));
+
+        let stop = handles[0].trigger(7, 1_000).expect("nothing latched yet");
+        assert_eq!((stop.id, stop.origin, stop.sensor_id), (1, ActuatorType::Gripper, 7));
+        assert!(estop.is_latched());
+        for handle in &mut handles {
+            assert_eq!(handle.take_pending().map(|stop| stop.id), Some(1));
+            assert!(handle.take_pending().is_none(), "reacted to once");
+            assert!(!handle.take_release());
+        }
+
+        assert!(handles[1].acknowledge_due(0.5, 2_000).is_none());
+        assert!(estop.is_latched());
+        assert!(handles.iter_mut().all(|handle| !handle.take_release()));
+
+        assert_eq!(handles[2].acknowledge_due(1.0, 3_000).map(|stop| stop.id), Some(1));
+        assert!(!estop.is_latched());
+        for handle in &mut handles {
+            assert!(handle.take_release());
+            assert!(!handle.take_release(), "released once");
+        }
+        assert!(handles[0].acknowledge_due(2.0, 4_000).is_none(), "the schedule is used up");
+    }
+
+    #[test]
+    fn a_second_trip_before_the_acknowledgement_stays_latched() {
+        let estop = coordinator(EStopConfig { auto_acknowledge_ms: Some(1), ..EStopConfig::default() });
+        let mut gripper = estop.handle(ActuatorType::Gripper);
+        let mut motor = estop.handle(ActuatorType::Motor);
+
+        assert!(gripper.trigger(1, 0).is_some());
+        assert!(motor.trigger(2, 100).is_none());
+        assert!(estop.is_latched());
+        let stop = motor.take_pending().unwrap();
+        assert_eq!((stop.id, stop.origin, stop.sensor_id), (1, ActuatorType::Gripper, 1));
+        gripper.take_pending().unwrap();
+
+        assert!(motor.acknowledge_due(0.0, 999_999).is_none());
+        assert!(estop.is_latched() && !motor.take_release());
+        assert_eq!(motor.acknowledge_due(0.0, 1_000_000).map(|stop| stop.id), Some(1));
+        assert!(gripper.take_release() && motor.take_release());
+
+        // Once acknowledged, the next trip is a new stop that holds again
+        let stop = motor.trigger(3, 2_000_000).expect("the first stop was acknowledged");
+        assert_eq!((stop.id, stop.origin), (2, ActuatorType::Motor));
+        assert_eq!(gripper.take_pending().map(|stop| stop.id), Some(2));
+        assert!(!gripper.take_release());
+    }
+
+    #[test]
+    fn an_operator_acknowledgement_releases_every_actuator_at_once() {
+        let estop = coordinator(EStopConfig::default());
+        let mut handles = ActuatorType::ALL.map(|actuator| estop.handle(actuator));
+        assert!(handles[0].acknowledge().is_none(), "nothing to acknowledge yet");
+
+        handles[1].trigger(4, 0).unwrap();
+        handles.iter_mut().for_each(|handle| assert!(handle.take_pending().is_some()));
+        assert!(handles[0].acknowledge_due(60.0, 60_000_000_000).is_none(), "nothing else acknowledges by default");
+
+        assert_eq!(handles[2].acknowledge().map(|stop| stop.id), Some(1));
+        assert!(handles[0].acknowledge().is_none(), "acknowledged once");
+        assert!(!estop.is_latched());
+        assert!(handles.iter_mut().all(|handle| handle.take_release()));
+    }
+
+    #[test]
+    fn the_longest_auto_acknowledgement_never_expires() {
+        let estop = coordinator(EStopConfig { auto_acknowledge_ms: Some(u64::MAX), ..EStopConfig::default() });
+        let handle = estop.handle(ActuatorType::Gripper);
+        handle.trigger(1, 0).unwrap();
+        assert!(handle.acknowledge_due(0.0, u64::MAX - 1).is_none());
+        assert!(estop.is_latched());
+    }
+
+    #[test]
+    fn wakers_run_once_per_stop_and_not_when_disabled() {
+        let woken = Arc::new(AtomicUsize::new(0));
+        let wake_counter = |estop: &EStopCoordinator| {
+            let woken = Arc::clone(&woken);
+            estop.on_trigger(move || {
+                woken.fetch_add(1, Ordering::Relaxed);
+            });
+        };
+
+        let estop = coordinator(EStopConfig::default());
+        wake_counter(&estop);
+        let handle = estop.handle(ActuatorType::Stabilizer);
+        handle.trigger(1, 0);
+        handle.trigger(2, 10);
+        assert_eq!(woken.load(Ordering::Relaxed), 1);
+
+        let disabled = coordinator(EStopConfig { enabled: false, ..EStopConfig::default() });
+        wake_counter(&disabled);
+        assert!(disabled.handle(ActuatorType::Stabilizer).trigger(1, 0).is_none());
+        assert!(!disabled.is_latched());
+        assert_eq!(woken.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn reactions_are_measured_against_the_deadline() {
+        let estop = coordinator(EStopConfig { reaction_deadline_us: 1_000, ..EStopConfig::default() });
+        let mut gripper = estop.handle(ActuatorType::Gripper);
+        let motor = estop.handle(ActuatorType::Motor);
+        gripper.trigger(1, 5_000).unwrap();
+        let stop = gripper.take_pending().unwrap();
+
+        let on_time = gripper.reacted(&stop, 1_005_000);
+        let late = motor.reacted(&stop, 1_005_001);
+        assert!(on_time.deadline_met && !late.deadline_met);
+        assert_eq!((late.actuator, late.origin, late.reaction_ns), (ActuatorType::Motor, ActuatorType::Gripper, 1_000_001));
+
+        let summary = EStopSummary::from_reactions(&[on_time, late]);
+        assert_eq!((summary.stops, summary.reactions, summary.missed_deadlines), (1, 2, 1));
+    }
+}
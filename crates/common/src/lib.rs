@@ -1,16 +1,44 @@
 use serde::{Deserialize, Serialize};
 
 pub mod metrics;
+pub mod clock;
 pub mod pid;
+pub mod control;
+pub mod status;
+pub mod estop;
 pub mod config;
+mod config_validation;
 pub mod diagnostics;
 pub mod sync_strategies;
 pub mod dashboard;
+pub mod load_generator;
+pub mod rng;
+pub mod sensor_model;
+pub mod replay;
+pub mod capture;
+pub mod plant;
+pub mod shared_resource;
+pub mod sweep;
+pub mod trace;
+pub mod chrome_trace;
+pub mod pipeline;
+pub mod runtime;
+pub mod shutdown;
+pub mod workload;
 
 pub use metrics::BenchmarkRecorder;
-pub use config::ExperimentConfig;
+pub use clock::{Clock, MonotonicClock, SharedClock, VirtualClock};
+pub use config::{ConfigError, ExperimentConfig};
 pub use diagnostics::SharedDiagnostics;
 pub use dashboard::{DashboardBuffer, DashboardData, MetricsSnapshot};
+pub use load_generator::{LoadGenerator, LoadProfile};
+pub use workload::Workload;
+pub use sensor_model::{SensorInputs, SensorModel, SensorReading, SensorSource, SourceSample};
+pub use trace::{StageTimestamps, TraceDrop, TraceRecord};
+pub use runtime::{Runtime, RuntimeError, RuntimeStatus};
+pub use shutdown::ShutdownReport;
+pub use capture::{CaptureFile, CaptureRecord};
+pub use plant::{LoopPerformance, PlantLoop};
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct SensorData {
@@ -19,6 +47,7 @@ pub struct SensorData {
     pub force: f64,
     pub position: f64,
     pub temperature: f64,
+    pub stamps: StageTimestamps, // Filled in by each stage as the sample moves through the pipeline
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
@@ -35,6 +64,19 @@ pub enum ActuatorType {
     Stabilizer,
 }
 
+impl ActuatorType {
+    pub const ALL: [ActuatorType; 3] = [ActuatorType::Gripper, ActuatorType::Motor, ActuatorType::Stabilizer];
+
+    /// Pipeline stage index, with the sensor as stage 0.
+    pub fn stage_index(self) -> usize {
+        match self {
+            ActuatorType::Gripper => 1,
+            ActuatorType::Motor => 2,
+            ActuatorType::Stabilizer => 3,
+        }
+    }
+}
+
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Feedback {
@@ -43,18 +85,25 @@ pub struct Feedback {
     pub timestamp: u64,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+/// Where an actuator is in its status machine (see `status`).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 pub enum ActuatorStatus {
+    Init,
     Normal,
     Correcting,
-    Emergency,
+    Degraded,   // Missing its deadlines
+    Emergency,  // Latched until acknowledged
+    SafeStop,   // Output held safe, latched until acknowledged
+    Recovering, // Acknowledged, proving itself before Normal
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct ActuatorFeedback {
     pub sensor_id: u64,
+    pub actuator: ActuatorType,
     pub status: ActuatorStatus,
     pub control_output: f64,
     pub error: f64,
     pub timestamp: u64,
+    pub stamps: StageTimestamps, // The sample's stamps up to and including feedback_sent_ns
 }
\ No newline at end of file
@@ -0,0 +1,198 @@
+use std::hint::black_box;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use crate::config::{CpuLoadConfig, LoadWorkerKind};
+
+const DUTY_WINDOW: Duration = Duration::from_millis(10);
+const CACHE_LINE: usize = 64;
+const SPIN_BATCH: u64 = 1024; // Work units between clock/shutdown checks
+const SYSCALL_BATCH: u64 = 16; // Syscalls are slow enough to check far more often
+
+/// Description of the background load that was actually applied during a run.
+/// Stored next to the CycleResults so stress runs can be compared like for like.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct LoadProfile {
+    pub threads: usize,
+    pub workers: Vec<LoadWorkerKind>,
+    pub cache_buffer_kb: usize,
+    pub duty_cycle_percent: u8,
+    pub active_ms: u64,
+    pub iterations: Vec<u64>, // Work units completed by each worker thread
+}
+
+impl LoadProfile {
+    pub fn total_iterations(&self) -> u64 {
+        self.iterations.iter().sum()
+    }
+}
+
+/// Spawns `cpu_load_threads` background workers that compete with the
+/// sensor/actuator pipeline until `stop()` is called (or the generator is dropped).
+pub struct LoadGenerator {
+    shutdown: Arc<AtomicBool>,
+    handles: Vec<JoinHandle<u64>>,
+    workers: Vec<LoadWorkerKind>,
+    config: CpuLoadConfig,
+    started: Instant,
+}
+
+impl LoadGenerator {
+    pub fn start(threads: usize, config: &CpuLoadConfig) -> Self {
+        let shutdown = Arc::new(AtomicBool::new(false));
+        let kinds = if config.workers.is_empty() {
+            vec![LoadWorkerKind::Spin]
+        } else {
+            config.workers.clone()
+        };
+
+        let workers: Vec<LoadWorkerKind> = (0..threads).map(|i| kinds[i % kinds.len()]).collect();
+        let handles = workers
+            .iter()
+            .enumerate()
+            .map(|(i, &kind)| {
+                let shutdown = Arc::clone(&shutdown);
+                let cfg = config.clone();
+                thread::Builder::new()
+                    .name(format!("load-{}-{:?}", i, kind).to_lowercase())
+                    .spawn(move || run_worker(kind, &cfg, &shutdown))
+                    .expect("Failed to spawn load generator thread")
+            })
+            .collect();
+
+        Self {
+            shutdown,
+            handles,
+            workers,
+            config: config.clone(),
+            started: Instant::now(),
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.handles.is_empty()
+    }
+
+    /// Signals every worker, joins them and returns the load that was applied.
+    pub fn stop(mut self) -> LoadProfile {
+        self.shutdown.store(true, Ordering::Relaxed);
+        let iterations = std::mem::take(&mut self.handles)
+            .into_iter()
+            .map(|h| h.join().unwrap_or(0))
+            .collect();
+
+        LoadProfile {
+            threads: self.workers.len(),
+            workers: self.workers.clone(),
+            cache_buffer_kb: self.config.cache_buffer_kb,
+            duty_cycle_percent: self.config.duty_cycle_percent,
+            active_ms: self.started.elapsed().as_millis() as u64,
+            iterations,
+        }
+    }
+}
+
+impl Drop for LoadGenerator {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::Relaxed);
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn run_worker(kind: LoadWorkerKind, config: &CpuLoadConfig, shutdown: &AtomicBool) -> u64 {
+    let duty = config.duty_cycle_percent.clamp(1, 100) as u32;
+    let busy_slice = DUTY_WINDOW * duty / 100;
+    let mut buffer = match kind {
+        LoadWorkerKind::CacheThrash => vec![0u8; config.cache_buffer_kb.max(1) * 1024],
+        _ => Vec::new(),
+    };
+    let mut state = 0x9E37_79B9_7F4A_7C15u64;
+    let mut offset = 0usize;
+    let mut iterations = 0u64;
+    let batch = match kind {
+        LoadWorkerKind::Syscall => SYSCALL_BATCH,
+        _ => SPIN_BATCH,
+    };
+
+    while !shutdown.load(Ordering::Relaxed) {
+        let window_start = Instant::now();
+
+        // Busy phase: only check the clock and flag once per batch
+        loop {
+            for _ in 0..batch {
+                match kind {
+                    LoadWorkerKind::Spin => {
+                        state ^= state << 13;
+                        state ^= state >> 7;
+                        state ^= state << 17;
+                        black_box(state);
+                    }
+                    LoadWorkerKind::CacheThrash => {
+                        buffer[offset] = buffer[offset].wrapping_add(1);
+                        offset = (offset + CACHE_LINE * 17) % buffer.len();
+                        black_box(&buffer[offset]);
+                    }
+                    LoadWorkerKind::Syscall => {
+                        thread::yield_now();
+                        black_box(std::fs::metadata(".").is_ok());
+                    }
+                }
+            }
+            iterations += batch;
+
+            if shutdown.load(Ordering::Relaxed) || window_start.elapsed() >= busy_slice {
+                break;
+            }
+        }
+
+        if duty < 100 {
+            let remaining = DUTY_WINDOW.saturating_sub(window_start.elapsed());
+            thread::sleep(remaining);
+        }
+    }
+
+    iterations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn workers_are_assigned_round_robin_and_their_work_is_accounted() {
+        let config = CpuLoadConfig {
+            workers: vec![LoadWorkerKind::Spin, LoadWorkerKind::CacheThrash],
+            cache_buffer_kb: 4,
+            duty_cycle_percent: 50,
+        };
+        let generator = LoadGenerator::start(3, &config);
+        assert!(generator.is_active());
+        thread::sleep(Duration::from_millis(30));
+        let profile = generator.stop();
+
+        assert_eq!(profile.threads, 3);
+        assert_eq!(profile.workers, [LoadWorkerKind::Spin, LoadWorkerKind::CacheThrash, LoadWorkerKind::Spin]);
+        assert_eq!((profile.cache_buffer_kb, profile.duty_cycle_percent), (4, 50));
+        assert!(profile.active_ms >= 30);
+        assert_eq!(profile.iterations.len(), 3);
+        assert!(profile.iterations.iter().all(|&iterations| iterations > 0 && iterations % SPIN_BATCH == 0));
+        assert_eq!(profile.total_iterations(), profile.iterations.iter().sum::<u64>());
+    }
+
+    #[test]
+    fn no_threads_applies_no_load_and_no_workers_means_spin() {
+        let idle = LoadGenerator::start(0, &CpuLoadConfig::default());
+        assert!(!idle.is_active());
+        let profile = idle.stop();
+        assert_eq!((profile.threads, profile.total_iterations()), (0, 0));
+        assert!(profile.workers.is_empty());
+
+        let profile = LoadGenerator::start(1, &CpuLoadConfig { workers: Vec::new(), ..CpuLoadConfig::default() }).stop();
+        assert_eq!(profile.workers, [LoadWorkerKind::Spin]);
+        assert_eq!(profile.iterations.len(), 1);
+    }
+}
@@ -1,7 +1,19 @@
-use std::sync::{atomic::{AtomicUsize, Ordering}, Arc, Mutex};
-use std::time::Instant;
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{atomic::{AtomicBool, AtomicUsize, Ordering}, Arc, Mutex, OnceLock};
+use hdrhistogram::Histogram;
 use serde::Serialize;
 use crate::ActuatorType;
+use crate::capture::{Capture, CaptureFile, CaptureRecord, CaptureSummary};
+use crate::clock::{MonotonicClock, SharedClock};
+use crate::load_generator::LoadProfile;
+use crate::plant::LoopPerformance;
+use crate::chrome_trace::ChromeTrace;
+use crate::config::{ExperimentConfig, RecordingConfig};
+use crate::estop::EStopReaction;
+use crate::shutdown::ShutdownReport;
+use crate::status::StatusTransition;
+use crate::trace::{TraceHistograms, TraceLatency, TraceRecord};
 
 #[derive(Debug, Serialize, Clone)]
 pub struct CycleResult {
@@ -13,6 +25,8 @@ pub struct CycleResult {
     pub lock_wait_ns: u64,
     pub deadline_met: bool,
     pub lateness_ns: i64,
+    pub release_jitter_ns: Option<i64>, // Actual minus scheduled release; periodic (sensor) stages only
+    pub control_ns: Option<u64>,        // Controller update cost (wall time; fixed in the sim); actuator stages only
 }
 
 /// Thread-safe recorder with Internal Mutability.
@@ -20,21 +34,75 @@ pub struct CycleResult {
 #[derive(Clone)]
 pub struct BenchmarkRecorder {
     // The Mutex is INSIDE, so users don't need to wrap the struct
-    results: Arc<Mutex<Vec<CycleResult>>>,
+    results: Arc<Mutex<VecDeque<CycleResult>>>, // The most recent `max_results`
+    max_results: usize,
     pub missed_deadlines: Arc<AtomicUsize>,
-    #[allow(dead_code)]
-    start_time: Instant,
+    load_profile: Arc<Mutex<Option<LoadProfile>>>,
+    stats: Arc<Mutex<RunStats>>, // Every result, however many were retained
+    traces: Arc<Mutex<VecDeque<TraceRecord>>>, // The most recent `max_traces`
+    max_traces: usize,
+    trace_histograms: Arc<Mutex<TraceHistograms>>, // Every trace, however many were retained
+    recorded_traces: Arc<AtomicUsize>,
+    closed: Arc<AtomicBool>,
+    rejected: Arc<AtomicUsize>, // Records that arrived after close()
+    shutdown_report: Arc<Mutex<Option<ShutdownReport>>>,
+    clock: SharedClock, // The run's time source; stages stamp samples with it
+    capture: Arc<OnceLock<Capture>>,
+    capture_summary: Arc<Mutex<Option<CaptureSummary>>>,
+    loop_performance: Arc<Mutex<Vec<LoopPerformance>>>, // Closed-loop plants only
+    status_transitions: Arc<Mutex<Vec<StatusTransition>>>,
+    estop_reactions: Arc<Mutex<Vec<EStopReaction>>>,
+}
+
+impl Default for BenchmarkRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl BenchmarkRecorder {
+    /// Recorder for a run on wall time, starting now.
     pub fn new() -> Self {
+        Self::with_clock(Arc::new(MonotonicClock::start()))
+    }
+
+    pub fn with_clock(clock: SharedClock) -> Self {
+        Self::for_run(clock, &RecordingConfig::default())
+    }
+
+    /// Recorder on `clock` that retains per-cycle results as `recording` allows.
+    pub fn for_run(clock: SharedClock, recording: &RecordingConfig) -> Self {
         Self {
-            results: Arc::new(Mutex::new(Vec::with_capacity(10_000))),
+            results: Arc::new(Mutex::new(VecDeque::with_capacity(recording.max_results.min(10_000)))),
+            max_results: recording.max_results.max(1),
             missed_deadlines: Arc::new(AtomicUsize::new(0)),
-            start_time: Instant::now(),
+            load_profile: Arc::new(Mutex::new(None)),
+            stats: Arc::new(Mutex::new(RunStats::new())),
+            traces: Arc::new(Mutex::new(VecDeque::with_capacity(recording.max_traces.min(10_000)))),
+            max_traces: recording.max_traces.max(1),
+            trace_histograms: Arc::new(Mutex::new(TraceHistograms::new())),
+            recorded_traces: Arc::new(AtomicUsize::new(0)),
+            closed: Arc::new(AtomicBool::new(false)),
+            rejected: Arc::new(AtomicUsize::new(0)),
+            shutdown_report: Arc::new(Mutex::new(None)),
+            clock,
+            capture: Arc::new(OnceLock::new()),
+            capture_summary: Arc::new(Mutex::new(None)),
+            loop_performance: Arc::new(Mutex::new(Vec::new())),
+            status_transitions: Arc::new(Mutex::new(Vec::new())),
+            estop_reactions: Arc::new(Mutex::new(Vec::new())),
         }
     }
 
+    pub fn clock(&self) -> SharedClock {
+        Arc::clone(&self.clock)
+    }
+
+    /// Nanoseconds since the start of the run, on the run's clock.
+    pub fn now_ns(&self) -> u64 {
+        self.clock.now_ns()
+    }
+
     pub fn record(&self, result: CycleResult) {
         // We handle the locking here, internally.
         // This is the "Shared Resource" access for the assignment.
@@ -42,19 +110,185 @@ impl BenchmarkRecorder {
         // This method measures the actual lock acquisition time
         let _lock_start = std::time::Instant::now();
         if let Ok(mut data) = self.results.lock() {
+            // Checked under the lock so nothing lands after close() returns
+            if self.closed.load(Ordering::Acquire) {
+                self.rejected.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
             // The lock_wait_ns in result is measured by caller before calling record()
             if !result.deadline_met {
                 self.missed_deadlines.fetch_add(1, Ordering::Relaxed);
             }
-            data.push(result);
+            if let Ok(mut stats) = self.stats.lock() {
+                stats.record(&result);
+            }
+            if data.len() == self.max_results {
+                data.pop_front();
+            }
+            data.push_back(result);
             // Lock is released here when 'data' goes out of scope
         }
     }
 
+    /// The retained per-cycle results, oldest first: every result unless the run
+    /// recorded more than `recording.max_results`.
     pub fn get_results(&self) -> Vec<CycleResult> {
-        self.results.lock().unwrap().clone()
+        self.results.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Results recorded so far, retained or not.
+    pub fn recorded_results(&self) -> usize {
+        self.stats.lock().unwrap().totals(Stage::All).cycles
+    }
+
+    /// Totals and percentiles over every recorded result.
+    pub fn stats(&self) -> RunStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    pub fn summary(&self, stage: Stage) -> RunSummary {
+        self.stats.lock().unwrap().summary(stage)
+    }
+
+    pub fn jitter(&self) -> JitterSummary {
+        self.stats.lock().unwrap().jitter()
+    }
+
+    /// Stores a completed end-to-end trace (recorded once the sensor consumes the feedback).
+    /// Only the most recent `recording.max_traces` are kept; latencies cover them all.
+    pub fn record_trace(&self, trace: TraceRecord) {
+        if let Ok(mut traces) = self.traces.lock() {
+            if self.closed.load(Ordering::Acquire) {
+                self.rejected.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+            if let Ok(mut histograms) = self.trace_histograms.lock() {
+                histograms.record(&trace);
+            }
+            self.recorded_traces.fetch_add(1, Ordering::Relaxed);
+            if traces.len() == self.max_traces {
+                traces.pop_front();
+            }
+            traces.push_back(trace);
+        }
+    }
+
+    /// Traces recorded so far, retained or not.
+    pub fn recorded_traces(&self) -> usize {
+        self.recorded_traces.load(Ordering::Relaxed)
+    }
+
+    /// End-to-end latency percentiles per actuator over every recorded trace.
+    pub fn trace_latencies(&self) -> Vec<TraceLatency> {
+        self.trace_histograms.lock().unwrap().latencies()
+    }
+
+    /// Stores an actuator's reaction to an e-stop, with its own deadline and percentiles.
+    pub fn record_estop_reaction(&self, reaction: EStopReaction) {
+        if let Ok(mut reactions) = self.estop_reactions.lock() {
+            if self.closed.load(Ordering::Acquire) {
+                self.rejected.fetch_add(1, Ordering::Relaxed);
+                return;
+            }
+            if let Ok(mut stats) = self.stats.lock() {
+                stats.histograms.record_estop_reaction(&reaction);
+            }
+            reactions.push(reaction);
+        }
+    }
+
+    pub fn estop_reactions(&self) -> Vec<EStopReaction> {
+        self.estop_reactions.lock().unwrap().clone()
+    }
+
+    /// Starts writing this run's capture to `file`. A run has at most one capture.
+    pub fn start_capture(&self, file: CaptureFile) {
+        let _ = self.capture.set(Capture::start(file, self.clock()));
+    }
+
+    /// Adds `record` to the run's capture, if it has one.
+    pub fn capture(&self, record: CaptureRecord) {
+        if let Some(capture) = self.capture.get() {
+            capture.record(record);
+        }
+    }
+
+    /// Seals the recorder: every later `record`/`record_trace` is counted and dropped,
+    /// so results read after this can't change under the reader. The capture, if any,
+    /// is written out and closed.
+    pub fn close(&self) {
+        {
+            let _results = self.results.lock();
+            let _traces = self.traces.lock();
+            let _reactions = self.estop_reactions.lock();
+            self.closed.store(true, Ordering::Release);
+        }
+        if let Some(summary) = self.capture.get().and_then(Capture::finish) {
+            *self.capture_summary.lock().unwrap() = Some(summary);
+        }
+    }
+
+    /// How the run's capture ended, once the recorder is closed.
+    pub fn capture_summary(&self) -> Option<CaptureSummary> {
+        self.capture_summary.lock().unwrap().clone()
+    }
+
+    /// Records and traces dropped because they arrived after `close`.
+    pub fn rejected_records(&self) -> usize {
+        self.rejected.load(Ordering::Relaxed)
+    }
+
+    pub fn set_shutdown_report(&self, report: ShutdownReport) {
+        *self.shutdown_report.lock().unwrap() = Some(report);
+    }
+
+    /// The run's shutdown report, with the records rejected after `close` so far.
+    pub fn shutdown_report(&self) -> Option<ShutdownReport> {
+        let mut report = self.shutdown_report.lock().unwrap().clone()?;
+        report.rejected_records = self.rejected_records();
+        Some(report)
+    }
+
+    /// The retained traces, oldest first.
+    pub fn traces(&self) -> Vec<TraceRecord> {
+        self.traces.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn set_loop_performance(&self, performance: Vec<LoopPerformance>) {
+        *self.loop_performance.lock().unwrap() = performance;
+    }
+
+    pub fn loop_performance(&self) -> Vec<LoopPerformance> {
+        self.loop_performance.lock().unwrap().clone()
     }
 
+    /// The run's actuator status changes, copied from its diagnostics once it's over.
+    pub fn set_status_transitions(&self, transitions: Vec<StatusTransition>) {
+        *self.status_transitions.lock().unwrap() = transitions;
+    }
+
+    pub fn status_transitions(&self) -> Vec<StatusTransition> {
+        self.status_transitions.lock().unwrap().clone()
+    }
+
+    pub fn set_load_profile(&self, profile: LoadProfile) {
+        *self.load_profile.lock().unwrap() = Some(profile);
+    }
+
+    pub fn load_profile(&self) -> Option<LoadProfile> {
+        self.load_profile.lock().unwrap().clone()
+    }
+
+    /// Tail percentiles per stage and metric for everything recorded so far.
+    pub fn percentiles(&self) -> Vec<PercentileRow> {
+        self.stats.lock().unwrap().histograms.rows()
+    }
+
+    /// Writes the per-cycle results to `filename`, the tail percentiles to a
+    /// `<stem>.percentiles.csv` file next to it, end-to-end traces to
+    /// `<stem>.traces.csv`, closed-loop plant performance to `<stem>.loop.csv`, actuator
+    /// status changes to `<stem>.status.csv`, e-stop reactions to `<stem>.estop.csv` and,
+    /// when background load was applied, its profile to `<stem>.load.toml`.
     pub fn save_to_csv(&self, filename: &str) -> Result<(), Box<dyn std::error::Error>> {
         let data = self.results.lock().unwrap();
         let mut wtr = csv::Writer::from_path(filename)?;
@@ -62,7 +296,676 @@ impl BenchmarkRecorder {
             wtr.serialize(record)?;
         }
         wtr.flush()?;
-        println!("Saved {} records to {}", data.len(), filename);
+        let dropped = self.recorded_results().saturating_sub(data.len());
+        if dropped > 0 {
+            println!("Saved the last {} records to {} ({} earlier ones over recording.max_results)", data.len(), filename, dropped);
+        } else {
+            println!("Saved {} records to {}", data.len(), filename);
+        }
+        drop(data);
+
+        let percentiles_path = std::path::Path::new(filename).with_extension("percentiles.csv");
+        let mut wtr = csv::Writer::from_path(&percentiles_path)?;
+        for row in self.percentiles() {
+            wtr.serialize(row)?;
+        }
+        wtr.flush()?;
+        println!("Saved tail percentiles to {}", percentiles_path.display());
+
+        let traces_path = std::path::Path::new(filename).with_extension("traces.csv");
+        let traces = self.traces.lock().unwrap();
+        let mut wtr = csv::Writer::from_path(&traces_path)?;
+        for trace in traces.iter() {
+            wtr.serialize(trace)?;
+        }
+        wtr.flush()?;
+        let dropped = self.recorded_traces().saturating_sub(traces.len());
+        if dropped > 0 {
+            println!("Saved the last {} end-to-end traces to {} ({} earlier ones over recording.max_traces)",
+                     traces.len(), traces_path.display(), dropped);
+        } else {
+            println!("Saved {} end-to-end traces to {}", traces.len(), traces_path.display());
+        }
+        drop(traces);
+
+        let performance = self.loop_performance();
+        if !performance.is_empty() {
+            let loop_path = std::path::Path::new(filename).with_extension("loop.csv");
+            let mut wtr = csv::Writer::from_path(&loop_path)?;
+            for row in &performance {
+                wtr.serialize(row)?;
+            }
+            wtr.flush()?;
+            println!("Saved closed-loop performance to {}", loop_path.display());
+        }
+
+        let transitions = self.status_transitions();
+        if !transitions.is_empty() {
+            let status_path = std::path::Path::new(filename).with_extension("status.csv");
+            let mut wtr = csv::Writer::from_path(&status_path)?;
+            for transition in &transitions {
+                wtr.serialize(transition)?;
+            }
+            wtr.flush()?;
+            println!("Saved {} status transitions to {}", transitions.len(), status_path.display());
+        }
+
+        let reactions = self.estop_reactions();
+        if !reactions.is_empty() {
+            let estop_path = std::path::Path::new(filename).with_extension("estop.csv");
+            let mut wtr = csv::Writer::from_path(&estop_path)?;
+            for reaction in &reactions {
+                wtr.serialize(reaction)?;
+            }
+            wtr.flush()?;
+            println!("Saved {} e-stop reactions to {}", reactions.len(), estop_path.display());
+        }
+
+        if let Some(profile) = self.load_profile() {
+            let profile_path = std::path::Path::new(filename).with_extension("load.toml");
+            std::fs::write(&profile_path, toml::to_string(&profile)?)?;
+            println!("Saved load profile to {}", profile_path.display());
+        }
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Writes the run as Chrome Trace Event JSON, viewable in Perfetto or chrome://tracing.
+    pub fn save_chrome_trace(&self, filename: &str, process_name: &str, config: &ExperimentConfig) -> Result<(), Box<dyn std::error::Error>> {
+        let trace = ChromeTrace::from_run(process_name, config, &self.traces(), &self.get_results());
+        trace.save(filename)?;
+        println!("Saved {} trace events to {}", trace.trace_events.len(), filename);
+        Ok(())
+    }
+}
+/// Aggregate figures for a set of cycle results, one row of a results table.
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct RunSummary {
+    pub cycles: usize,
+    pub missed_deadlines: usize,
+    pub compliance_percent: f64,
+    pub avg_processing_us: f64,
+    pub max_processing_us: f64,
+    pub avg_latency_us: f64,
+    pub max_latency_us: f64,
+    pub avg_lock_wait_us: f64,
+    pub max_lock_wait_us: f64,
+    pub max_lateness_ns: i64,
+    pub p99_processing_us: f64,
+    pub p50_latency_us: f64,
+    pub p99_latency_us: f64,
+    pub p99_9_latency_us: f64,
+    pub p99_lock_wait_us: f64,
+    pub p99_lateness_ns: u64,
+    pub p99_jitter_us: f64,
+    pub max_jitter_us: f64,
+    pub drift_ns_per_cycle: f64,
+}
+
+impl RunSummary {
+    /// Column names matching `values()`, for tables that prepend their own key columns.
+    pub fn columns() -> [&'static str; 19] {
+        [
+            "cycles", "missed_deadlines", "compliance_percent",
+            "avg_processing_us", "max_processing_us",
+            "avg_latency_us", "max_latency_us",
+            "avg_lock_wait_us", "max_lock_wait_us",
+            "max_lateness_ns",
+            "p99_processing_us",
+            "p50_latency_us", "p99_latency_us", "p99_9_latency_us",
+            "p99_lock_wait_us",
+            "p99_lateness_ns",
+            "p99_jitter_us", "max_jitter_us", "drift_ns_per_cycle",
+        ]
+    }
+
+    pub fn values(&self) -> Vec<String> {
+        vec![
+            self.cycles.to_string(),
+            self.missed_deadlines.to_string(),
+            format!("{:.3}", self.compliance_percent),
+            format!("{:.3}", self.avg_processing_us),
+            format!("{:.3}", self.max_processing_us),
+            format!("{:.3}", self.avg_latency_us),
+            format!("{:.3}", self.max_latency_us),
+            format!("{:.3}", self.avg_lock_wait_us),
+            format!("{:.3}", self.max_lock_wait_us),
+            self.max_lateness_ns.to_string(),
+            format!("{:.3}", self.p99_processing_us),
+            format!("{:.3}", self.p50_latency_us),
+            format!("{:.3}", self.p99_latency_us),
+            format!("{:.3}", self.p99_9_latency_us),
+            format!("{:.3}", self.p99_lock_wait_us),
+            self.p99_lateness_ns.to_string(),
+            format!("{:.3}", self.p99_jitter_us),
+            format!("{:.3}", self.max_jitter_us),
+            format!("{:.3}", self.drift_ns_per_cycle),
+        ]
+    }
+}
+
+/// Release jitter of the periodic sensor stage and the drift of its achieved period.
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct JitterSummary {
+    pub count: usize,
+    pub mean_us: f64,
+    pub std_dev_us: f64,
+    pub p99_us: f64,
+    pub max_us: f64,
+    /// Least-squares slope of jitter against cycle number: how much later each release
+    /// lands than the one before. The achieved period is the configured one plus this.
+    pub drift_ns_per_cycle: f64,
+}
+
+impl JitterSummary {
+    /// Drift relative to the configured period, in parts per million.
+    pub fn drift_ppm(&self, period: std::time::Duration) -> f64 {
+        if period.is_zero() {
+            0.0
+        } else {
+            self.drift_ns_per_cycle / period.as_nanos() as f64 * 1e6
+        }
+    }
+}
+
+/// Running totals for one stage's results.
+#[derive(Debug, Clone, Default)]
+pub struct StageTotals {
+    pub cycles: usize,
+    pub missed_deadlines: usize,
+    pub late_cycles: usize, // Cycles with positive lateness
+    pub processing_sum_ns: u64,
+    pub min_processing_ns: u64,
+    pub max_processing_ns: u64,
+    pub latency_count: usize, // Cycles with a recorded total latency
+    pub latency_sum_ns: u64,
+    pub min_latency_ns: u64,
+    pub max_latency_ns: u64,
+    pub lock_wait_sum_ns: u64,
+    pub max_lock_wait_ns: u64,
+    pub max_lateness_ns: i64,
+    jitter: JitterTrend,
+}
+
+impl StageTotals {
+    fn add(&mut self, r: &CycleResult) {
+        if self.cycles == 0 {
+            self.min_processing_ns = r.processing_time_ns;
+        }
+        self.cycles += 1;
+        if !r.deadline_met {
+            self.missed_deadlines += 1;
+        }
+        if r.lateness_ns > 0 {
+            self.late_cycles += 1;
+        }
+        self.processing_sum_ns += r.processing_time_ns;
+        self.min_processing_ns = self.min_processing_ns.min(r.processing_time_ns);
+        self.max_processing_ns = self.max_processing_ns.max(r.processing_time_ns);
+        if r.total_latency_ns > 0 {
+            if self.latency_count == 0 {
+                self.min_latency_ns = r.total_latency_ns;
+            }
+            self.latency_count += 1;
+            self.latency_sum_ns += r.total_latency_ns;
+            self.min_latency_ns = self.min_latency_ns.min(r.total_latency_ns);
+            self.max_latency_ns = self.max_latency_ns.max(r.total_latency_ns);
+        }
+        self.lock_wait_sum_ns += r.lock_wait_ns;
+        self.max_lock_wait_ns = self.max_lock_wait_ns.max(r.lock_wait_ns);
+        self.max_lateness_ns = self.max_lateness_ns.max(r.lateness_ns);
+        self.jitter.add(r);
+    }
+
+    pub fn compliance_percent(&self) -> f64 {
+        if self.cycles == 0 {
+            0.0
+        } else {
+            (self.cycles - self.missed_deadlines) as f64 / self.cycles as f64 * 100.0
+        }
+    }
+
+    pub fn avg_processing_ns(&self) -> f64 {
+        if self.cycles == 0 { 0.0 } else { self.processing_sum_ns as f64 / self.cycles as f64 }
+    }
+
+    pub fn avg_latency_ns(&self) -> f64 {
+        if self.latency_count == 0 { 0.0 } else { self.latency_sum_ns as f64 / self.latency_count as f64 }
+    }
+
+    pub fn avg_lock_wait_ns(&self) -> f64 {
+        if self.cycles == 0 { 0.0 } else { self.lock_wait_sum_ns as f64 / self.cycles as f64 }
+    }
+}
+
+/// Everything a run's summaries are built from, accumulated one result at a time:
+/// running totals and HDR histograms per stage. Its size doesn't grow with the run.
+#[derive(Clone)]
+pub struct RunStats {
+    totals: Vec<StageTotals>, // Indexed by Stage::index
+    histograms: LatencyHistograms,
+}
+
+impl Default for RunStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RunStats {
+    pub fn new() -> Self {
+        Self {
+            totals: vec![StageTotals::default(); Stage::ALL.len()],
+            histograms: LatencyHistograms::new(),
+        }
+    }
+
+    pub fn from_results<'a>(results: impl IntoIterator<Item = &'a CycleResult>) -> Self {
+        let mut stats = Self::new();
+        for result in results {
+            stats.record(result);
+        }
+        stats
+    }
+
+    /// Adds a result under its own stage and under `Stage::All`.
+    pub fn record(&mut self, result: &CycleResult) {
+        for stage in [Stage::All, Stage::of(result)] {
+            self.totals[stage.index()].add(result);
+        }
+        self.histograms.record(result);
+    }
+
+    pub fn totals(&self, stage: Stage) -> &StageTotals {
+        &self.totals[stage.index()]
+    }
+
+    pub fn histograms(&self) -> &LatencyHistograms {
+        &self.histograms
+    }
+
+    pub fn summary(&self, stage: Stage) -> RunSummary {
+        let totals = self.totals(stage);
+        let percentile_us = |metric, percentile| self.histograms.get(stage, metric).value_at_percentile(percentile) as f64 / 1000.0;
+        RunSummary {
+            cycles: totals.cycles,
+            missed_deadlines: totals.missed_deadlines,
+            compliance_percent: totals.compliance_percent(),
+            avg_processing_us: totals.avg_processing_ns() / 1000.0,
+            max_processing_us: totals.max_processing_ns as f64 / 1000.0,
+            avg_latency_us: totals.avg_latency_ns() / 1000.0,
+            max_latency_us: totals.max_latency_ns as f64 / 1000.0,
+            avg_lock_wait_us: totals.avg_lock_wait_ns() / 1000.0,
+            max_lock_wait_us: totals.max_lock_wait_ns as f64 / 1000.0,
+            max_lateness_ns: totals.max_lateness_ns,
+            p99_processing_us: percentile_us(Metric::ProcessingTime, 99.0),
+            p50_latency_us: percentile_us(Metric::TotalLatency, 50.0),
+            p99_latency_us: percentile_us(Metric::TotalLatency, 99.0),
+            p99_9_latency_us: percentile_us(Metric::TotalLatency, 99.9),
+            p99_lock_wait_us: percentile_us(Metric::LockWait, 99.0),
+            p99_lateness_ns: self.histograms.get(stage, Metric::Lateness).value_at_percentile(99.0),
+            p99_jitter_us: percentile_us(Metric::ReleaseJitter, 99.0),
+            max_jitter_us: self.histograms.get(stage, Metric::ReleaseJitter).max() as f64 / 1000.0,
+            drift_ns_per_cycle: totals.jitter.drift_ns_per_cycle,
+        }
+    }
+
+    /// Release jitter of the periodic sensor stage.
+    pub fn jitter(&self) -> JitterSummary {
+        let trend = &self.totals(Stage::All).jitter;
+        let histogram = self.histograms.get(Stage::All, Metric::ReleaseJitter);
+        JitterSummary {
+            count: trend.count,
+            mean_us: trend.mean() / 1000.0,
+            std_dev_us: trend.std_dev() / 1000.0,
+            p99_us: histogram.value_at_percentile(99.0) as f64 / 1000.0,
+            max_us: histogram.max() as f64 / 1000.0,
+            drift_ns_per_cycle: trend.drift_ns_per_cycle,
+        }
+    }
+}
+
+/// Running sums for jitter mean, variance and its regression on cycle number.
+#[derive(Debug, Clone, Default)]
+struct JitterTrend {
+    count: usize,
+    sum_x: f64,
+    sum_y: f64,
+    sum_xx: f64,
+    sum_xy: f64,
+    sum_yy: f64,
+    drift_ns_per_cycle: f64,
+}
+
+impl JitterTrend {
+    fn add(&mut self, result: &CycleResult) {
+        let Some(jitter) = result.release_jitter_ns else {
+            return;
+        };
+        let (x, y) = (result.cycle_id as f64, jitter as f64);
+        self.count += 1;
+        self.sum_x += x;
+        self.sum_y += y;
+        self.sum_xx += x * x;
+        self.sum_xy += x * y;
+        self.sum_yy += y * y;
+
+        let n = self.count as f64;
+        let denominator = n * self.sum_xx - self.sum_x * self.sum_x;
+        if denominator > 0.0 {
+            self.drift_ns_per_cycle = (n * self.sum_xy - self.sum_x * self.sum_y) / denominator;
+        }
+    }
+
+    fn mean(&self) -> f64 {
+        if self.count == 0 { 0.0 } else { self.sum_y / self.count as f64 }
+    }
+
+    fn std_dev(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        let mean = self.mean();
+        (self.sum_yy / self.count as f64 - mean * mean).max(0.0).sqrt()
+    }
+}
+
+/// Pipeline stage a result belongs to, plus `All` for the whole pipeline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    All,
+    Sensor,
+    Actuator(ActuatorType),
+}
+
+impl Stage {
+    pub const ALL: [Stage; 5] = [
+        Stage::All,
+        Stage::Sensor,
+        Stage::Actuator(ActuatorType::Gripper),
+        Stage::Actuator(ActuatorType::Motor),
+        Stage::Actuator(ActuatorType::Stabilizer),
+    ];
+
+    pub fn of(result: &CycleResult) -> Stage {
+        result.actuator.map_or(Stage::Sensor, Stage::Actuator)
+    }
+
+    pub fn matches(self, result: &CycleResult) -> bool {
+        self == Stage::All || self == Stage::of(result)
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Stage::All => 0,
+            Stage::Sensor => 1,
+            Stage::Actuator(actuator) => 1 + actuator.stage_index(),
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stage::All => f.write_str("all"),
+            Stage::Sensor => f.write_str("sensor"),
+            Stage::Actuator(actuator) => f.write_str(&format!("{:?}", actuator).to_lowercase()),
+        }
+    }
+}
+
+/// Per-cycle quantity tracked by the latency histograms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    ProcessingTime,
+    TotalLatency,
+    LockWait,
+    Lateness,
+    ReleaseJitter,
+    ControlCost,
+    EStopReaction, // Recorded per e-stop reaction rather than per cycle
+}
+
+impl Metric {
+    pub const ALL: [Metric; 7] = [
+        Metric::ProcessingTime,
+        Metric::TotalLatency,
+        Metric::LockWait,
+        Metric::Lateness,
+        Metric::ReleaseJitter,
+        Metric::ControlCost,
+        Metric::EStopReaction,
+    ];
+
+    /// The metric's value for `result`, or `None` when the stage doesn't have one.
+    fn value_ns(self, result: &CycleResult) -> Option<u64> {
+        match self {
+            Metric::ProcessingTime => Some(result.processing_time_ns),
+            Metric::TotalLatency => Some(result.total_latency_ns),
+            Metric::LockWait => Some(result.lock_wait_ns),
+            Metric::Lateness => Some(result.lateness_ns.max(0) as u64), // Early finishes count as on time
+            Metric::ReleaseJitter => result.release_jitter_ns.map(|jitter| jitter.max(0) as u64),
+            Metric::ControlCost => result.control_ns,
+            Metric::EStopReaction => None,
+        }
+    }
+}
+
+pub(crate) const HISTOGRAM_MAX_NS: u64 = 60_000_000_000; // Values above a minute saturate
+pub(crate) const HISTOGRAM_SIGNIFICANT_FIGURES: u8 = 3;
+
+/// Tail percentiles of one metric for one stage, in nanoseconds.
+#[derive(Debug, Clone, Serialize)]
+pub struct PercentileRow {
+    pub stage: String,
+    pub metric: Metric,
+    pub count: u64,
+    pub p50_ns: u64,
+    pub p90_ns: u64,
+    pub p99_ns: u64,
+    pub p99_9_ns: u64,
+    pub p99_99_ns: u64,
+    pub max_ns: u64,
+}
+
+/// HDR histograms for every (stage, metric) pair. Memory is fixed by the value range
+/// and precision (3 significant figures), not by how many cycles are recorded.
+#[derive(Clone)]
+pub struct LatencyHistograms {
+    histograms: Vec<Histogram<u64>>, // Indexed by stage * Metric::ALL.len() + metric
+}
+
+impl Default for LatencyHistograms {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LatencyHistograms {
+    pub fn new() -> Self {
+        let histogram = Histogram::new_with_bounds(1, HISTOGRAM_MAX_NS, HISTOGRAM_SIGNIFICANT_FIGURES)
+            .expect("valid histogram bounds");
+        Self {
+            histograms: vec![histogram; Stage::ALL.len() * Metric::ALL.len()],
+        }
+    }
+
+    fn slot(stage: Stage, metric: Metric) -> usize {
+        let metric_index = Metric::ALL.iter().position(|&m| m == metric).unwrap_or(0);
+        stage.index() * Metric::ALL.len() + metric_index
+    }
+
+    /// Records a result under its own stage and under `Stage::All`.
+    pub fn record(&mut self, result: &CycleResult) {
+        for stage in [Stage::All, Stage::of(result)] {
+            for metric in Metric::ALL {
+                if let Some(value) = metric.value_ns(result) {
+                    self.histograms[Self::slot(stage, metric)].saturating_record(value);
+                }
+            }
+        }
+    }
+
+    /// Records an e-stop reaction under its actuator's stage and under `Stage::All`.
+    pub fn record_estop_reaction(&mut self, reaction: &EStopReaction) {
+        for stage in [Stage::All, Stage::Actuator(reaction.actuator)] {
+            self.histograms[Self::slot(stage, Metric::EStopReaction)].saturating_record(reaction.reaction_ns);
+        }
+    }
+
+    pub fn get(&self, stage: Stage, metric: Metric) -> &Histogram<u64> {
+        &self.histograms[Self::slot(stage, metric)]
+    }
+
+    /// One row per stage and metric, skipping stages with no results.
+    pub fn rows(&self) -> Vec<PercentileRow> {
+        let mut rows = Vec::new();
+        for stage in Stage::ALL {
+            for metric in Metric::ALL {
+                let histogram = self.get(stage, metric);
+                if histogram.is_empty() {
+                    continue;
+                }
+                rows.push(PercentileRow {
+                    stage: stage.to_string(),
+                    metric,
+                    count: histogram.len(),
+                    p50_ns: histogram.value_at_percentile(50.0),
+                    p90_ns: histogram.value_at_percentile(90.0),
+                    p99_ns: histogram.value_at_percentile(99.0),
+                    p99_9_ns: histogram.value_at_percentile(99.9),
+                    p99_99_ns: histogram.value_at_percentile(99.99),
+                    max_ns: histogram.max(),
+                });
+            }
+        }
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::VirtualClock;
+    use crate::rng::SimRng;
+
+    fn result(cycle_id: u64, actuator: Option<ActuatorType>, latency_ns: u64) -> CycleResult {
+        CycleResult {
+            cycle_id,
+            mode: "test".to_string(),
+            actuator,
+            total_latency_ns: latency_ns,
+            processing_time_ns: latency_ns / 2,
+            lock_wait_ns: 10,
+            deadline_met: latency_ns < 1_000_000,
+            lateness_ns: latency_ns as i64 - 1_000_000,
+            release_jitter_ns: actuator.is_none().then_some((cycle_id % 7) as i64 * 100),
+            control_ns: actuator.map(|_| 500),
+        }
+    }
+
+    /// The nearest-rank percentile, as `value_at_percentile` defines it.
+    fn exact_percentile(sorted: &[u64], percentile: f64) -> u64 {
+        let rank = ((percentile / 100.0 * sorted.len() as f64).ceil() as usize).max(1);
+        sorted[rank - 1]
+    }
+
+    #[test]
+    fn histogram_percentiles_match_exact_percentiles_to_three_significant_figures() {
+        let mut rng = SimRng::new(7);
+        let mut histograms = LatencyHistograms::new();
+        let mut latencies = Vec::new();
+        for cycle_id in 0..50_000 {
+            // Long-tailed, like real latencies: mostly tens of μs, a few ms
+            let latency_ns = 20_000 + rng.exponential(15_000.0) as u64 + if rng.chance(0.01) { 3_000_000 } else { 0 };
+            histograms.record(&result(cycle_id, Some(ActuatorType::Motor), latency_ns));
+            latencies.push(latency_ns);
+        }
+        latencies.sort_unstable();
+
+        let histogram = histograms.get(Stage::Actuator(ActuatorType::Motor), Metric::TotalLatency);
+        assert_eq!(histogram.len(), latencies.len() as u64);
+        for percentile in [50.0, 90.0, 99.0, 99.9, 99.99, 100.0] {
+            let exact = exact_percentile(&latencies, percentile);
+            let estimate = histogram.value_at_percentile(percentile);
+            let error = estimate.abs_diff(exact) as f64 / exact as f64;
+            assert!(error <= 1e-3, "p{}: histogram {} vs exact {}", percentile, estimate, exact);
+        }
+    }
+
+    #[test]
+    fn run_stats_totals_match_the_results() {
+        let results: Vec<CycleResult> = (0..100)
+            .flat_map(|cycle_id| [result(cycle_id, None, 0), result(cycle_id, Some(ActuatorType::Gripper), 900_000 + cycle_id * 2_000)])
+            .collect();
+        let stats = RunStats::from_results(&results);
+
+        let gripper = stats.totals(Stage::Actuator(ActuatorType::Gripper));
+        assert_eq!(gripper.cycles, 100);
+        assert_eq!(gripper.missed_deadlines, 50); // 1_000_000 ns and up
+        assert_eq!(gripper.late_cycles, 49);
+        assert_eq!(gripper.min_latency_ns, 900_000);
+        assert_eq!(gripper.max_latency_ns, 1_098_000);
+        assert_eq!(gripper.avg_latency_ns(), 999_000.0);
+
+        let all = stats.summary(Stage::All);
+        assert_eq!(all.cycles, 200);
+        assert_eq!(all.compliance_percent, 75.0);
+        assert_eq!(all.avg_latency_us, 999.0); // Sensor results without a latency don't count
+        assert_eq!(stats.jitter().count, 100);
+        assert_eq!(stats.summary(Stage::Sensor).missed_deadlines, 0);
+    }
+
+    #[test]
+    fn recorder_caps_retained_results_but_summarises_all_of_them() {
+        let recorder = BenchmarkRecorder::for_run(Arc::new(VirtualClock::new()), &RecordingConfig { max_results: 10, ..RecordingConfig::default() });
+        for cycle_id in 0..25 {
+            recorder.record(result(cycle_id, Some(ActuatorType::Stabilizer), 1_000 * (cycle_id + 1)));
+        }
+
+        let retained: Vec<u64> = recorder.get_results().iter().map(|r| r.cycle_id).collect();
+        assert_eq!(retained, (15..25).collect::<Vec<_>>());
+        assert_eq!(recorder.recorded_results(), 25);
+        let summary = recorder.summary(Stage::All);
+        assert_eq!(summary.cycles, 25);
+        assert_eq!(summary.max_latency_us, 25.0);
+        assert_eq!(recorder.stats().histograms().get(Stage::All, Metric::TotalLatency).len(), 25);
+    }
+
+    #[test]
+    fn recorder_keeps_the_latest_traces_and_latencies_for_all_of_them() {
+        let recorder = BenchmarkRecorder::for_run(Arc::new(VirtualClock::new()), &RecordingConfig { max_traces: 4, ..RecordingConfig::default() });
+        for trace_id in 0..10 {
+            let stamps = crate::StageTimestamps {
+                generated_ns: 1_000,
+                control_computed_ns: 1_000 + (trace_id + 1) * 100,
+                feedback_consumed_ns: 1_000 + (trace_id + 1) * 200,
+                ..Default::default()
+            };
+            recorder.record_trace(TraceRecord::new(trace_id, ActuatorType::Motor, stamps));
+        }
+
+        let retained: Vec<u64> = recorder.traces().iter().map(|trace| trace.trace_id).collect();
+        assert_eq!(retained, vec![6, 7, 8, 9]);
+        assert_eq!(recorder.recorded_traces(), 10);
+        let latencies = recorder.trace_latencies();
+        assert_eq!(latencies.len(), 1);
+        assert_eq!((latencies[0].traces, latencies[0].actuation_max_ns, latencies[0].round_trip_max_ns), (10, 1_000, 2_000));
+        assert_eq!(latencies[0].actuation_p50_ns, 500);
+    }
+
+    #[test]
+    fn shutdown_report_counts_records_rejected_after_close() {
+        let recorder = BenchmarkRecorder::with_clock(Arc::new(VirtualClock::new()));
+        recorder.record(result(0, None, 1_000));
+        recorder.set_shutdown_report(ShutdownReport { failed_to_stop: vec!["motor".to_string()], ..ShutdownReport::default() });
+        recorder.close();
+        recorder.record(result(1, Some(ActuatorType::Motor), 1_000));
+        recorder.record_trace(TraceRecord::new(1, ActuatorType::Motor, crate::StageTimestamps::default()));
+
+        let report = recorder.shutdown_report().unwrap();
+        assert_eq!(report.rejected_records, 2);
+        assert!(report.to_string().ends_with("; 2 late records rejected"));
+        assert_eq!(recorder.recorded_results(), 1);
+    }
+}
@@ -1,41 +1,261 @@
-#[derive(Debug)]
+//! PID control with setpoint tracking, a filtered derivative, output saturation and
+//! anti-windup, configured per actuator by `[actuators.<name>.pid]`.
+
+use crate::config::{ActuatorConfig, AntiWindup, DerivativeSource, PidConfig};
+use crate::control::{ControlOutput, Controller};
+
+pub mod tuning;
+
 pub struct PidController {
     kp: f64,
     ki: f64,
     kd: f64,
-    integral: f64,
-    prev_error: f64,
+    config: PidConfig,
+    next_change: usize, // First `gain_schedule` entry not yet applied
+    integral: f64,      // Of the error, so ki can change without rescaling the history
+    derivative: f64,    // Filtered slope of the error (or negated measurement)
+    prev: Option<(f64, f64)>, // Last measurement and error
 }
 
 impl PidController {
+    /// Regulates to zero with no output limits and a ±100 integral clamp.
     pub fn new(kp: f64, ki: f64, kd: f64) -> Self {
+        Self::with_config(kp, ki, kd, PidConfig::default())
+    }
+
+    pub fn from_config(settings: &ActuatorConfig) -> Self {
+        Self::with_config(settings.kp, settings.ki, settings.kd, settings.pid.clone())
+    }
+
+    pub fn with_config(kp: f64, ki: f64, kd: f64, config: PidConfig) -> Self {
         Self {
             kp,
             ki,
             kd,
+            config,
+            next_change: 0,
             integral: 0.0,
-            prev_error: 0.0,
+            derivative: 0.0,
+            prev: None,
         }
     }
 
-    pub fn reset(&mut self) {
-        self.integral = 0.0;
-        self.prev_error = 0.0;
+    pub fn gains(&self) -> (f64, f64, f64) {
+        (self.kp, self.ki, self.kd)
+    }
+
+    /// Switches gains without a bump: the integral absorbs the change in the
+    /// proportional and derivative terms, so the next output continues from the last.
+    /// Without integral action in the new gains the output may still jump.
+    pub fn set_gains(&mut self, kp: f64, ki: f64, kd: f64) {
+        if let Some((_, error)) = self.prev.filter(|_| ki != 0.0) {
+            let held = self.ki * self.integral + (self.kp - kp) * error + (self.kd - kd) * self.derivative;
+            self.integral = (held / ki).clamp(-self.config.integral_limit, self.config.integral_limit);
+        }
+        self.kp = kp;
+        self.ki = ki;
+        self.kd = kd;
     }
 
+    /// Drives `error` to zero, as `update` with a zero setpoint would.
     pub fn compute(&mut self, error: f64, dt: f64) -> f64 {
-        // Integral with simple anti-windup
-        self.integral += error * dt;
-        self.integral = self.integral.clamp(-100.0, 100.0);
-
-        let derivative = if dt > 0.0 {
-            (error - self.prev_error) / dt
-        } else {
-            0.0
+        self.step(0.0, -error, dt).output
+    }
+
+    fn step(&mut self, setpoint: f64, measurement: f64, dt: f64) -> ControlOutput {
+        let config = &self.config;
+        let error = setpoint - measurement;
+
+        // No slope on the first sample, so starting away from the setpoint doesn't kick
+        let slope = match self.prev {
+            Some((prev_measurement, prev_error)) if dt > 0.0 => match config.derivative_on {
+                DerivativeSource::Measurement => -(measurement - prev_measurement) / dt,
+                DerivativeSource::Error => (error - prev_error) / dt,
+            },
+            _ => 0.0,
         };
+        let alpha = if config.derivative_filter_secs > 0.0 { dt / (config.derivative_filter_secs + dt) } else { 1.0 };
+        self.derivative += alpha * (slope - self.derivative);
+        self.prev = Some((measurement, error));
+
+        let limit = config.integral_limit;
+        let feedforward = config.feedforward_gain * setpoint + config.feedforward_bias;
+        let unsaturated = |integral: f64| self.kp * error + self.ki * integral + self.kd * self.derivative + feedforward;
+        let saturate = |output: f64| {
+            output.max(config.output_min.unwrap_or(f64::NEG_INFINITY)).min(config.output_max.unwrap_or(f64::INFINITY))
+        };
+
+        let integrated = (self.integral + error * dt).clamp(-limit, limit);
+        let mut candidate = unsaturated(integrated);
+        let mut output = saturate(candidate);
+        match config.anti_windup {
+            AntiWindup::Clamp => self.integral = integrated,
+            AntiWindup::ConditionalIntegration => {
+                // Hold the integral while it would drive the output further into saturation
+                if (candidate - output) * self.ki * error > 0.0 {
+                    candidate = unsaturated(self.integral);
+                    output = saturate(candidate);
+                } else {
+                    self.integral = integrated;
+                }
+            }
+            // Without integral action there's nothing to track; the correction would divide by zero
+            AntiWindup::BackCalculation if self.ki == 0.0 => self.integral = integrated,
+            AntiWindup::BackCalculation => {
+                let tracking_secs = self.tracking_time_secs();
+                let correction = (output - candidate) * dt / (self.ki * tracking_secs);
+                self.integral = (integrated + correction).clamp(-limit, limit);
+            }
+        }
+
+        ControlOutput { setpoint, error, output, saturated: output != candidate }
+    }
+
+    /// `tracking_time_secs`, or √(Ti·Td) (Ti without derivative action) from the current gains.
+    fn tracking_time_secs(&self) -> f64 {
+        self.config.tracking_time_secs.unwrap_or_else(|| {
+            let ti = self.kp / self.ki;
+            let td = self.kd / self.kp;
+            let tracking = if td > 0.0 { (ti * td).sqrt() } else { ti };
+            if tracking.is_finite() && tracking > 0.0 { tracking } else { 1.0 / self.ki }
+        })
+    }
+}
+
+impl Controller for PidController {
+    /// Gain changes scheduled up to `t_secs` take effect first.
+    fn update(&mut self, t_secs: f64, setpoint: f64, measurement: f64, dt: f64) -> ControlOutput {
+        while let Some(change) = self.config.gain_schedule.get(self.next_change).copied() {
+            if change.at_secs > t_secs {
+                break;
+            }
+            self.set_gains(change.kp, change.ki, change.kd);
+            self.next_change += 1;
+        }
+        self.step(setpoint, measurement, dt)
+    }
+
+    fn reset(&mut self) {
+        self.integral = 0.0;
+        self.derivative = 0.0;
+        self.prev = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GainChange;
+
+    const DT: f64 = 0.01;
+
+    fn pid(kp: f64, ki: f64, kd: f64, config: PidConfig) -> PidController {
+        PidController::with_config(kp, ki, kd, config)
+    }
 
-        self.prev_error = error;
+    fn limited(anti_windup: AntiWindup) -> PidConfig {
+        PidConfig { output_max: Some(1.0), output_min: Some(-1.0), anti_windup, ..PidConfig::default() }
+    }
+
+    /// Holds `measurement` against a setpoint of 10 for `steps` samples; returns the last output.
+    fn hold(controller: &mut PidController, measurement: f64, steps: usize) -> ControlOutput {
+        let mut last = None;
+        for step in 0..steps {
+            last = Some(controller.update(step as f64 * DT, 10.0, measurement, DT));
+        }
+        last.expect("at least one step")
+    }
+
+    #[test]
+    fn pi_control_tracks_a_setpoint_change() {
+        // First-order plant, y' = (u - y) / 0.1
+        let mut controller = pid(2.0, 20.0, 0.0, PidConfig::default());
+        let mut y = 0.0;
+        let mut error = f64::NAN;
+        for step in 0..500 {
+            let setpoint = if step < 100 { 1.0 } else { -2.0 };
+            let out = controller.update(step as f64 * DT, setpoint, y, DT);
+            y += (out.output - y) * DT / 0.1;
+            error = out.error;
+        }
+        assert!(error.abs() < 1e-3 && (y + 2.0).abs() < 1e-3, "y = {}", y);
+    }
+
+    #[test]
+    fn derivative_is_low_pass_filtered_and_taken_on_the_measurement() {
+        let mut unfiltered = pid(0.0, 0.0, 1.0, PidConfig::default());
+        let filter = PidConfig { derivative_filter_secs: 9.0 * DT, ..PidConfig::default() }; // alpha = 0.1
+        let mut filtered = pid(0.0, 0.0, 1.0, filter);
+        for controller in [&mut unfiltered, &mut filtered] {
+            // The first sample has no slope, so starting away from the setpoint doesn't kick
+            assert_eq!(controller.update(0.0, 0.0, 0.0, DT).output, 0.0);
+        }
+        assert!((unfiltered.update(DT, 0.0, 1.0, DT).output + 100.0).abs() < 1e-9);
+        assert!((filtered.update(DT, 0.0, 1.0, DT).output + 10.0).abs() < 1e-9);
+        // A steady measurement lets the filtered slope decay rather than drop to zero
+        assert_eq!(unfiltered.update(2.0 * DT, 0.0, 1.0, DT).output, 0.0);
+        assert!((filtered.update(2.0 * DT, 0.0, 1.0, DT).output + 9.0).abs() < 1e-9);
+
+        // A setpoint step kicks the derivative on the error, not on the measurement
+        let on_error = PidConfig { derivative_on: DerivativeSource::Error, ..PidConfig::default() };
+        let mut on_error = pid(0.0, 0.0, 1.0, on_error);
+        let mut on_measurement = pid(0.0, 0.0, 1.0, PidConfig::default());
+        for controller in [&mut on_error, &mut on_measurement] {
+            controller.update(0.0, 0.0, 0.0, DT);
+        }
+        assert!((on_error.update(DT, 1.0, 0.0, DT).output - 100.0).abs() < 1e-9);
+        assert_eq!(on_measurement.update(DT, 1.0, 0.0, DT).output, 0.0);
+    }
+
+    #[test]
+    fn output_saturates_at_its_limits() {
+        let mut controller = pid(1.0, 0.0, 0.0, limited(AntiWindup::Clamp));
+        let high = controller.update(0.0, 10.0, 0.0, DT);
+        assert_eq!((high.output, high.saturated), (1.0, true));
+        let low = controller.update(DT, -10.0, 0.0, DT);
+        assert_eq!((low.output, low.saturated), (-1.0, true));
+        let within = controller.update(2.0 * DT, 0.5, 0.0, DT);
+        assert_eq!((within.output, within.saturated), (0.5, false));
+    }
+
+    #[test]
+    fn clamping_alone_winds_up_to_the_integral_limit() {
+        let config = PidConfig { integral_limit: 5.0, ..limited(AntiWindup::Clamp) };
+        let mut controller = pid(0.0, 1.0, 0.0, config);
+        hold(&mut controller, 0.0, 1_000); // Error 10 for 10 s
+        assert_eq!(controller.integral, 5.0);
+        // Reversing the error leaves the output pinned until the integral unwinds
+        assert!(hold(&mut controller, 20.0, 1).saturated);
+    }
+
+    #[test]
+    fn conditional_integration_holds_the_integral_in_saturation() {
+        let mut controller = pid(0.0, 1.0, 0.0, limited(AntiWindup::ConditionalIntegration));
+        hold(&mut controller, 0.0, 1_000);
+        assert!(controller.integral <= 1.0 + 10.0 * DT, "integral = {}", controller.integral);
+        // The output leaves saturation as soon as the error reverses
+        assert!(!hold(&mut controller, 20.0, 1).saturated);
+    }
+
+    #[test]
+    fn back_calculation_unwinds_the_integral_towards_the_limit() {
+        let config = PidConfig { tracking_time_secs: Some(0.1), ..limited(AntiWindup::BackCalculation) };
+        let mut controller = pid(0.0, 1.0, 0.0, config);
+        hold(&mut controller, 0.0, 1_000);
+        // Settles where integration and tracking balance: I = max + (Tt − dt)·e
+        assert!((controller.integral - 1.9).abs() < 1e-6, "integral = {}", controller.integral);
+        let released = hold(&mut controller, 20.0, 20);
+        assert!(!released.saturated && released.output < 1.0);
+    }
 
-        (self.kp * error) + (self.ki * self.integral) + (self.kd * derivative)
+    #[test]
+    fn back_calculation_skips_tracking_without_integral_action() {
+        let mut config = limited(AntiWindup::BackCalculation);
+        config.gain_schedule = vec![GainChange { at_secs: 0.5, kp: 2.0, ki: 0.0, kd: 0.0 }];
+        let mut controller = pid(1.0, 1.0, 0.0, config);
+        let out = hold(&mut controller, 0.0, 100);
+        assert_eq!(controller.gains(), (2.0, 0.0, 0.0));
+        assert!(out.output.is_finite() && controller.integral.is_finite());
+        assert_eq!(out.output, 1.0);
     }
 }
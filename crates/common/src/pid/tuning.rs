@@ -0,0 +1,480 @@
+//! Auto-tuning against the plant models of `[plants]`. An identification experiment
+//! (relay feedback or an open-loop step) is run on the plant alone, sampled at the
+//! sensor period, and a tuning rule turns what it measured into PID gains. The result
+//! is written as a TOML snippet that can be merged over an experiment config.
+
+use std::fmt;
+use std::str::FromStr;
+
+use toml::{Table, Value};
+
+use crate::config::{ExperimentConfig, PlantDynamics};
+use crate::plant::{build_plant, model_name};
+use crate::ActuatorType;
+
+/// Relay cycles whose period and amplitude must agree before the oscillation counts as settled.
+const RELAY_SETTLED_CYCLES: usize = 3;
+
+/// Relative spread allowed between those cycles.
+const RELAY_TOLERANCE: f64 = 0.02;
+
+/// A relay cycle shorter than this many samples mostly measures the sampling delay.
+const RELAY_MIN_SAMPLES_PER_CYCLE: f64 = 4.0;
+
+/// A step response is settled once its last quarter moves less than this share of its total change.
+const STEP_SETTLED_SPREAD: f64 = 0.01;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TuningMethod {
+    /// Åström–Hägglund relay feedback, measuring the ultimate gain and period
+    Relay,
+    /// Open-loop step, fitted to a first-order-plus-dead-time model
+    StepResponse,
+}
+
+impl FromStr for TuningMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "relay" => Ok(TuningMethod::Relay),
+            "step" => Ok(TuningMethod::StepResponse),
+            other => Err(format!("unknown tuning method \"{}\" (expected \"relay\" or \"step\")", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TuningRule {
+    /// Ultimate-cycle rules after a relay experiment, reaction-curve rules after a step
+    ZieglerNichols,
+    /// PID from a first-order-plus-dead-time model
+    CohenCoon,
+    /// Skogestad's PI rule from a first-order-plus-dead-time model
+    Simc,
+}
+
+impl TuningRule {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TuningRule::ZieglerNichols => "ziegler_nichols",
+            TuningRule::CohenCoon => "cohen_coon",
+            TuningRule::Simc => "simc",
+        }
+    }
+}
+
+impl FromStr for TuningRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ziegler_nichols" => Ok(TuningRule::ZieglerNichols),
+            "cohen_coon" => Ok(TuningRule::CohenCoon),
+            "simc" => Ok(TuningRule::Simc),
+            other => Err(format!(
+                "unknown tuning rule \"{}\" (expected \"ziegler_nichols\", \"cohen_coon\" or \"simc\")",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TuningOptions {
+    pub method: TuningMethod,
+    pub rule: TuningRule,
+    pub amplitude: f64,              // Relay output ±d, or the step size
+    pub hysteresis: f64,             // Relay dead band around the rest output
+    pub sample_secs: f64,            // Control period the experiment is sampled at
+    pub max_secs: f64,               // Give up on an experiment after this much plant time
+    pub closed_loop_secs: Option<f64>, // SIMC's τc; defaults to the dead time
+}
+
+impl TuningOptions {
+    /// Relay feedback with Ziegler–Nichols rules, sampled at the config's sensor period.
+    pub fn for_config(config: &ExperimentConfig) -> Self {
+        Self {
+            method: TuningMethod::Relay,
+            rule: TuningRule::ZieglerNichols,
+            amplitude: 1.0,
+            hysteresis: 0.0,
+            sample_secs: config.sensor_period_ms as f64 / 1000.0,
+            max_secs: 60.0,
+            closed_loop_secs: None,
+        }
+    }
+}
+
+/// What an identification experiment measured.
+#[derive(Debug, Clone, Copy)]
+pub enum Identification {
+    UltimateCycle { ultimate_gain: f64, period_secs: f64, amplitude: f64 },
+    Fopdt { gain: f64, time_constant_secs: f64, dead_time_secs: f64 },
+}
+
+impl fmt::Display for Identification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Identification::UltimateCycle { ultimate_gain, period_secs, amplitude } => {
+                write!(f, "Ku = {:.4}, Tu = {:.4}s (oscillation amplitude {:.4})", ultimate_gain, period_secs, amplitude)
+            }
+            Identification::Fopdt { gain, time_constant_secs, dead_time_secs } => {
+                write!(f, "K = {:.4}, τ = {:.4}s, θ = {:.4}s", gain, time_constant_secs, dead_time_secs)
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TuningError {
+    NoPlant(ActuatorType),
+    /// The relay never drove the plant into a steady oscillation
+    NoOscillation { secs: f64 },
+    /// The step response was still moving when the experiment gave up (an integrating plant?)
+    NotSettled { secs: f64 },
+    NoResponse,
+    Diverged,
+    RuleNeedsModel(TuningRule),
+    InvalidGains(TuningRule),
+}
+
+impl fmt::Display for TuningError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TuningError::NoPlant(actuator) => write!(f, "{:?} has no plant under [plants] to tune against", actuator),
+            TuningError::NoOscillation { secs } => {
+                write!(f, "relay feedback found no steady oscillation within {}s; try a step response", secs)
+            }
+            TuningError::NotSettled { secs } => {
+                write!(f, "step response did not settle within {}s; integrating plants need relay feedback", secs)
+            }
+            TuningError::NoResponse => write!(f, "the plant output did not respond to the step"),
+            TuningError::Diverged => write!(f, "the plant output diverged during the experiment"),
+            TuningError::RuleNeedsModel(rule) => {
+                write!(f, "{} needs a step-response model; run it with the step method", rule.as_str())
+            }
+            TuningError::InvalidGains(rule) => write!(f, "{} produced non-finite gains for this plant", rule.as_str()),
+        }
+    }
+}
+
+impl std::error::Error for TuningError {}
+
+/// Gains for one actuator, and how they were found.
+#[derive(Debug, Clone)]
+pub struct TunedGains {
+    pub actuator: ActuatorType,
+    pub model: &'static str,
+    pub identification: Identification,
+    pub rule: TuningRule,
+    pub kp: f64,
+    pub ki: f64,
+    pub kd: f64,
+    pub warning: Option<&'static str>, // Reason to distrust these gains
+}
+
+impl TunedGains {
+    /// A partial experiment config setting just this actuator's gains.
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        let mut gains = Table::new();
+        gains.insert("kp".to_string(), Value::Float(self.kp));
+        gains.insert("ki".to_string(), Value::Float(self.ki));
+        gains.insert("kd".to_string(), Value::Float(self.kd));
+        let mut actuators = Table::new();
+        actuators.insert(format!("{:?}", self.actuator).to_lowercase(), Value::Table(gains));
+        let mut root = Table::new();
+        root.insert("actuators".to_string(), Value::Table(actuators));
+
+        let warning = self.warning.map(|warning| format!("# Warning: {}\n", warning)).unwrap_or_default();
+        Ok(format!(
+            "# {:?}: {} plant, {}\n# Rule: {}\n{}{}",
+            self.actuator,
+            self.model,
+            self.identification,
+            self.rule.as_str(),
+            warning,
+            toml::to_string(&root)?
+        ))
+    }
+}
+
+/// Tunes `actuator` against its plant in `config`.
+pub fn tune(config: &ExperimentConfig, actuator: ActuatorType, options: &TuningOptions) -> Result<TunedGains, TuningError> {
+    let plant = config.plants.get(actuator).ok_or(TuningError::NoPlant(actuator))?;
+    let identification = match options.method {
+        TuningMethod::Relay => relay_experiment(plant.dynamics, options)?,
+        TuningMethod::StepResponse => step_experiment(plant.dynamics, options)?,
+    };
+    let (kp, ki, kd) = gains(identification, options)?;
+    let warning = match identification {
+        Identification::UltimateCycle { period_secs, .. }
+            if period_secs < RELAY_MIN_SAMPLES_PER_CYCLE * options.sample_secs =>
+        {
+            Some("the relay cycle spans only a few samples, so it mostly reflects the sample period; \
+                  a step-response model is likely to tune more robustly")
+        }
+        _ => None,
+    };
+    Ok(TunedGains { actuator, model: model_name(plant.dynamics), identification, rule: options.rule, kp, ki, kd, warning })
+}
+
+/// Parallel-form gains `(kp, ki, kd)` from `identification` by `options.rule`.
+pub fn gains(identification: Identification, options: &TuningOptions) -> Result<(f64, f64, f64), TuningError> {
+    // Proportional gain, integral time and derivative time
+    let (kp, ti, td) = match (options.rule, identification) {
+        (TuningRule::ZieglerNichols, Identification::UltimateCycle { ultimate_gain, period_secs, .. }) => {
+            (0.6 * ultimate_gain, period_secs / 2.0, period_secs / 8.0)
+        }
+        (TuningRule::ZieglerNichols, Identification::Fopdt { gain, time_constant_secs: tau, dead_time_secs: theta }) => {
+            (1.2 * tau / (gain * theta), 2.0 * theta, theta / 2.0)
+        }
+        (TuningRule::CohenCoon, Identification::Fopdt { gain, time_constant_secs: tau, dead_time_secs: theta }) => {
+            let ratio = theta / tau;
+            (
+                (tau / (gain * theta)) * (4.0 / 3.0 + ratio / 4.0),
+                theta * (32.0 + 6.0 * ratio) / (13.0 + 8.0 * ratio),
+                4.0 * theta / (11.0 + 2.0 * ratio),
+            )
+        }
+        (TuningRule::Simc, Identification::Fopdt { gain, time_constant_secs: tau, dead_time_secs: theta }) => {
+            let tau_c = options.closed_loop_secs.unwrap_or(theta);
+            (tau / (gain * (tau_c + theta)), tau.min(4.0 * (tau_c + theta)), 0.0)
+        }
+        (rule, Identification::UltimateCycle { .. }) => return Err(TuningError::RuleNeedsModel(rule)),
+    };
+    let gains = (kp, kp / ti, kp * td);
+    if [gains.0, gains.1, gains.2].iter().all(|gain| gain.is_finite()) {
+        Ok(gains)
+    } else {
+        Err(TuningError::InvalidGains(options.rule))
+    }
+}
+
+/// Where a plant settles with no input.
+fn rest_output(dynamics: PlantDynamics) -> f64 {
+    match dynamics {
+        PlantDynamics::Thermal { ambient, .. } => ambient,
+        _ => 0.0,
+    }
+}
+
+/// Switches the input between ±`amplitude` on the sign of the output's deviation from
+/// rest, until the resulting limit cycle repeats itself.
+pub fn relay_experiment(dynamics: PlantDynamics, options: &TuningOptions) -> Result<Identification, TuningError> {
+    let rest = rest_output(dynamics);
+    let mut plant = build_plant(dynamics, rest);
+    let (d, eps, dt) = (options.amplitude, options.hysteresis, options.sample_secs);
+    let steps = (options.max_secs / dt).ceil() as usize;
+
+    let mut input = d;
+    let mut last_switch_up: Option<f64> = None;
+    let (mut low, mut high) = (0.0f64, 0.0f64);
+    let mut cycles: Vec<(f64, f64)> = Vec::new(); // Period and amplitude, switch-up to switch-up
+    for step in 1..=steps {
+        plant.step(input, dt);
+        let deviation = plant.output() - rest;
+        if !deviation.is_finite() {
+            return Err(TuningError::Diverged);
+        }
+        low = low.min(deviation);
+        high = high.max(deviation);
+
+        let next = if deviation > eps { -d } else if deviation < -eps { d } else { input };
+        if next != input && next > 0.0 {
+            let t = step as f64 * dt;
+            if let Some(since) = last_switch_up {
+                cycles.push((t - since, (high - low) / 2.0));
+            }
+            last_switch_up = Some(t);
+            (low, high) = (deviation, deviation);
+        }
+        input = next;
+
+        if cycles.len() >= RELAY_SETTLED_CYCLES {
+            let recent = &cycles[cycles.len() - RELAY_SETTLED_CYCLES..];
+            let agrees = |value: fn(&(f64, f64)) -> f64| {
+                let (min, max) = recent.iter().map(value).fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| (lo.min(v), hi.max(v)));
+                max - min <= RELAY_TOLERANCE * max.abs()
+            };
+            if agrees(|cycle| cycle.0) && agrees(|cycle| cycle.1) {
+                break;
+            }
+        }
+    }
+
+    // Sampled limit cycles can alternate between neighbouring periods, so average the last few
+    if cycles.len() < 2 {
+        return Err(TuningError::NoOscillation { secs: options.max_secs });
+    }
+    let recent = &cycles[cycles.len().saturating_sub(RELAY_SETTLED_CYCLES)..];
+    let period_secs = recent.iter().map(|cycle| cycle.0).sum::<f64>() / recent.len() as f64;
+    let amplitude = recent.iter().map(|cycle| cycle.1).sum::<f64>() / recent.len() as f64;
+    if amplitude <= eps {
+        return Err(TuningError::NoOscillation { secs: options.max_secs });
+    }
+    let ultimate_gain = 4.0 * d / (std::f64::consts::PI * (amplitude * amplitude - eps * eps).sqrt());
+    Ok(Identification::UltimateCycle { ultimate_gain, period_secs, amplitude })
+}
+
+/// Steps the input from rest to `amplitude` and fits a first-order-plus-dead-time model
+/// by the two-point (28.3% / 63.2%) method. Half a sample period is added to the dead
+/// time for the zero-order hold.
+pub fn step_experiment(dynamics: PlantDynamics, options: &TuningOptions) -> Result<Identification, TuningError> {
+    let rest = rest_output(dynamics);
+    let mut plant = build_plant(dynamics, rest);
+    let dt = options.sample_secs;
+    let steps = (options.max_secs / dt).ceil() as usize;
+
+    let mut response = vec![(0.0, 0.0)];
+    let mut settled = false;
+    for step in 1..=steps {
+        plant.step(options.amplitude, dt);
+        let deviation = plant.output() - rest;
+        if !deviation.is_finite() {
+            return Err(TuningError::Diverged);
+        }
+        response.push((step as f64 * dt, deviation));
+
+        if step >= 20 {
+            let tail = &response[response.len() * 3 / 4..];
+            let (min, max) = tail.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &(_, y)| (lo.min(y), hi.max(y)));
+            if deviation != 0.0 && max - min <= STEP_SETTLED_SPREAD * deviation.abs() {
+                settled = true;
+                break;
+            }
+        }
+    }
+    if !settled {
+        return Err(TuningError::NotSettled { secs: options.max_secs });
+    }
+
+    let change = response.last().map_or(0.0, |&(_, y)| y);
+    if change.abs() < f64::EPSILON {
+        return Err(TuningError::NoResponse);
+    }
+    // First time the response reaches `fraction` of its final change, between samples
+    let crossing = |fraction: f64| {
+        response.windows(2).find_map(|pair| {
+            let ((t0, y0), (t1, y1)) = (pair[0], pair[1]);
+            let (f0, f1) = (y0 / change, y1 / change);
+            (f0 < fraction && f1 >= fraction).then(|| t0 + (fraction - f0) / (f1 - f0) * (t1 - t0))
+        })
+    };
+    let (Some(t28), Some(t63)) = (crossing(0.283), crossing(0.632)) else {
+        return Err(TuningError::NoResponse);
+    };
+    let time_constant_secs = (1.5 * (t63 - t28)).max(dt / 2.0);
+    let dead_time_secs = (t63 - time_constant_secs).max(0.0) + dt / 2.0;
+    Ok(Identification::Fopdt { gain: change / options.amplitude, time_constant_secs, dead_time_secs })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // K = 2, τ = 4 s, θ = 1 s
+    const FOPDT: Identification = Identification::Fopdt { gain: 2.0, time_constant_secs: 4.0, dead_time_secs: 1.0 };
+
+    fn options(method: TuningMethod, rule: TuningRule, sample_secs: f64) -> TuningOptions {
+        TuningOptions {
+            method,
+            rule,
+            amplitude: 1.0,
+            hysteresis: 0.0,
+            sample_secs,
+            max_secs: 60.0,
+            closed_loop_secs: None,
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64, what: &str) {
+        let within = (actual - expected).abs() <= tolerance * expected.abs();
+        assert!(within, "{}: {} is not within {} of {}", what, actual, tolerance, expected);
+    }
+
+    fn assert_gains(rule: TuningRule, identification: Identification, expected: (f64, f64, f64)) {
+        let (kp, ki, kd) = gains(identification, &options(TuningMethod::StepResponse, rule, 0.01)).unwrap();
+        assert_close(kp, expected.0, 1e-9, "kp");
+        assert_close(ki, expected.1, 1e-9, "ki");
+        assert_close(kd, expected.2, 1e-9, "kd");
+    }
+
+    #[test]
+    fn tuning_rules_give_their_textbook_gains() {
+        // Ultimate cycle: kp = 0.6·Ku, Ti = Tu/2, Td = Tu/8
+        let cycle = Identification::UltimateCycle { ultimate_gain: 5.0, period_secs: 2.0, amplitude: 0.1 };
+        assert_gains(TuningRule::ZieglerNichols, cycle, (3.0, 3.0, 0.75));
+        // Reaction curve: kp = 1.2·τ/(K·θ), Ti = 2θ, Td = θ/2
+        assert_gains(TuningRule::ZieglerNichols, FOPDT, (2.4, 1.2, 1.2));
+        // kp = τ/(K·θ)·(4/3 + θ/4τ) = 2.7917, Ti = θ·33.5/15, Td = 4θ/11.5
+        let kp = 2.0 * (4.0 / 3.0 + 1.0 / 16.0);
+        assert_gains(TuningRule::CohenCoon, FOPDT, (kp, kp * 15.0 / 33.5, kp * 4.0 / 11.5));
+        // τc = θ: kp = τ/(K·2θ), Ti = min(τ, 8θ)
+        assert_gains(TuningRule::Simc, FOPDT, (1.0, 0.25, 0.0));
+
+        let err = gains(cycle, &options(TuningMethod::Relay, TuningRule::CohenCoon, 0.01)).unwrap_err();
+        assert!(matches!(err, TuningError::RuleNeedsModel(TuningRule::CohenCoon)));
+        let no_dead_time = Identification::Fopdt { gain: 2.0, time_constant_secs: 4.0, dead_time_secs: 0.0 };
+        let err = gains(no_dead_time, &options(TuningMethod::StepResponse, TuningRule::ZieglerNichols, 0.01)).unwrap_err();
+        assert!(matches!(err, TuningError::InvalidGains(TuningRule::ZieglerNichols)));
+    }
+
+    #[test]
+    fn relay_feedback_finds_the_ultimate_cycle_of_a_dc_motor() {
+        // kt/(s·((Ls + R)(Js + b) + kt²)) crosses −180° at ω² = (R·b + kt²)/(L·J) = 4,
+        // where its gain is kt/(ω²·(L·b + R·J)) = 1/6
+        let motor = PlantDynamics::DcMotor {
+            resistance_ohm: 1.0,
+            inductance_h: 0.5,
+            torque_constant: 1.0,
+            inertia: 1.0,
+            friction: 1.0,
+        };
+        let Identification::UltimateCycle { ultimate_gain, period_secs, .. } =
+            relay_experiment(motor, &options(TuningMethod::Relay, TuningRule::ZieglerNichols, 0.001)).unwrap()
+        else {
+            panic!("relay feedback measures an ultimate cycle");
+        };
+        assert_close(period_secs, std::f64::consts::PI, 0.03, "Tu");
+        assert_close(ultimate_gain, 6.0, 0.1, "Ku"); // The describing function is an approximation
+    }
+
+    #[test]
+    fn a_step_response_fits_a_first_order_plant() {
+        let dt = 0.01;
+        // Thermal plant around its ambient: K = heater_gain·R = 1, τ = C·R = 5 s
+        let thermal = PlantDynamics::Thermal { heat_capacity: 10.0, thermal_resistance: 0.5, ambient: 20.0, heater_gain: 2.0 };
+        let lag = PlantDynamics::FirstOrderLag { gain: 2.0, time_constant_secs: 0.5 };
+        for (dynamics, gain, tau) in [(thermal, 1.0, 5.0), (lag, 2.0, 0.5)] {
+            let Identification::Fopdt { gain: k, time_constant_secs, dead_time_secs } =
+                step_experiment(dynamics, &options(TuningMethod::StepResponse, TuningRule::Simc, dt)).unwrap()
+            else {
+                panic!("a step response fits a model");
+            };
+            assert_close(k, gain, 0.01, "K");
+            assert_close(time_constant_secs, tau, 0.01, "τ");
+            // No real dead time, just the half sample of the zero-order hold
+            assert!(dead_time_secs <= dt, "θ = {}", dead_time_secs);
+        }
+    }
+
+    #[test]
+    fn tuned_gains_write_an_overlay_for_their_actuator() {
+        let tuned = TunedGains {
+            actuator: ActuatorType::Motor,
+            model: "first_order_lag",
+            identification: FOPDT,
+            rule: TuningRule::Simc,
+            kp: 1.0,
+            ki: 0.25,
+            kd: 0.0,
+            warning: Some("just a test"),
+        };
+        let snippet = tuned.to_toml().unwrap();
+        assert!(snippet.contains("# Warning: just a test\n"));
+        let overlay: Table = toml::from_str(&snippet).unwrap();
+        let motor = &overlay["actuators"]["motor"];
+        assert_eq!((motor["kp"].as_float(), motor["ki"].as_float(), motor["kd"].as_float()), (Some(1.0), Some(0.25), Some(0.0)));
+    }
+}
@@ -0,0 +1,502 @@
+//! Stage logic shared by the threaded, async and simulated runtimes: the sensor's
+//! adaptive filter and deadline verdict, and everything an actuator does with a sample
+//! between receiving it and sending its feedback. The runtimes differ only in how the
+//! stages are scheduled, how time passes and how messages move between them.
+
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::config::{ActuatorConfig, SensorChannel, SensorConfig, StatusCommand};
+use crate::control::ActuatorController;
+use crate::estop::{EStop, EStopHandle};
+use crate::metrics::CycleResult;
+use crate::status::{StatusInput, StatusMachine};
+use crate::trace::{TraceDrop, TraceRecord};
+use crate::{
+    ActuatorFeedback, ActuatorStatus, ActuatorType, BenchmarkRecorder, CaptureRecord, DashboardBuffer, ExperimentConfig,
+    PlantLoop, SensorData, SharedClock, SharedDiagnostics,
+};
+
+const FILTER_WINDOW: usize = 5;
+const MAX_FILTER_WINDOW: usize = 10;
+const MIN_FILTER_WINDOW: usize = 3;
+
+/// |Filtered force| above this counts as an anomaly.
+pub const ANOMALY_FORCE: f64 = 80.0;
+
+/// How `ForceFilter::adapt` resized the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterChange {
+    Widened { from: usize, to: usize },  // High error: smooth out more noise
+    Narrowed { from: usize, to: usize }, // Low error: respond faster
+}
+
+/// The sensor's moving-average force filter, whose window adapts to actuator feedback.
+#[derive(Debug, Clone)]
+pub struct ForceFilter {
+    history: Vec<f64>,
+    window: usize,
+}
+
+impl Default for ForceFilter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ForceFilter {
+    pub fn new() -> Self {
+        Self { history: Vec::with_capacity(FILTER_WINDOW), window: FILTER_WINDOW }
+    }
+
+    /// Adds `raw_force` and returns the filtered force.
+    pub fn filter(&mut self, raw_force: f64) -> f64 {
+        self.history.push(raw_force);
+        if self.history.len() > FILTER_WINDOW {
+            self.history.remove(0);
+        }
+        self.history.iter().sum::<f64>() / self.history.len() as f64
+    }
+
+    /// Resizes the window for one actuator's `error`, when it is high or low enough.
+    pub fn adapt(&mut self, error: f64) -> Option<FilterChange> {
+        let from = self.window;
+        let change = if error.abs() > 5.0 {
+            self.window = (self.window + 1).min(MAX_FILTER_WINDOW);
+            FilterChange::Widened { from, to: self.window }
+        } else if error.abs() < 1.0 {
+            self.window = (self.window - 1).max(MIN_FILTER_WINDOW);
+            FilterChange::Narrowed { from, to: self.window }
+        } else {
+            return None;
+        };
+        self.history.resize(self.window, 0.0);
+        Some(change)
+    }
+}
+
+/// The sensor's deadline verdict for one cycle.
+#[derive(Debug, Clone, Copy)]
+pub struct SensorVerdict {
+    pub processing_deadline_met: bool,
+    pub transmission_deadline_met: bool,
+    pub deadline_met: bool, // Both deadlines, and the sample was sent
+    pub lateness_ns: i64,   // Over the deadline that was missed by more
+}
+
+impl SensorVerdict {
+    pub fn new(config: &SensorConfig, processing_time_ns: u64, transmission_time_ns: u64, sent: bool) -> Self {
+        let processing_deadline_ns = config.processing_deadline().as_nanos() as u64;
+        let transmission_deadline_ns = config.transmission_deadline().as_nanos() as u64;
+        let processing_deadline_met = processing_time_ns <= processing_deadline_ns;
+        let transmission_deadline_met = transmission_time_ns <= transmission_deadline_ns;
+        Self {
+            processing_deadline_met,
+            transmission_deadline_met,
+            deadline_met: processing_deadline_met && transmission_deadline_met && sent,
+            lateness_ns: processing_time_ns
+                .saturating_sub(processing_deadline_ns)
+                .max(transmission_time_ns.saturating_sub(transmission_deadline_ns)) as i64,
+        }
+    }
+}
+
+/// The completed trace of the sample `feedback` answers, consumed by the sensor at `consumed_ns`.
+pub fn feedback_trace(feedback: &ActuatorFeedback, consumed_ns: u64) -> TraceRecord {
+    let mut stamps = feedback.stamps;
+    stamps.feedback_consumed_ns = consumed_ns;
+    TraceRecord::new(feedback.sensor_id, feedback.actuator, stamps)
+}
+
+/// What an actuator decided for one sample.
+#[derive(Debug, Clone, Copy)]
+pub struct ControlStep {
+    pub error: f64,
+    pub output: f64, // The safe output while an e-stop holds the actuator
+    pub status: ActuatorStatus,
+    pub control_ns: u64,          // Cost of the controller's update
+    pub triggered: Option<EStop>, // The e-stop this sample triggered, if any
+}
+
+/// An actuator's deadline verdict for one sample.
+#[derive(Debug, Clone, Copy)]
+pub struct ActuatorVerdict {
+    pub processing_time_ns: u64, // Received -> control output applied
+    pub deadline_met: bool,
+    pub lateness_ns: i64,
+}
+
+/// One actuator's controller, status machine and e-stop handle, driven one sample at a
+/// time: `control`, then (after the runtime's workload) `actuate`, then `feedback`.
+pub struct ActuatorStage {
+    actuator_type: ActuatorType,
+    settings: ActuatorConfig,
+    mode: String,
+    channel: SensorChannel,
+    period_secs: f64,
+    enable_logging: bool,
+    controller: ActuatorController,
+    status_machine: StatusMachine,
+    estop: EStopHandle,
+    error_threshold: f64, // Adapts within ±40% of the configured correcting threshold
+    last_deadline_met: bool,
+    control_cost_ns: Option<u64>, // Fixed controller cost; `None` measures wall time
+    clock: SharedClock,
+    recorder: Arc<BenchmarkRecorder>,
+    diagnostics: Arc<SharedDiagnostics>,
+    plants: Option<Arc<PlantLoop>>,
+    dashboard: Option<DashboardBuffer>, // Source of operator commands
+}
+
+impl ActuatorStage {
+    pub fn new(
+        config: &ExperimentConfig,
+        actuator_type: ActuatorType,
+        estop: EStopHandle,
+        clock: SharedClock,
+        recorder: Arc<BenchmarkRecorder>,
+        diagnostics: Arc<SharedDiagnostics>,
+        plants: Option<Arc<PlantLoop>>,
+    ) -> Self {
+        let settings = config.actuators.get(actuator_type).clone();
+        Self {
+            actuator_type,
+            mode: config.mode.clone(),
+            channel: config.plants.measured_channel(actuator_type),
+            period_secs: config.sensor_period_ms as f64 / 1000.0,
+            enable_logging: config.enable_logging,
+            controller: ActuatorController::from_config(config, actuator_type),
+            status_machine: StatusMachine::new(actuator_type, settings.status.clone(), settings.emergency_threshold),
+            estop,
+            error_threshold: settings.correcting_threshold,
+            last_deadline_met: true,
+            control_cost_ns: None,
+            settings,
+            clock,
+            recorder,
+            diagnostics,
+            plants,
+            dashboard: None,
+        }
+    }
+
+    /// Takes acknowledge and reset commands for this actuator from `dashboard` each cycle.
+    pub fn with_dashboard(mut self, dashboard: Option<DashboardBuffer>) -> Self {
+        self.dashboard = dashboard;
+        self
+    }
+
+    /// Charges every controller update `cost_ns` instead of measuring it, so a run
+    /// in virtual time doesn't depend on the host.
+    pub fn with_fixed_control_cost(mut self, cost_ns: u64) -> Self {
+        self.control_cost_ns = Some(cost_ns);
+        self
+    }
+
+    pub fn settings(&self) -> &ActuatorConfig {
+        &self.settings
+    }
+
+    pub fn controller_kind(&self) -> &'static str {
+        self.controller.kind()
+    }
+
+    /// Runs the controller and status machine on `data`, reacts to any e-stop and
+    /// applies any acknowledgement due.
+    pub fn control(&mut self, data: &SensorData) -> ControlStep {
+        // Setpoints run on nominal sample time, like the sensor models
+        let t_secs = data.id as f64 * self.period_secs;
+        let control_start = Instant::now();
+        let step = self.controller.update(t_secs, self.channel.read(data), self.period_secs);
+        let control_ns = self.control_cost_ns.unwrap_or_else(|| control_start.elapsed().as_nanos() as u64);
+        let error = step.error;
+
+        // Status machine, on the last cycle's deadline; SafeStop overrides the output
+        let commands = self.dashboard.as_ref().map(|dashboard| dashboard.take_commands(self.actuator_type));
+        for command in commands.into_iter().flatten() {
+            // While an e-stop holds the actuator, the operator's acknowledgement is for the stop
+            if command == StatusCommand::Acknowledge && self.status_machine.is_held() {
+                if let Some(stop) = self.estop.acknowledge() {
+                    if self.enable_logging {
+                        println!("[{:>8}] [ESTOP] {:?}: Stop #{} acknowledged from the dashboard",
+                                 self.log_time(), self.actuator_type, stop.id);
+                    }
+                }
+            } else {
+                self.status_machine.command(command);
+            }
+        }
+        let transitions = self.status_machine.update(&StatusInput {
+            now_ns: self.clock.now_ns(),
+            t_secs,
+            sensor_id: data.id,
+            abs_error: error.abs(),
+            correcting_threshold: self.error_threshold,
+            deadline_met: self.last_deadline_met,
+        });
+        let mut triggered = None;
+        for transition in transitions {
+            if transition.resets_controller() {
+                self.controller.reset();
+            }
+            if self.enable_logging {
+                println!("[{:>8}] [STATUS] {:?}: {:?} -> {:?} ({}) - Error: {:.2}",
+                         self.log_time(), self.actuator_type, transition.from, transition.to, transition.cause,
+                         error.abs());
+            }
+            self.diagnostics.record_transition(transition);
+            if transition.to == ActuatorStatus::Emergency {
+                if let Some(stop) = self.estop.trigger(data.id, self.clock.now_ns()) {
+                    if self.enable_logging {
+                        println!("[{:>8}] [ESTOP] {:?}: Triggered stop #{} on cycle #{}",
+                                 self.log_time(), self.actuator_type, stop.id, data.id);
+                    }
+                    triggered = Some(stop);
+                }
+            }
+        }
+
+        // E-stop: react to a new stop (this actuator's own included), then release once acknowledged
+        self.react_to_estop();
+        if let Some(stop) = self.estop.acknowledge_due(t_secs, self.clock.now_ns()) {
+            if self.enable_logging {
+                println!("[{:>8}] [ESTOP] Stop #{} acknowledged", self.log_time(), stop.id);
+            }
+        }
+        if self.estop.take_release() {
+            self.status_machine.release();
+        }
+
+        // Dynamic recalibration: adjust threshold within ±40% of the configured one
+        let settings = &self.settings;
+        if error.abs() < settings.correcting_threshold * 0.4 {
+            self.error_threshold = (self.error_threshold * 0.99).max(settings.correcting_threshold * 0.6);
+        } else if error.abs() > settings.emergency_threshold * 0.8 {
+            self.error_threshold = (self.error_threshold * 1.01).min(settings.correcting_threshold * 1.4);
+        }
+
+        ControlStep {
+            error,
+            output: self.status_machine.output_override().unwrap_or(step.output),
+            status: self.status_machine.status(),
+            control_ns,
+            triggered,
+        }
+    }
+
+    /// Reacts to a stop this actuator hasn't seen yet: holds its status machine in SafeStop
+    /// and its plant at the safe output, then records how long that took.
+    pub fn react_to_estop(&mut self) {
+        let Some(stop) = self.estop.take_pending() else {
+            return;
+        };
+        let transitions = self.status_machine.hold(self.clock.now_ns(), stop.sensor_id);
+        let safe_output = self.status_machine.output_override().expect("held in SafeStop");
+        if let Some(plants) = &self.plants {
+            plants.actuate(self.actuator_type, safe_output, self.clock.now_ns());
+        }
+        let reaction = self.estop.reacted(&stop, self.clock.now_ns());
+
+        for transition in transitions {
+            if self.enable_logging {
+                println!("[{:>8}] [STATUS] {:?}: {:?} -> {:?} ({})",
+                         self.log_time(), self.actuator_type, transition.from, transition.to, transition.cause);
+            }
+            self.diagnostics.record_transition(transition);
+        }
+        if self.enable_logging {
+            println!("[{:>8}] [ESTOP] {:?}: Output held at {:.2} {:.1}μs after stop #{} from {:?} {}",
+                     self.log_time(), self.actuator_type, safe_output, reaction.reaction_ns as f64 / 1000.0, stop.id,
+                     stop.origin, if reaction.deadline_met { "✓" } else { "✗" });
+        }
+        self.recorder.record_estop_reaction(reaction);
+    }
+
+    /// Applies `step`'s output to the plant now, and judges the sample against the deadline.
+    pub fn actuate(&mut self, data: &mut SensorData, step: &ControlStep) -> ActuatorVerdict {
+        data.stamps.control_computed_ns = self.clock.now_ns();
+        if let Some(plants) = &self.plants {
+            plants.actuate(self.actuator_type, step.output, data.stamps.control_computed_ns);
+        }
+        self.recorder.capture(CaptureRecord::Control {
+            actuator: self.actuator_type,
+            sensor_id: data.id,
+            error: step.error,
+            output: step.output,
+            status: step.status,
+        });
+
+        let deadline_ns = self.settings.deadline().as_nanos() as u64;
+        let processing_time_ns = data.stamps.control_computed_ns - data.stamps.actuator_received_ns;
+        let deadline_met = processing_time_ns <= deadline_ns;
+        self.last_deadline_met = deadline_met;
+        ActuatorVerdict {
+            processing_time_ns,
+            deadline_met,
+            lateness_ns: processing_time_ns.saturating_sub(deadline_ns) as i64,
+        }
+    }
+
+    /// The sample's result, with its latency from release to now.
+    pub fn cycle_result(&self, data: &SensorData, step: &ControlStep, verdict: &ActuatorVerdict, lock_wait_ns: u64) -> CycleResult {
+        CycleResult {
+            cycle_id: data.id,
+            mode: self.mode.clone(),
+            actuator: Some(self.actuator_type),
+            total_latency_ns: self.clock.now_ns() - data.timestamp,
+            processing_time_ns: verdict.processing_time_ns,
+            lock_wait_ns,
+            deadline_met: verdict.deadline_met,
+            lateness_ns: verdict.lateness_ns,
+            release_jitter_ns: None, // Actuators are event-driven, not periodic
+            control_ns: Some(step.control_ns),
+        }
+    }
+
+    /// Feedback on `data` for the sensor, stamped as sent now.
+    pub fn feedback(&self, data: &mut SensorData, step: &ControlStep) -> ActuatorFeedback {
+        data.stamps.feedback_sent_ns = self.clock.now_ns();
+        ActuatorFeedback {
+            sensor_id: data.id,
+            actuator: self.actuator_type,
+            status: step.status,
+            control_output: step.output,
+            error: step.error,
+            timestamp: data.stamps.feedback_sent_ns,
+            stamps: data.stamps,
+        }
+    }
+
+    /// Records whether `feedback` reached the sensor; one that didn't ends its trace here.
+    pub fn feedback_sent(&self, feedback: ActuatorFeedback, sent: bool) {
+        self.recorder.capture(CaptureRecord::Feedback { feedback, sent });
+        if !sent {
+            self.recorder.record_trace(TraceRecord::dropped(feedback.sensor_id, self.actuator_type, feedback.stamps,
+                                                            TraceDrop::Feedback));
+        }
+    }
+
+    fn log_time(&self) -> String {
+        format!("{:.3}s", self.clock.now_ns() as f64 / 1e9)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn force_filter_averages_the_last_samples_and_adapts_its_window() {
+        let mut filter = ForceFilter::new();
+        for force in [1.0, 2.0, 3.0, 4.0, 5.0] {
+            filter.filter(force);
+        }
+        assert_eq!(filter.filter(11.0), 5.0); // 2..=5 and 11
+
+        assert_eq!(filter.adapt(3.0), None);
+        assert_eq!(filter.adapt(-6.0), Some(FilterChange::Widened { from: 5, to: 6 }));
+        assert_eq!(filter.adapt(0.5), Some(FilterChange::Narrowed { from: 6, to: 5 }));
+        for _ in 0..5 {
+            filter.adapt(0.0);
+        }
+        assert_eq!(filter.adapt(0.0), Some(FilterChange::Narrowed { from: 3, to: 3 }));
+    }
+
+    #[test]
+    fn sensor_verdict_reports_the_larger_overrun() {
+        let config = SensorConfig { processing_deadline_us: 200, transmission_deadline_us: 100, ..SensorConfig::default() };
+        let met = SensorVerdict::new(&config, 200_000, 100_000, true);
+        assert!(met.deadline_met && met.lateness_ns == 0);
+
+        let late = SensorVerdict::new(&config, 250_000, 180_000, true);
+        assert!(!late.processing_deadline_met && !late.transmission_deadline_met);
+        assert_eq!(late.lateness_ns, 80_000);
+
+        let unsent = SensorVerdict::new(&config, 1_000, 1_000, false);
+        assert!(unsent.processing_deadline_met && unsent.transmission_deadline_met && !unsent.deadline_met);
+    }
+
+    fn config(extra: &str) -> crate::ExperimentConfig {
+        crate::config::parse_config(
+            "test.toml",
+            &format!(
+                "experiment_name = \"test\"\nduration_secs = 1\nsensor_period_ms = 10\ncpu_load_threads = 0\n\
+                 mode = \"baseline\"\nprocessing_time_ns = 1000\nenable_logging = false\n{}",
+                extra,
+            ),
+            false,
+        )
+        .unwrap()
+    }
+
+    fn stage(
+        config: &crate::ExperimentConfig,
+        estop: &Arc<crate::estop::EStopCoordinator>,
+        actuator_type: ActuatorType,
+        dashboard: &DashboardBuffer,
+    ) -> ActuatorStage {
+        let clock: SharedClock = Arc::new(crate::VirtualClock::new());
+        ActuatorStage::new(
+            config,
+            actuator_type,
+            estop.handle(actuator_type),
+            clock,
+            Arc::new(BenchmarkRecorder::new()),
+            Arc::new(SharedDiagnostics::default()),
+            None,
+        )
+        .with_dashboard(Some(dashboard.clone()))
+    }
+
+    fn sample(id: u64, position: f64) -> SensorData {
+        SensorData {
+            id,
+            timestamp: 0,
+            force: 0.0,
+            position,
+            temperature: 25.0,
+            stamps: crate::StageTimestamps::default(),
+        }
+    }
+
+    #[test]
+    fn dashboard_commands_reach_their_actuator_on_its_next_cycle() {
+        let config = config("[estop]\nenabled = false\n");
+        let dashboard = DashboardBuffer::new(10);
+        let estop = Arc::new(crate::estop::EStopCoordinator::new(config.estop.clone()));
+        let mut stage = stage(&config, &estop, ActuatorType::Motor, &dashboard);
+
+        assert_eq!(stage.control(&sample(0, 1_000.0)).status, ActuatorStatus::Emergency);
+        assert_eq!(stage.control(&sample(1, 0.0)).status, ActuatorStatus::Emergency); // Latched
+        dashboard.send_command(ActuatorType::Gripper, StatusCommand::Acknowledge);
+        assert_eq!(stage.control(&sample(2, 0.0)).status, ActuatorStatus::Emergency);
+        dashboard.send_command(ActuatorType::Motor, StatusCommand::Acknowledge);
+        assert_eq!(stage.control(&sample(3, 0.0)).status, ActuatorStatus::Recovering);
+        assert_eq!(dashboard.take_commands(ActuatorType::Gripper), vec![StatusCommand::Acknowledge]);
+    }
+
+    #[test]
+    fn the_dashboard_acknowledges_an_estop_and_held_commands_wait_for_it() {
+        let config = config("");
+        let dashboard = DashboardBuffer::new(10);
+        let estop = Arc::new(crate::estop::EStopCoordinator::new(config.estop.clone()));
+        let mut motor = stage(&config, &estop, ActuatorType::Motor, &dashboard);
+        let mut gripper = stage(&config, &estop, ActuatorType::Gripper, &dashboard);
+
+        // The motor's Emergency stops both
+        assert_eq!(motor.control(&sample(0, 1_000.0)).status, ActuatorStatus::SafeStop);
+        assert_eq!(gripper.control(&sample(0, 0.0)).status, ActuatorStatus::SafeStop);
+        dashboard.send_command(ActuatorType::Gripper, StatusCommand::Reset);
+        assert_eq!(gripper.control(&sample(1, 0.0)).status, ActuatorStatus::SafeStop, "the reset waits");
+        assert_eq!(motor.control(&sample(1, 0.0)).status, ActuatorStatus::SafeStop);
+
+        dashboard.send_command(ActuatorType::Motor, StatusCommand::Acknowledge);
+        motor.control(&sample(2, 0.0));
+        assert!(!estop.is_latched());
+        assert_eq!(motor.control(&sample(3, 0.0)).status, ActuatorStatus::Recovering);
+
+        // Released by the same acknowledgement, then the reset it held back applies
+        assert_eq!(gripper.control(&sample(2, 0.0)).status, ActuatorStatus::SafeStop);
+        assert_eq!(gripper.control(&sample(3, 0.0)).status, ActuatorStatus::Normal);
+    }
+}
@@ -0,0 +1,531 @@
+//! Simulated plants that close the control loop, built from `[plants]`.
+//!
+//! Each actuator's control output drives a model of the physical system it acts on,
+//! held from the moment the output is computed until the next one. The sensor measures
+//! the model on every sample in place of its input channel, so a late or missed control
+//! output shows up in the measured response.
+
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+use crate::config::{ExperimentConfig, PlantDynamics, SensorChannel};
+use crate::sensor_model::{build_signal, SensorModel, SensorReading};
+use crate::{ActuatorType, SensorData};
+
+/// Longest integration step; longer intervals are split so stiff plants stay accurate.
+const MAX_STEP_SECS: f64 = 1e-4;
+
+/// Band around the setpoint, as a share of the error when the response began, that counts as settled.
+const SETTLING_BAND: f64 = 0.02;
+
+pub trait Plant: Send {
+    /// Advances the plant by `dt_secs` with `input` held constant.
+    fn step(&mut self, input: f64, dt_secs: f64);
+
+    /// The measured output.
+    fn output(&self) -> f64;
+}
+
+/// Fourth-order Runge-Kutta over `dt_secs`, in steps of at most `MAX_STEP_SECS`.
+fn integrate<const N: usize>(state: &mut [f64; N], dt_secs: f64, derivative: impl Fn(&[f64; N]) -> [f64; N]) {
+    let steps = (dt_secs / MAX_STEP_SECS).ceil().max(1.0) as usize;
+    let h = dt_secs / steps as f64;
+    let offset = |base: &[f64; N], slope: &[f64; N], by: f64| std::array::from_fn(|i| base[i] + slope[i] * by);
+    for _ in 0..steps {
+        let k1 = derivative(state);
+        let k2 = derivative(&offset(state, &k1, h / 2.0));
+        let k3 = derivative(&offset(state, &k2, h / 2.0));
+        let k4 = derivative(&offset(state, &k3, h));
+        for i in 0..N {
+            state[i] += h / 6.0 * (k1[i] + 2.0 * k2[i] + 2.0 * k3[i] + k4[i]);
+        }
+    }
+}
+
+pub struct FirstOrderLag {
+    pub gain: f64,
+    pub time_constant_secs: f64,
+    pub y: f64,
+}
+
+impl Plant for FirstOrderLag {
+    fn step(&mut self, input: f64, dt_secs: f64) {
+        let mut state = [self.y];
+        integrate(&mut state, dt_secs, |&[y]| [(self.gain * input - y) / self.time_constant_secs]);
+        self.y = state[0];
+    }
+
+    fn output(&self) -> f64 {
+        self.y
+    }
+}
+
+pub struct MassSpringDamper {
+    pub mass_kg: f64,
+    pub stiffness: f64,
+    pub damping: f64,
+    pub state: [f64; 2], // Position, velocity
+}
+
+impl Plant for MassSpringDamper {
+    fn step(&mut self, input: f64, dt_secs: f64) {
+        integrate(&mut self.state, dt_secs, |&[x, v]| {
+            [v, (input - self.damping * v - self.stiffness * x) / self.mass_kg]
+        });
+    }
+
+    fn output(&self) -> f64 {
+        self.state[0]
+    }
+}
+
+pub struct DcMotor {
+    pub resistance_ohm: f64,
+    pub inductance_h: f64,
+    pub torque_constant: f64, // Also the back-EMF constant, in SI units
+    pub inertia: f64,
+    pub friction: f64,
+    pub state: [f64; 3], // Armature current, shaft speed, shaft angle
+}
+
+impl Plant for DcMotor {
+    fn step(&mut self, input: f64, dt_secs: f64) {
+        let (r, l, kt, j, b) = (self.resistance_ohm, self.inductance_h, self.torque_constant, self.inertia, self.friction);
+        integrate(&mut self.state, dt_secs, |&[current, speed, _angle]| {
+            [(input - r * current - kt * speed) / l, (kt * current - b * speed) / j, speed]
+        });
+    }
+
+    fn output(&self) -> f64 {
+        self.state[2]
+    }
+}
+
+pub struct Thermal {
+    pub heat_capacity: f64,
+    pub thermal_resistance: f64,
+    pub ambient: f64,
+    pub heater_gain: f64,
+    pub temperature: f64,
+}
+
+impl Plant for Thermal {
+    fn step(&mut self, input: f64, dt_secs: f64) {
+        let mut state = [self.temperature];
+        integrate(&mut state, dt_secs, |&[t]| {
+            [(self.heater_gain * input - (t - self.ambient) / self.thermal_resistance) / self.heat_capacity]
+        });
+        self.temperature = state[0];
+    }
+
+    fn output(&self) -> f64 {
+        self.temperature
+    }
+}
+
+/// A plant at rest with its measured output at `initial`.
+pub fn build_plant(dynamics: PlantDynamics, initial: f64) -> Box<dyn Plant> {
+    match dynamics {
+        PlantDynamics::FirstOrderLag { gain, time_constant_secs } => {
+            Box::new(FirstOrderLag { gain, time_constant_secs, y: initial })
+        }
+        PlantDynamics::MassSpringDamper { mass_kg, stiffness, damping } => {
+            Box::new(MassSpringDamper { mass_kg, stiffness, damping, state: [initial, 0.0] })
+        }
+        PlantDynamics::DcMotor { resistance_ohm, inductance_h, torque_constant, inertia, friction } => {
+            Box::new(DcMotor { resistance_ohm, inductance_h, torque_constant, inertia, friction, state: [0.0, 0.0, initial] })
+        }
+        PlantDynamics::Thermal { heat_capacity, thermal_resistance, ambient, heater_gain } => {
+            Box::new(Thermal { heat_capacity, thermal_resistance, ambient, heater_gain, temperature: initial })
+        }
+    }
+}
+
+/// Continuous-time linear model of a plant, `x' = A·x + B·u`, measuring
+/// `offset + x[output_state]`.
+#[derive(Debug, Clone)]
+pub struct LinearModel {
+    pub a: Vec<Vec<f64>>,
+    pub b: Vec<f64>,
+    pub output_state: usize,
+    pub offset: f64,
+}
+
+impl LinearModel {
+    pub fn order(&self) -> usize {
+        self.b.len()
+    }
+}
+
+impl PlantDynamics {
+    /// The dynamics as a linear model; every plant here is linear, the thermal one in
+    /// its deviation from ambient.
+    pub fn linear_model(self) -> LinearModel {
+        let (a, b, output_state, offset) = match self {
+            PlantDynamics::FirstOrderLag { gain, time_constant_secs: tau } => {
+                (vec![vec![-1.0 / tau]], vec![gain / tau], 0, 0.0)
+            }
+            PlantDynamics::MassSpringDamper { mass_kg: m, stiffness: k, damping: c } => {
+                (vec![vec![0.0, 1.0], vec![-k / m, -c / m]], vec![0.0, 1.0 / m], 0, 0.0)
+            }
+            PlantDynamics::DcMotor { resistance_ohm: r, inductance_h: l, torque_constant: kt, inertia: j, friction: b } => (
+                vec![vec![-r / l, -kt / l, 0.0], vec![kt / j, -b / j, 0.0], vec![0.0, 1.0, 0.0]],
+                vec![1.0 / l, 0.0, 0.0],
+                2,
+                0.0,
+            ),
+            PlantDynamics::Thermal { heat_capacity, thermal_resistance, ambient, heater_gain } => (
+                vec![vec![-1.0 / (thermal_resistance * heat_capacity)]],
+                vec![heater_gain / heat_capacity],
+                0,
+                ambient,
+            ),
+        };
+        LinearModel { a, b, output_state, offset }
+    }
+}
+
+impl SensorChannel {
+    pub fn read(self, data: &SensorData) -> f64 {
+        match self {
+            SensorChannel::Force => data.force,
+            SensorChannel::Position => data.position,
+            SensorChannel::Temperature => data.temperature,
+        }
+    }
+
+    fn write(self, reading: &mut SensorReading, value: f64) {
+        match self {
+            SensorChannel::Force => reading.force = value,
+            SensorChannel::Position => reading.position = value,
+            SensorChannel::Temperature => reading.temperature = value,
+        }
+    }
+}
+
+pub(crate) fn model_name(dynamics: PlantDynamics) -> &'static str {
+    match dynamics {
+        PlantDynamics::FirstOrderLag { .. } => "first_order_lag",
+        PlantDynamics::MassSpringDamper { .. } => "mass_spring_damper",
+        PlantDynamics::DcMotor { .. } => "dc_motor",
+        PlantDynamics::Thermal { .. } => "thermal",
+    }
+}
+
+/// How well one loop made its plant's output track the actuator's setpoint over a run.
+/// A setpoint jump wider than the settling band starts a new response.
+#[derive(Debug, Clone, Serialize)]
+pub struct LoopPerformance {
+    pub actuator: ActuatorType,
+    pub model: &'static str,
+    pub samples: u64,
+    pub initial: f64,
+    pub final_output: f64,
+    pub final_setpoint: f64,
+    pub overshoot_percent: f64,        // Largest excursion past the setpoint, relative to the error it started from
+    pub settling_time_ms: Option<f64>, // From the last response's start until it stays within 2% of its starting error
+    pub iae: f64,                      // Integral of |setpoint − output| over the run
+    pub max_abs_input: f64,            // Largest control output applied
+    pub stable: bool,                  // Finite and ending no further from the setpoint than the last response started
+}
+
+/// One actuator's plant and what has been measured of it.
+struct Loop {
+    actuator: ActuatorType,
+    model: &'static str,
+    channel: SensorChannel,
+    plant: Box<dyn Plant>,
+    setpoint: Box<dyn SensorModel>, // The actuator's, on nominal sample time
+    period_secs: f64,
+    initial: f64,
+    input: f64,
+    advanced_to_ns: u64, // The plant has been integrated up to here
+    samples: u64,
+    last_sample: Option<(u64, f64, f64)>, // When, output, setpoint
+    response_start_ns: u64,
+    response_span: f64,      // |Error| when the current response started
+    response_direction: f64, // Its sign
+    overshoot: f64,
+    settled_since_ns: Option<u64>,
+    iae: f64,
+    max_abs_input: f64,
+}
+
+impl Loop {
+    fn advance(&mut self, now_ns: u64) {
+        // Stages read the clock independently, so an older `now_ns` can arrive late
+        if now_ns > self.advanced_to_ns {
+            self.plant.step(self.input, (now_ns - self.advanced_to_ns) as f64 / 1e9);
+            self.advanced_to_ns = now_ns;
+        }
+    }
+
+    fn band(&self) -> f64 {
+        (self.response_span * SETTLING_BAND).max(f64::EPSILON)
+    }
+
+    fn measure(&mut self, cycle_id: u64, now_ns: u64) -> f64 {
+        let output = self.plant.output();
+        // The setpoint the actuator tracks for this sample, which jitter doesn't move
+        let setpoint = self.setpoint.sample(cycle_id as f64 * self.period_secs);
+        let error = setpoint - output;
+
+        match self.last_sample {
+            Some((last_ns, last_output, last_setpoint)) => {
+                let last_error = (last_setpoint - last_output).abs();
+                self.iae += (last_error + error.abs()) / 2.0 * now_ns.saturating_sub(last_ns) as f64 / 1e9;
+                if (setpoint - last_setpoint).abs() > self.band() {
+                    self.start_response(now_ns, error);
+                }
+            }
+            None => self.start_response(now_ns, error),
+        }
+        self.last_sample = Some((now_ns, output, setpoint));
+        self.samples += 1;
+
+        if self.response_span > 0.0 {
+            // Positive once the output has crossed the setpoint it was approaching
+            let past = -error * self.response_direction;
+            self.overshoot = self.overshoot.max(past / self.response_span);
+        }
+        if error.abs() <= self.band() {
+            self.settled_since_ns.get_or_insert(now_ns);
+        } else {
+            self.settled_since_ns = None;
+        }
+        output
+    }
+
+    fn start_response(&mut self, now_ns: u64, error: f64) {
+        self.response_start_ns = now_ns;
+        self.response_span = error.abs();
+        self.response_direction = error.signum();
+        self.settled_since_ns = None;
+    }
+
+    fn performance(&self) -> LoopPerformance {
+        let (final_output, final_setpoint) = self
+            .last_sample
+            .map_or((self.initial, 0.0), |(_, output, setpoint)| (output, setpoint));
+        let final_error = (final_setpoint - final_output).abs();
+        LoopPerformance {
+            actuator: self.actuator,
+            model: self.model,
+            samples: self.samples,
+            initial: self.initial,
+            final_output,
+            final_setpoint,
+            overshoot_percent: self.overshoot * 100.0,
+            settling_time_ms: self.settled_since_ns.map(|ns| (ns - self.response_start_ns) as f64 / 1e6),
+            iae: self.iae,
+            max_abs_input: self.max_abs_input,
+            stable: final_output.is_finite() && final_error <= self.response_span.max(f64::EPSILON),
+        }
+    }
+}
+
+/// Every configured plant of a run, shared by the sensor and the actuators.
+pub struct PlantLoop {
+    loops: Vec<(ActuatorType, Mutex<Loop>)>,
+}
+
+impl PlantLoop {
+    /// `None` when no plant is configured, which leaves every loop open.
+    pub fn from_config(config: &ExperimentConfig) -> Option<Self> {
+        let loops: Vec<_> = ActuatorType::ALL
+            .into_iter()
+            .filter_map(|actuator| {
+                let plant = config.plants.get(actuator)?;
+                Some((actuator, Mutex::new(Loop {
+                    actuator,
+                    model: model_name(plant.dynamics),
+                    channel: plant.channel,
+                    plant: build_plant(plant.dynamics, plant.initial),
+                    setpoint: build_signal(&config.actuators.get(actuator).setpoint, 0),
+                    period_secs: config.sensor_period_ms as f64 / 1000.0,
+                    initial: plant.initial,
+                    input: 0.0,
+                    advanced_to_ns: 0,
+                    samples: 0,
+                    last_sample: None,
+                    response_start_ns: 0,
+                    response_span: 0.0,
+                    response_direction: 0.0,
+                    overshoot: 0.0,
+                    settled_since_ns: None,
+                    iae: 0.0,
+                    max_abs_input: 0.0,
+                })))
+            })
+            .collect();
+        (!loops.is_empty()).then_some(Self { loops })
+    }
+
+    /// Applies `actuator`'s new control output from run time `now_ns` on.
+    pub fn actuate(&self, actuator: ActuatorType, output: f64, now_ns: u64) {
+        let Some((_, plant_loop)) = self.loops.iter().find(|(driven_by, _)| *driven_by == actuator) else {
+            return;
+        };
+        let mut plant_loop = plant_loop.lock().unwrap();
+        plant_loop.advance(now_ns);
+        plant_loop.input = output;
+        plant_loop.max_abs_input = plant_loop.max_abs_input.max(output.abs());
+    }
+
+    /// Measures every plant at run time `now_ns` into its channel of `reading`, scoring it
+    /// against the setpoint for sample `cycle_id`, as the actuators do.
+    pub fn sample(&self, cycle_id: u64, now_ns: u64, reading: &mut SensorReading) {
+        for (_, plant_loop) in &self.loops {
+            let mut plant_loop = plant_loop.lock().unwrap();
+            plant_loop.advance(now_ns);
+            let output = plant_loop.measure(cycle_id, now_ns);
+            plant_loop.channel.write(reading, output);
+        }
+    }
+
+    pub fn performance(&self) -> Vec<LoopPerformance> {
+        self.loops.iter().map(|(_, plant_loop)| plant_loop.lock().unwrap().performance()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{PI, TAU};
+
+    use super::*;
+    use crate::config::SignalConfig;
+
+    const PERIOD_NS: u64 = 1_000_000;
+
+    /// A loop driven open by a constant `input`, sampled every `PERIOD_NS`.
+    fn open_loop(plant: Box<dyn Plant>, setpoint: SignalConfig, initial: f64, input: f64) -> Loop {
+        Loop {
+            actuator: ActuatorType::Motor,
+            model: "test",
+            channel: SensorChannel::Position,
+            plant,
+            setpoint: build_signal(&setpoint, 0),
+            period_secs: PERIOD_NS as f64 / 1e9,
+            initial,
+            input,
+            advanced_to_ns: 0,
+            samples: 0,
+            last_sample: None,
+            response_start_ns: 0,
+            response_span: 0.0,
+            response_direction: 0.0,
+            overshoot: 0.0,
+            settled_since_ns: None,
+            iae: 0.0,
+            max_abs_input: input.abs(),
+        }
+    }
+
+    fn run(plant_loop: &mut Loop, samples: u64) -> LoopPerformance {
+        for cycle_id in 0..samples {
+            plant_loop.advance(cycle_id * PERIOD_NS);
+            plant_loop.measure(cycle_id, cycle_id * PERIOD_NS);
+        }
+        plant_loop.performance()
+    }
+
+    #[test]
+    fn first_order_lag_and_thermal_plants_follow_their_step_responses() {
+        let mut lag = build_plant(PlantDynamics::FirstOrderLag { gain: 2.0, time_constant_secs: 0.05 }, 0.0);
+        for step in 1..=200 {
+            lag.step(1.0, 0.001);
+            let t = step as f64 * 0.001;
+            assert!((lag.output() - 2.0 * (1.0 - (-t / 0.05).exp())).abs() < 1e-9);
+        }
+        // One long interval is split into steps of at most MAX_STEP_SECS
+        let mut lag = build_plant(PlantDynamics::FirstOrderLag { gain: 2.0, time_constant_secs: 0.05 }, 0.0);
+        lag.step(1.0, 0.2);
+        assert!((lag.output() - 2.0 * (1.0 - (-4.0f64).exp())).abs() < 1e-9);
+
+        // Heats toward ambient + R·gain·input with time constant R·C
+        let dynamics = PlantDynamics::Thermal { heat_capacity: 10.0, thermal_resistance: 0.5, ambient: 20.0, heater_gain: 4.0 };
+        let mut thermal = build_plant(dynamics, 25.0);
+        thermal.step(5.0, 3.0);
+        let expected = 30.0 + (25.0 - 30.0) * (-3.0f64 / 5.0).exp();
+        assert!((thermal.output() - expected).abs() < 1e-9);
+    }
+
+    #[test]
+    fn second_and_third_order_plants_match_their_closed_forms() {
+        // Undamped at ω = 2π: x(t) = cos(2πt) from x = 1 at rest
+        let mut spring = build_plant(PlantDynamics::MassSpringDamper { mass_kg: 1.0, stiffness: TAU * TAU, damping: 0.0 }, 1.0);
+        spring.step(0.0, 0.25);
+        assert!(spring.output().abs() < 1e-9);
+        spring.step(0.0, 0.25);
+        assert!((spring.output() + 1.0).abs() < 1e-9);
+
+        // At a constant voltage the shaft settles at kt·V / (R·b + kt²), so the angle grows at that rate
+        let (r, kt, b, volts) = (1.0, 0.5, 0.1, 12.0);
+        let mut motor = build_plant(
+            PlantDynamics::DcMotor { resistance_ohm: r, inductance_h: 0.01, torque_constant: kt, inertia: 0.01, friction: b },
+            0.0,
+        );
+        motor.step(volts, 5.0);
+        let before = motor.output();
+        motor.step(volts, 1.0);
+        assert!((motor.output() - before - kt * volts / (r * b + kt * kt)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn an_overdamped_response_is_scored_without_overshoot() {
+        let tau = 0.05;
+        let plant = build_plant(PlantDynamics::FirstOrderLag { gain: 1.0, time_constant_secs: tau }, 0.0);
+        let performance = run(&mut open_loop(plant, SignalConfig::Constant { value: 1.0 }, 0.0, 1.0), 1_000);
+
+        assert_eq!(performance.samples, 1_000);
+        assert_eq!(performance.overshoot_percent, 0.0);
+        // Within 2% of the unit error once e^(−t/τ) ≤ 0.02, at the first sample after τ·ln 50
+        let settles_ms = (tau * 50f64.ln() * 1e3).ceil();
+        assert_eq!(performance.settling_time_ms, Some(settles_ms));
+        // ∫ e^(−t/τ) dt over the run, up to the trapezoid rule's error
+        assert!((performance.iae - tau * (1.0 - (-0.999 / tau).exp())).abs() < 1e-5);
+        assert!(performance.stable && performance.final_setpoint == 1.0 && performance.max_abs_input == 1.0);
+    }
+
+    #[test]
+    fn an_underdamped_response_overshoots_by_its_damping_ratio() {
+        // ω = 10 rad/s, ζ = 0.2; a force of k holds it at 1
+        let (zeta, omega) = (0.2, 10.0);
+        let dynamics = PlantDynamics::MassSpringDamper { mass_kg: 1.0, stiffness: omega * omega, damping: 2.0 * zeta * omega };
+        let plant_loop = &mut open_loop(build_plant(dynamics, 0.0), SignalConfig::Constant { value: 1.0 }, 0.0, omega * omega);
+        let performance = run(plant_loop, 5_000);
+
+        let expected = (-zeta * PI / (1.0 - zeta * zeta).sqrt()).exp() * 100.0;
+        assert!((performance.overshoot_percent - expected).abs() < 0.05, "{} vs {}", performance.overshoot_percent, expected);
+        assert!(performance.settling_time_ms.is_some_and(|ms| ms > 1_000.0 && ms < 4_000.0));
+        assert!(performance.stable);
+
+        let diverging = PlantDynamics::MassSpringDamper { mass_kg: 1.0, stiffness: 1.0, damping: -1.0 };
+        let plant_loop = &mut open_loop(build_plant(diverging, 0.0), SignalConfig::Constant { value: 1.0 }, 0.0, 1.0);
+        assert!(!run(plant_loop, 20_000).stable);
+    }
+
+    #[test]
+    fn a_setpoint_jump_starts_a_new_response_on_nominal_sample_time() {
+        let plant = build_plant(PlantDynamics::FirstOrderLag { gain: 1.0, time_constant_secs: 0.01 }, 0.0);
+        let plant_loop = &mut open_loop(plant, SignalConfig::Step { from: 0.0, to: 1.0, at_secs: 0.5 }, 0.0, 0.0);
+        run(plant_loop, 500);
+        assert_eq!(plant_loop.performance().iae, 0.0);
+
+        // Sampled late, sample 499 still scores against the setpoint the actuator tracked for it
+        plant_loop.advance(503 * PERIOD_NS);
+        plant_loop.measure(499, 503 * PERIOD_NS);
+        assert_eq!(plant_loop.performance().final_setpoint, 0.0);
+
+        plant_loop.input = 1.0;
+        for cycle_id in 500..1_000 {
+            plant_loop.advance((cycle_id + 4) * PERIOD_NS);
+            plant_loop.measure(cycle_id, (cycle_id + 4) * PERIOD_NS);
+        }
+        let performance = plant_loop.performance();
+        assert_eq!(plant_loop.response_start_ns, 504 * PERIOD_NS);
+        assert_eq!(performance.final_setpoint, 1.0);
+        assert!(performance.settling_time_ms.is_some_and(|ms| ms < 100.0));
+    }
+}
@@ -0,0 +1,272 @@
+//! Recorded sensor traces played back through the pipeline in place of the sensor
+//! models, configured by `[replay]`.
+
+use std::fmt;
+use std::fs;
+use std::io::{BufRead, BufReader};
+
+use serde::Deserialize;
+
+use crate::capture::{CaptureError, CaptureReader, CaptureRecord};
+use crate::config::{ReplayConfig, ReplayEnd, ReplayFormat, ReplayPacing};
+use crate::sensor_model::SensorReading;
+
+/// One recorded sample. Other columns or fields (`id`, `stamps`, ...) are ignored, so a
+/// serialized `SensorData` replays as is.
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct ReplaySample {
+    pub timestamp: u64, // Nanoseconds, on any epoch
+    pub force: f64,
+    pub position: f64,
+    pub temperature: f64,
+}
+
+impl ReplaySample {
+    pub fn reading(&self) -> SensorReading {
+        SensorReading { force: self.force, position: self.position, temperature: self.temperature }
+    }
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io { path: String, source: std::io::Error },
+    Parse { path: String, line: usize, message: String },
+    Capture { path: String, source: CaptureError },
+    Empty { path: String },
+    /// Timestamps must never go backwards.
+    OutOfOrder { path: String, line: usize },
+    UnknownFormat { path: String },
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io { path, source } => write!(f, "failed to read replay trace {}: {}", path, source),
+            ReplayError::Parse { path, line, message } => write!(f, "{}:{}: {}", path, line, message),
+            ReplayError::Capture { path, source } => write!(f, "{}: {}", path, source),
+            ReplayError::Empty { path } => write!(f, "replay trace {} has no samples", path),
+            ReplayError::OutOfOrder { path, line } => write!(f, "{}:{}: timestamp goes backwards", path, line),
+            ReplayError::UnknownFormat { path } => write!(f, "can't tell the format of replay trace {}", path),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// Reads a whole trace and checks that its timestamps are in order. A capture replays
+/// the raw readings its sensor took, so filtering runs on them again as it did live.
+pub fn load_trace(path: &str, format: ReplayFormat) -> Result<Vec<ReplaySample>, ReplayError> {
+    let file = fs::File::open(path).map_err(|source| ReplayError::Io { path: path.to_string(), source })?;
+    let parse_error = |line: usize, message: String| ReplayError::Parse { path: path.to_string(), line, message };
+
+    // Pairs each sample with its line (or capture record) number for error messages
+    let mut samples = Vec::new();
+    match format {
+        ReplayFormat::Csv => {
+            let mut reader = csv::Reader::from_reader(file);
+            for record in reader.deserialize::<ReplaySample>() {
+                let sample = record.map_err(|err| {
+                    let line = err.position().map_or(0, |position| position.line() as usize);
+                    parse_error(line, err.to_string())
+                })?;
+                samples.push((samples.len() + 2, sample)); // Line 1 is the header
+            }
+        }
+        ReplayFormat::Jsonl => {
+            for (index, line) in BufReader::new(file).lines().enumerate() {
+                let line = line.map_err(|source| ReplayError::Io { path: path.to_string(), source })?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let sample = serde_json::from_str(&line).map_err(|err| parse_error(index + 1, err.to_string()))?;
+                samples.push((index + 1, sample));
+            }
+        }
+        ReplayFormat::Capture => {
+            let capture_error = |source| ReplayError::Capture { path: path.to_string(), source };
+            let reader = CaptureReader::new(BufReader::new(file)).map_err(capture_error)?;
+            for (index, entry) in reader.enumerate() {
+                if let CaptureRecord::Sensor { data, raw_force, .. } = entry.map_err(capture_error)?.record {
+                    let sample = ReplaySample {
+                        timestamp: data.timestamp,
+                        force: raw_force,
+                        position: data.position,
+                        temperature: data.temperature,
+                    };
+                    samples.push((index + 1, sample));
+                }
+            }
+        }
+    }
+
+    if samples.is_empty() {
+        return Err(ReplayError::Empty { path: path.to_string() });
+    }
+    if let Some(pair) = samples.windows(2).find(|pair| pair[1].1.timestamp < pair[0].1.timestamp) {
+        return Err(ReplayError::OutOfOrder { path: path.to_string(), line: pair[1].0 });
+    }
+    Ok(samples.into_iter().map(|(_, sample)| sample).collect())
+}
+
+/// Plays a loaded trace back on the run's timeline.
+pub struct Replay {
+    samples: Vec<ReplaySample>,
+    pacing: ReplayPacing,
+    speed: f64,
+    at_end: ReplayEnd,
+    next: usize,
+    loop_offset_ns: u64, // Recorded time already played by earlier loops
+    loop_span_ns: u64,   // One pass, plus one mean sample gap so loops keep the spacing
+}
+
+impl Replay {
+    /// Loads the trace named by `config.path`. `fallback_gap_ns` spaces loops of a trace
+    /// whose samples all share one timestamp, a single sample included.
+    pub fn from_config(config: &ReplayConfig, fallback_gap_ns: u64) -> Result<Self, ReplayError> {
+        let path = config.path.clone().unwrap_or_default();
+        let format = config.format().ok_or_else(|| ReplayError::UnknownFormat { path: path.clone() })?;
+        let samples = load_trace(&path, format)?;
+        Ok(Self::new(samples, config, fallback_gap_ns))
+    }
+
+    pub fn new(samples: Vec<ReplaySample>, config: &ReplayConfig, fallback_gap_ns: u64) -> Self {
+        let first = samples.first().map_or(0, |sample| sample.timestamp);
+        let last = samples.last().map_or(0, |sample| sample.timestamp);
+        // A pass of no length would replay every loop at once
+        let gap_ns = if last == first { fallback_gap_ns } else { (last - first) / (samples.len() as u64 - 1) };
+        Self {
+            samples,
+            pacing: config.pacing,
+            speed: config.speed,
+            at_end: config.at_end,
+            next: 0,
+            loop_offset_ns: 0,
+            loop_span_ns: last - first + gap_ns,
+        }
+    }
+
+    pub fn pacing(&self) -> ReplayPacing {
+        self.pacing
+    }
+
+    /// Next sample and when it's due, in nanoseconds since the run started (`None` when
+    /// unpaced). `None` once the trace is over and doesn't loop.
+    pub fn next_sample(&mut self) -> Option<(Option<u64>, SensorReading)> {
+        if self.next == self.samples.len() {
+            if self.at_end == ReplayEnd::Stop || self.samples.is_empty() {
+                return None;
+            }
+            self.next = 0;
+            self.loop_offset_ns += self.loop_span_ns;
+        }
+        let sample = self.samples[self.next];
+        self.next += 1;
+
+        let recorded_ns = self.loop_offset_ns + (sample.timestamp - self.samples[0].timestamp);
+        let release_ns = match self.pacing {
+            ReplayPacing::RealTime => Some(recorded_ns),
+            ReplayPacing::Accelerated => Some((recorded_ns as f64 / self.speed) as u64),
+            ReplayPacing::AsFastAsPossible => None,
+        };
+        Some((release_ns, sample.reading()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// Writes `content` to a file of its own and loads it as `format`.
+    fn load(name: &str, content: &str, format: ReplayFormat) -> Result<Vec<ReplaySample>, ReplayError> {
+        let path: PathBuf = std::env::temp_dir().join(format!("replay_{}_{}", std::process::id(), name));
+        std::fs::write(&path, content).unwrap();
+        let loaded = load_trace(path.to_str().unwrap(), format);
+        std::fs::remove_file(&path).ok();
+        loaded
+    }
+
+    fn samples(timestamps: &[u64]) -> Vec<ReplaySample> {
+        timestamps
+            .iter()
+            .enumerate()
+            .map(|(index, &timestamp)| ReplaySample { timestamp, force: index as f64, position: 0.0, temperature: 25.0 })
+            .collect()
+    }
+
+    fn play(config: &ReplayConfig, timestamps: &[u64], count: usize) -> Vec<Option<(Option<u64>, f64)>> {
+        let mut replay = Replay::new(samples(timestamps), config, 1_000);
+        (0..count).map(|_| replay.next_sample().map(|(release_ns, reading)| (release_ns, reading.force))).collect()
+    }
+
+    #[test]
+    fn csv_and_jsonl_traces_load_ignoring_extra_fields() {
+        let csv = "id,timestamp,force,position,temperature\n7,1000,1.5,0.25,20.0\n8,3000,2.5,0.5,21.0\n";
+        let jsonl = "{\"timestamp\":1000,\"force\":1.5,\"position\":0.25,\"temperature\":20.0,\"id\":7}\n\n\
+                     {\"timestamp\":3000,\"force\":2.5,\"position\":0.5,\"temperature\":21.0}\n";
+        for loaded in [load("ok.csv", csv, ReplayFormat::Csv), load("ok.jsonl", jsonl, ReplayFormat::Jsonl)] {
+            let loaded = loaded.unwrap();
+            let fields: Vec<_> =
+                loaded.iter().map(|sample| (sample.timestamp, sample.force, sample.position, sample.temperature)).collect();
+            assert_eq!(fields, vec![(1000, 1.5, 0.25, 20.0), (3000, 2.5, 0.5, 21.0)]);
+        }
+    }
+
+    #[test]
+    fn bad_traces_report_the_offending_line() {
+        let backwards = "timestamp,force,position,temperature\n1000,0,0,0\n3000,0,0,0\n2000,0,0,0\n";
+        assert!(matches!(load("back.csv", backwards, ReplayFormat::Csv), Err(ReplayError::OutOfOrder { line: 4, .. })));
+        let backwards = "{\"timestamp\":5,\"force\":0,\"position\":0,\"temperature\":0}\n\n\
+                         {\"timestamp\":4,\"force\":0,\"position\":0,\"temperature\":0}\n";
+        assert!(matches!(load("back.jsonl", backwards, ReplayFormat::Jsonl), Err(ReplayError::OutOfOrder { line: 3, .. })));
+
+        let garbled = "{\"timestamp\":5,\"force\":0,\"position\":0,\"temperature\":0}\n{\"timestamp\":\"soon\"}\n";
+        assert!(matches!(load("bad.jsonl", garbled, ReplayFormat::Jsonl), Err(ReplayError::Parse { line: 2, .. })));
+        let garbled = "timestamp,force,position,temperature\n1000,0,0,0\n2000,lots,0,0\n";
+        assert!(matches!(load("bad.csv", garbled, ReplayFormat::Csv), Err(ReplayError::Parse { line: 3, .. })));
+
+        let empty = "timestamp,force,position,temperature\n";
+        assert!(matches!(load("empty.csv", empty, ReplayFormat::Csv), Err(ReplayError::Empty { .. })));
+    }
+
+    #[test]
+    fn pacing_sets_release_times_from_the_recorded_offsets() {
+        let timestamps = [5_000, 6_000, 9_000];
+        let real_time = ReplayConfig::default();
+        assert_eq!(play(&real_time, &timestamps, 4), vec![
+            Some((Some(0), 0.0)),
+            Some((Some(1_000), 1.0)),
+            Some((Some(4_000), 2.0)),
+            None,
+        ]);
+
+        let accelerated = ReplayConfig { pacing: ReplayPacing::Accelerated, speed: 4.0, ..ReplayConfig::default() };
+        let releases: Vec<_> = play(&accelerated, &timestamps, 3).into_iter().map(|sample| sample.unwrap().0).collect();
+        assert_eq!(releases, vec![Some(0), Some(250), Some(1_000)]);
+
+        let unpaced = ReplayConfig { pacing: ReplayPacing::AsFastAsPossible, ..ReplayConfig::default() };
+        assert!(play(&unpaced, &timestamps, 3).into_iter().all(|sample| sample.unwrap().0.is_none()));
+    }
+
+    #[test]
+    fn loops_continue_one_mean_gap_after_the_last_sample() {
+        let looping = ReplayConfig { at_end: ReplayEnd::Loop, ..ReplayConfig::default() };
+        // One pass spans 4 µs, with a mean gap of 2 µs
+        let played = play(&looping, &[5_000, 6_000, 9_000], 7);
+        let releases: Vec<_> = played.iter().map(|sample| sample.unwrap().0.unwrap()).collect();
+        assert_eq!(releases, vec![0, 1_000, 4_000, 6_000, 7_000, 10_000, 12_000]);
+        let forces: Vec<_> = played.iter().map(|sample| sample.unwrap().1).collect();
+        assert_eq!(forces, vec![0.0, 1.0, 2.0, 0.0, 1.0, 2.0, 0.0]);
+
+        // A single sample loops at the fallback gap
+        let releases: Vec<_> = play(&looping, &[42], 3).into_iter().map(|sample| sample.unwrap().0.unwrap()).collect();
+        assert_eq!(releases, vec![0, 1_000, 2_000]);
+
+        // So do samples that share one timestamp, rather than every loop being due at once
+        let accelerated = ReplayConfig { pacing: ReplayPacing::Accelerated, speed: 2.0, ..looping };
+        let played = play(&accelerated, &[42, 42], 6);
+        let releases: Vec<_> = played.into_iter().map(|sample| sample.unwrap().0.unwrap()).collect();
+        assert_eq!(releases, vec![0, 0, 500, 500, 1_000, 1_000]);
+    }
+}
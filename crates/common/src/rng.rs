@@ -0,0 +1,90 @@
+/// Small deterministic PRNG (SplitMix64) so synthetic workloads and signals
+/// are reproducible from a seed without pulling in an external RNG crate.
+#[derive(Debug, Clone)]
+pub struct SimRng {
+    state: u64,
+}
+
+impl SimRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform sample in (0, 1]
+    pub fn next_f64(&mut self) -> f64 {
+        ((self.next_u64() >> 11) + 1) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn uniform(&mut self, low: f64, high: f64) -> f64 {
+        low + (high - low) * self.next_f64()
+    }
+
+    /// Box-Muller transform
+    pub fn normal(&mut self, mean: f64, std_dev: f64) -> f64 {
+        let u1 = self.next_f64();
+        let u2 = self.next_f64();
+        let z = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+        mean + std_dev * z
+    }
+
+    pub fn exponential(&mut self, mean: f64) -> f64 {
+        -mean * self.next_f64().ln()
+    }
+
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.next_f64() <= probability
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_seed_gives_one_sequence() {
+        let draws = |seed: u64| {
+            let mut rng = SimRng::new(seed);
+            (0..8).map(|_| rng.next_u64()).collect::<Vec<_>>()
+        };
+        assert_eq!(draws(42), draws(42));
+        assert_ne!(draws(42), draws(43));
+        assert_eq!(SimRng::new(0).next_u64(), 0xE220_A839_7B1D_CDAF); // SplitMix64's first output for seed 0
+    }
+
+    #[test]
+    fn samples_follow_their_distributions() {
+        let mut rng = SimRng::new(7);
+        let n = 20_000;
+        let mean = |samples: &[f64]| samples.iter().sum::<f64>() / samples.len() as f64;
+
+        let unit: Vec<f64> = (0..n).map(|_| rng.next_f64()).collect();
+        assert!(unit.iter().all(|&u| u > 0.0 && u <= 1.0));
+        assert!((mean(&unit) - 0.5).abs() < 0.01);
+
+        let uniform: Vec<f64> = (0..n).map(|_| rng.uniform(-2.0, 4.0)).collect();
+        assert!(uniform.iter().all(|&x| (-2.0..=4.0).contains(&x)));
+        assert!((mean(&uniform) - 1.0).abs() < 0.05);
+
+        let normal: Vec<f64> = (0..n).map(|_| rng.normal(10.0, 2.0)).collect();
+        let normal_mean = mean(&normal);
+        let variance = normal.iter().map(|x| (x - normal_mean).powi(2)).sum::<f64>() / n as f64;
+        assert!((normal_mean - 10.0).abs() < 0.05 && (variance.sqrt() - 2.0).abs() < 0.05);
+
+        let exponential: Vec<f64> = (0..n).map(|_| rng.exponential(3.0)).collect();
+        assert!(exponential.iter().all(|&x| x >= 0.0));
+        assert!((mean(&exponential) - 3.0).abs() < 0.1);
+
+        assert!((0..100).all(|_| rng.chance(1.0)));
+        assert!((0..100).all(|_| !rng.chance(0.0)));
+        let hits = (0..n).filter(|_| rng.chance(0.25)).count();
+        assert!((hits as f64 / n as f64 - 0.25).abs() < 0.01);
+    }
+}
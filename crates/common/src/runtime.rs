@@ -0,0 +1,141 @@
+//! One control surface over the experiment implementations, so callers can start,
+//! observe, stop and collect a run without knowing whether it uses threads or tasks.
+
+use std::fmt;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use crate::config::ExperimentConfig;
+use crate::dashboard::DashboardBuffer;
+use crate::diagnostics::SharedDiagnostics;
+use crate::estop::EStopSummary;
+use crate::metrics::{BenchmarkRecorder, Stage};
+use crate::shared_resource::ContentionStats;
+use crate::ActuatorType;
+
+/// Where a runtime is in its run lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeStatus {
+    Idle,     // No run started, or the last run's results were collected
+    Running,
+    Stopping, // Stop requested, components still winding down
+    Finished, // Run over, results waiting in `wait`
+}
+
+#[derive(Debug)]
+pub enum RuntimeError {
+    AlreadyRunning,
+    NotStarted,
+    /// The run's orchestrator panicked or was torn down before returning results.
+    Failed(String),
+    /// A pipeline component panicked; the run was shut down early.
+    Panicked { component: String, message: String },
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeError::AlreadyRunning => write!(f, "an experiment is already running"),
+            RuntimeError::NotStarted => write!(f, "no experiment has been started"),
+            RuntimeError::Failed(reason) => write!(f, "experiment failed: {}", reason),
+            RuntimeError::Panicked { component, message } => write!(f, "{} panicked: {}", component, message),
+        }
+    }
+}
+
+impl std::error::Error for RuntimeError {}
+
+/// An experiment implementation. `start` returns as soon as the pipeline is running;
+/// the run ends after `duration_secs` or on `stop`, whichever comes first.
+pub trait Runtime: Send {
+    /// Short name used in reports and result file names ("threaded", "async", "sim").
+    fn name(&self) -> &'static str;
+
+    /// Streams live samples and metrics into `dashboard` on every later run.
+    fn attach_dashboard(&mut self, dashboard: DashboardBuffer);
+
+    fn start(&mut self, config: ExperimentConfig) -> Result<(), RuntimeError>;
+
+    /// Asks the current run to end early. Its results are still collected by `wait`.
+    fn stop(&self);
+
+    fn status(&self) -> RuntimeStatus;
+
+    /// Blocks until the current run ends and returns its recorder.
+    fn wait(&mut self) -> Result<Arc<BenchmarkRecorder>, RuntimeError>;
+
+    /// Runs one experiment to completion.
+    fn run(&mut self, config: ExperimentConfig) -> Result<Arc<BenchmarkRecorder>, RuntimeError> {
+        self.start(config)?;
+        self.wait()
+    }
+}
+
+/// Startup banner shared by every implementation, printed when logging is on.
+pub fn print_start_banner(title: &str, config: &ExperimentConfig) {
+    println!("===========================================");
+    println!("Real-Time Sensor-Actuator System Starting ({})", title);
+    println!("===========================================");
+    println!("Configuration: {}", config.experiment_name);
+    println!("Duration: {} seconds", config.duration_secs);
+    println!("Sensor period: {} ms", config.sensor_period_ms);
+    println!("Mode: {}", config.mode);
+    println!("Components: Sensor + Dispatcher + 3 Actuators (Gripper, Motor, Stabilizer)");
+    println!("Deadlines: Sensor({}μs/{}μs), Gripper({}μs), Motor({}μs), Stabilizer({}μs), Feedback({}μs)",
+             config.sensor.processing_deadline_us, config.sensor.transmission_deadline_us,
+             config.actuators.gripper.deadline_us, config.actuators.motor.deadline_us,
+             config.actuators.stabilizer.deadline_us, config.actuators.gripper.feedback_deadline_us);
+    println!("Background load: {} threads {:?}", config.cpu_load_threads, config.cpu_load.workers);
+    println!("Workload: {:?}, mean {} ns per stage", config.workload.distribution, config.processing_time_ns);
+    println!("===========================================");
+}
+
+/// End-of-run summary shared by every implementation, printed when logging is on.
+pub fn print_final_summary(
+    title: &str,
+    recorder: &BenchmarkRecorder,
+    diagnostics: &SharedDiagnostics,
+    contention: Option<ContentionStats>,
+) {
+    let summary = recorder.summary(Stage::All);
+
+    println!("===========================================");
+    println!("FINAL {} SYSTEM RESULTS", title.to_uppercase());
+    println!("===========================================");
+    println!("Total Cycles: {}", summary.cycles);
+    println!("Deadline Compliance: {:.2}% ({} missed)", summary.compliance_percent, summary.missed_deadlines);
+    println!("Anomalies Detected: {}", diagnostics.anomaly_count.load(Ordering::Relaxed));
+    println!("Emergency Events: {}", diagnostics.emergency_stops.load(Ordering::Relaxed));
+    match recorder.load_profile() {
+        Some(profile) if profile.threads > 0 => {
+            println!("Background Load: {} threads, {} work units", profile.threads, profile.total_iterations());
+        }
+        Some(_) => println!("Background Load: off"),
+        None => {}
+    }
+    for row in recorder.loop_performance() {
+        let settling = row.settling_time_ms.map_or("not settled".to_string(), |ms| format!("settled at {:.1}ms", ms));
+        println!("{:?} Loop: {} {}, overshoot {:.1}%, final {:.3} (setpoint {:.3})", row.actuator, row.model,
+                 settling, row.overshoot_percent, row.final_output, row.final_setpoint);
+    }
+    let transitions = diagnostics.transitions();
+    for actuator in ActuatorType::ALL {
+        let own: Vec<_> = transitions.iter().filter(|transition| transition.actuator == actuator).collect();
+        if let Some(last) = own.last() {
+            println!("{:?} Status: {:?} after {} transitions", actuator, last.to, own.len());
+        }
+    }
+    let estop = EStopSummary::from_reactions(&recorder.estop_reactions());
+    if estop.stops > 0 {
+        println!("E-Stops: {} ({} reactions, max {:.2}μs, {} over deadline)",
+                 estop.stops, estop.reactions, estop.max_us, estop.missed_deadlines);
+    }
+    if let Some(stats) = contention {
+        println!("Shared Resource: {} accesses, avg wait {:.2}μs, max wait {:.2}μs",
+                 stats.accesses, stats.avg_wait_ns() / 1000.0, stats.max_wait_ns as f64 / 1000.0);
+    }
+    if let Some(report) = recorder.shutdown_report() {
+        println!("Shutdown: {}", report);
+    }
+    println!("===========================================");
+}
@@ -0,0 +1,265 @@
+use std::hint::black_box;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use crate::config::{LockKind, SharedResourceConfig};
+use crate::rng::SimRng;
+use crate::workload::busy_wait;
+
+const STAGE_COUNT: usize = 4; // Sensor + 3 actuators
+const SENSOR_STAGE: usize = 0;
+
+enum Shard {
+    Mutex(Mutex<Vec<f64>>),
+    RwLock(RwLock<Vec<f64>>),
+}
+
+/// Aggregate lock statistics over every access made to the table.
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct ContentionStats {
+    pub accesses: u64,
+    pub total_wait_ns: u64,
+    pub max_wait_ns: u64,
+}
+
+impl ContentionStats {
+    pub fn avg_wait_ns(&self) -> f64 {
+        if self.accesses == 0 {
+            0.0
+        } else {
+            self.total_wait_ns as f64 / self.accesses as f64
+        }
+    }
+}
+
+/// Simulated shared state table used by the "contention" experiment mode.
+/// With `high_contention` every stage goes through a single lock; otherwise each
+/// stage writes its own shard and only reads of the sensor's shard are shared.
+pub struct SharedStateTable {
+    shards: Vec<Shard>,
+    config: SharedResourceConfig,
+    accesses: AtomicU64,
+    total_wait_ns: AtomicU64,
+    max_wait_ns: AtomicU64,
+}
+
+impl SharedStateTable {
+    pub fn new(config: &SharedResourceConfig) -> Self {
+        let shard_count = if config.high_contention { 1 } else { STAGE_COUNT };
+        let entries = config.table_size.max(1);
+        let shards = (0..shard_count)
+            .map(|_| match config.lock {
+                LockKind::RwLock => Shard::RwLock(RwLock::new(vec![0.0; entries])),
+                LockKind::Mutex | LockKind::Spin => Shard::Mutex(Mutex::new(vec![0.0; entries])),
+            })
+            .collect();
+
+        Self {
+            shards,
+            config: config.clone(),
+            accesses: AtomicU64::new(0),
+            total_wait_ns: AtomicU64::new(0),
+            max_wait_ns: AtomicU64::new(0),
+        }
+    }
+
+    /// Handle a single pipeline stage uses to access the table.
+    pub fn client(self: &Arc<Self>, stage: usize, seed: u64) -> SharedResourceClient {
+        SharedResourceClient {
+            table: Arc::clone(self),
+            stage,
+            rng: SimRng::new(seed ^ (stage as u64).wrapping_mul(0xD1B5_4A32_D192_ED03)),
+        }
+    }
+
+    pub fn stats(&self) -> ContentionStats {
+        ContentionStats {
+            accesses: self.accesses.load(Ordering::Relaxed),
+            total_wait_ns: self.total_wait_ns.load(Ordering::Relaxed),
+            max_wait_ns: self.max_wait_ns.load(Ordering::Relaxed),
+        }
+    }
+
+    fn shard_for(&self, stage: usize) -> &Shard {
+        &self.shards[stage % self.shards.len()]
+    }
+
+    fn slot(&self, stage: usize, access: u32) -> usize {
+        (stage * 16 + access as usize) % self.config.table_size.max(1)
+    }
+
+    fn critical_section(&self, entries: &[f64]) {
+        busy_wait(Duration::from_nanos(self.config.critical_section_ns));
+        black_box(entries);
+    }
+
+    /// Writes `value` into the stage's entry, returning the time spent waiting for the lock.
+    fn write(&self, stage: usize, access: u32, value: f64) -> Duration {
+        let slot = self.slot(stage, access);
+        let lock_start = Instant::now();
+        let wait = match self.shard_for(stage) {
+            Shard::Mutex(lock) => {
+                let mut entries = if self.config.lock == LockKind::Spin {
+                    spin_lock(lock)
+                } else {
+                    lock.lock().unwrap()
+                };
+                let wait = lock_start.elapsed();
+                entries[slot] = value;
+                self.critical_section(&entries);
+                wait
+            }
+            Shard::RwLock(lock) => {
+                let mut entries = lock.write().unwrap();
+                let wait = lock_start.elapsed();
+                entries[slot] = value;
+                self.critical_section(&entries);
+                wait
+            }
+        };
+        self.record_wait(wait);
+        wait
+    }
+
+    /// Reads the sensor's entry, returning the time spent waiting for the lock.
+    fn read(&self, access: u32) -> Duration {
+        let slot = self.slot(SENSOR_STAGE, access);
+        let lock_start = Instant::now();
+        let wait = match self.shard_for(SENSOR_STAGE) {
+            Shard::Mutex(lock) => {
+                let entries = if self.config.lock == LockKind::Spin {
+                    spin_lock(lock)
+                } else {
+                    lock.lock().unwrap()
+                };
+                let wait = lock_start.elapsed();
+                black_box(entries[slot]);
+                self.critical_section(&entries);
+                wait
+            }
+            Shard::RwLock(lock) => {
+                let entries = lock.read().unwrap();
+                let wait = lock_start.elapsed();
+                black_box(entries[slot]);
+                self.critical_section(&entries);
+                wait
+            }
+        };
+        self.record_wait(wait);
+        wait
+    }
+
+    fn record_wait(&self, wait: Duration) {
+        let wait_ns = wait.as_nanos() as u64;
+        self.accesses.fetch_add(1, Ordering::Relaxed);
+        self.total_wait_ns.fetch_add(wait_ns, Ordering::Relaxed);
+        self.max_wait_ns.fetch_max(wait_ns, Ordering::Relaxed);
+    }
+}
+
+fn spin_lock<T>(lock: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    loop {
+        match lock.try_lock() {
+            Ok(guard) => return guard,
+            Err(std::sync::TryLockError::WouldBlock) => std::hint::spin_loop(),
+            Err(std::sync::TryLockError::Poisoned(e)) => panic!("shared state table poisoned: {}", e),
+        }
+    }
+}
+
+/// Per-stage accessor that performs the configured number of accesses each cycle.
+pub struct SharedResourceClient {
+    table: Arc<SharedStateTable>,
+    stage: usize,
+    rng: SimRng,
+}
+
+impl SharedResourceClient {
+    /// Performs one cycle's worth of accesses and returns the summed lock wait.
+    /// The sensor only writes; actuators read the sensor's entry with probability `read_ratio`.
+    pub fn access_cycle(&mut self, value: f64) -> Duration {
+        let config = &self.table.config;
+        let mut total_wait = Duration::ZERO;
+        for access in 0..config.accesses_per_cycle {
+            let is_read = self.stage != SENSOR_STAGE && self.rng.chance(config.read_ratio);
+            total_wait += if is_read {
+                self.table.read(access)
+            } else {
+                self.table.write(self.stage, access, value)
+            };
+        }
+        total_wait
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    const HELD: Duration = Duration::from_millis(20);
+
+    fn table(high_contention: bool, lock: LockKind) -> Arc<SharedStateTable> {
+        Arc::new(SharedStateTable::new(&SharedResourceConfig {
+            high_contention,
+            lock,
+            critical_section_ns: 0,
+            accesses_per_cycle: 2,
+            ..SharedResourceConfig::default()
+        }))
+    }
+
+    /// Runs one cycle of `client` while the test holds the lock of `stage`'s shard for `HELD`.
+    fn cycle_against_held_shard(table: &Arc<SharedStateTable>, stage: usize, mut client: SharedResourceClient) -> Duration {
+        let Shard::Mutex(lock) = table.shard_for(stage) else {
+            panic!("mutex shard expected");
+        };
+        let guard = lock.lock().unwrap();
+        let cycle = thread::spawn(move || client.access_cycle(1.0));
+        thread::sleep(HELD);
+        drop(guard);
+        cycle.join().unwrap()
+    }
+
+    #[test]
+    fn lock_waits_are_counted_per_access() {
+        let table = table(true, LockKind::Mutex);
+        assert_eq!(table.stats().avg_wait_ns(), 0.0);
+
+        let wait = cycle_against_held_shard(&table, SENSOR_STAGE, table.client(1, 0));
+        assert!(wait >= HELD);
+        let stats = table.stats();
+        assert_eq!(stats.accesses, 2);
+        assert!(stats.max_wait_ns >= HELD.as_nanos() as u64 && stats.max_wait_ns <= stats.total_wait_ns);
+        assert_eq!(stats.avg_wait_ns(), stats.total_wait_ns as f64 / 2.0);
+    }
+
+    #[test]
+    fn only_reads_of_the_sensor_share_a_lock_in_low_contention() {
+        let sharded = table(false, LockKind::Spin);
+        let writer = cycle_against_held_shard(&sharded, SENSOR_STAGE, sharded.client(2, 0));
+        assert!(writer < HELD, "an actuator writes its own shard");
+
+        let reads = Arc::new(SharedStateTable::new(&SharedResourceConfig { read_ratio: 1.0, ..sharded.config.clone() }));
+        let reader = cycle_against_held_shard(&reads, SENSOR_STAGE, reads.client(2, 0));
+        assert!(reader >= HELD, "an actuator reads the sensor's shard");
+    }
+
+    #[test]
+    fn rwlock_tables_write_and_read_the_sensor_entry() {
+        let table = Arc::new(SharedStateTable::new(&SharedResourceConfig {
+            lock: LockKind::RwLock,
+            critical_section_ns: 0,
+            read_ratio: 1.0,
+            ..SharedResourceConfig::default()
+        }));
+        table.client(SENSOR_STAGE, 0).access_cycle(3.5);
+        table.client(1, 0).access_cycle(0.0);
+        let Shard::RwLock(lock) = table.shard_for(SENSOR_STAGE) else {
+            panic!("rwlock shard expected");
+        };
+        assert_eq!(lock.read().unwrap()[table.slot(SENSOR_STAGE, 0)], 3.5, "the actuator only read it");
+        assert_eq!(table.stats().accesses, 2);
+    }
+}
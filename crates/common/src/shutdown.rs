@@ -0,0 +1,47 @@
+use std::fmt;
+use std::time::Duration;
+
+use serde::Serialize;
+
+/// How a run's components wound down after its duration was up.
+/// Component names are listed in pipeline order.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ShutdownReport {
+    pub elapsed: Duration,  // Stop request -> last component joined (or deadline hit)
+    pub deadline: Duration,
+    pub stopped: Vec<String>,
+    pub failed_to_stop: Vec<String>, // Still running at the deadline, abandoned
+    pub panicked: Vec<String>,
+    pub rejected_records: usize, // Written after the recorder was sealed, by components left running
+}
+
+impl ShutdownReport {
+    pub fn is_clean(&self) -> bool {
+        self.failed_to_stop.is_empty() && self.panicked.is_empty()
+    }
+}
+
+impl fmt::Display for ShutdownReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_clean() {
+            write!(f, "clean, {} components stopped in {:.1}ms",
+                   self.stopped.len(), self.elapsed.as_secs_f64() * 1000.0)?;
+        } else {
+            write!(f, "{} of {} components stopped in {:.1}ms",
+                   self.stopped.len(),
+                   self.stopped.len() + self.failed_to_stop.len() + self.panicked.len(),
+                   self.elapsed.as_secs_f64() * 1000.0)?;
+            if !self.failed_to_stop.is_empty() {
+                write!(f, "; failed to stop within {}ms: {}",
+                       self.deadline.as_millis(), self.failed_to_stop.join(", "))?;
+            }
+            if !self.panicked.is_empty() {
+                write!(f, "; panicked: {}", self.panicked.join(", "))?;
+            }
+        }
+        if self.rejected_records > 0 {
+            write!(f, "; {} late records rejected", self.rejected_records)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,413 @@
+//! Actuator status machine, configured per actuator by `[actuators.<name>.status]`.
+//!
+//! Init classifies the first cycle. Normal and Correcting follow the error with
+//! hysteresis, and any running status drops to Degraded on consecutive deadline misses.
+//! An error past `emergency_threshold` latches Emergency; left unacknowledged it becomes
+//! SafeStop, which holds the output safe. Acknowledging releases either into Recovering
+//! once the error has cleared, and Recovering returns to Normal after its dwell. Reset
+//! returns to Init from anywhere. An e-stop (`crate::estop`) holds the machine in
+//! SafeStop, refusing both commands, until the stop itself is acknowledged.
+
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::config::{StatusCommand, StatusConfig};
+use crate::{ActuatorStatus, ActuatorType};
+
+/// Why a status changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransitionCause {
+    Started,
+    ErrorAbove,
+    ErrorCleared,
+    DeadlineMisses,
+    DeadlinesMet,
+    EmergencyHeld,
+    EStop,
+    Acknowledged,
+    Reset,
+}
+
+impl fmt::Display for TransitionCause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TransitionCause::Started => "first cycle",
+            TransitionCause::ErrorAbove => "error above threshold",
+            TransitionCause::ErrorCleared => "error cleared",
+            TransitionCause::DeadlineMisses => "deadline misses",
+            TransitionCause::DeadlinesMet => "deadlines met again",
+            TransitionCause::EmergencyHeld => "emergency unacknowledged",
+            TransitionCause::EStop => "emergency stop",
+            TransitionCause::Acknowledged => "acknowledged",
+            TransitionCause::Reset => "reset",
+        })
+    }
+}
+
+/// One status change, as recorded to diagnostics.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct StatusTransition {
+    pub at_ns: u64, // Run time
+    pub actuator: ActuatorType,
+    pub sensor_id: u64, // Sample being handled when it changed
+    pub from: ActuatorStatus,
+    pub to: ActuatorStatus,
+    pub cause: TransitionCause,
+}
+
+impl StatusTransition {
+    /// Whether the controller should start afresh: after a reset, or when coming out
+    /// of SafeStop with whatever it accumulated while its output was overridden.
+    pub fn resets_controller(&self) -> bool {
+        self.to == ActuatorStatus::Init || self.from == ActuatorStatus::SafeStop
+    }
+}
+
+/// What the machine sees of one actuator cycle.
+#[derive(Debug, Clone, Copy)]
+pub struct StatusInput {
+    pub now_ns: u64,  // Run time, for dwell times
+    pub t_secs: f64,  // Nominal sample time, for scheduled commands
+    pub sensor_id: u64,
+    pub abs_error: f64,
+    pub correcting_threshold: f64, // Current one; it adapts at runtime
+    pub deadline_met: bool,
+}
+
+pub struct StatusMachine {
+    actuator: ActuatorType,
+    config: StatusConfig,
+    emergency_threshold: f64,
+    status: ActuatorStatus,
+    entered_ns: u64,
+    acknowledged: bool, // Pending release of a latched status
+    held: bool,         // By an e-stop, until it is acknowledged
+    misses: u32,        // Consecutive deadline misses
+    met: u32,           // Consecutive deadlines met
+    next_command: usize,
+    pending: Vec<StatusCommand>, // Issued outside the schedule, applied on the next cycle
+}
+
+impl StatusMachine {
+    pub fn new(actuator: ActuatorType, config: StatusConfig, emergency_threshold: f64) -> Self {
+        Self {
+            actuator,
+            config,
+            emergency_threshold,
+            status: ActuatorStatus::Init,
+            entered_ns: 0,
+            acknowledged: false,
+            held: false,
+            misses: 0,
+            met: 0,
+            next_command: 0,
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn status(&self) -> ActuatorStatus {
+        self.status
+    }
+
+    /// The output SafeStop holds in place of the controller's.
+    pub fn output_override(&self) -> Option<f64> {
+        (self.status == ActuatorStatus::SafeStop).then_some(self.config.safe_output)
+    }
+
+    /// Issues `command` outside the schedule, as the dashboard does; it takes effect on
+    /// the next cycle, or once an e-stop holding the machine lets go.
+    pub fn command(&mut self, command: StatusCommand) {
+        self.pending.push(command);
+    }
+
+    /// Holds the machine in SafeStop for an e-stop triggered by sample `sensor_id`.
+    pub fn hold(&mut self, now_ns: u64, sensor_id: u64) -> Vec<StatusTransition> {
+        let mut transitions = Vec::new();
+        self.held = true;
+        if self.status != ActuatorStatus::SafeStop {
+            self.enter(ActuatorStatus::SafeStop, TransitionCause::EStop, now_ns, sensor_id, &mut transitions);
+        }
+        transitions
+    }
+
+    /// Whether an e-stop is holding the machine; commands wait until it lets go.
+    pub fn is_held(&self) -> bool {
+        self.held
+    }
+
+    /// Lets go once the e-stop is acknowledged, which also acknowledges the SafeStop.
+    pub fn release(&mut self) {
+        self.held = false;
+        self.acknowledged = self.is_latched();
+    }
+
+    /// Advances the machine by one cycle and returns the transitions it made, in order.
+    pub fn update(&mut self, input: &StatusInput) -> Vec<StatusTransition> {
+        let mut transitions = Vec::new();
+
+        if input.deadline_met {
+            self.misses = 0;
+            self.met += 1;
+        } else {
+            self.misses += 1;
+            self.met = 0;
+        }
+
+        let mut commands = std::mem::take(&mut self.pending);
+        while let Some(scheduled) = self.config.commands.get(self.next_command).copied() {
+            if scheduled.at_secs > input.t_secs {
+                break;
+            }
+            commands.push(scheduled.command);
+            self.next_command += 1;
+        }
+        for command in commands {
+            match command {
+                _ if self.held => self.pending.push(command), // Only acknowledging the e-stop lets go
+                StatusCommand::Acknowledge => self.acknowledged = self.is_latched(),
+                StatusCommand::Reset => {
+                    self.enter(ActuatorStatus::Init, TransitionCause::Reset, input.now_ns, input.sensor_id, &mut transitions)
+                }
+            }
+        }
+
+        let held_ns = input.now_ns.saturating_sub(self.entered_ns);
+        let auto_acknowledge = self.config.auto_acknowledge_ms.is_some_and(|ms| held_ns >= ms.saturating_mul(1_000_000));
+        if self.is_latched() && !self.held && auto_acknowledge {
+            self.acknowledged = true;
+        }
+
+        if let Some((to, cause)) = self.next(input, held_ns) {
+            self.enter(to, cause, input.now_ns, input.sensor_id, &mut transitions);
+        }
+        transitions
+    }
+
+    fn is_latched(&self) -> bool {
+        matches!(self.status, ActuatorStatus::Emergency | ActuatorStatus::SafeStop)
+    }
+
+    fn next(&self, input: &StatusInput, held_ns: u64) -> Option<(ActuatorStatus, TransitionCause)> {
+        use ActuatorStatus::*;
+
+        let easing = 1.0 - self.config.hysteresis;
+        let emergency = input.abs_error > self.emergency_threshold;
+        let emergency_cleared = input.abs_error < self.emergency_threshold * easing;
+        let correcting = input.abs_error > input.correcting_threshold;
+        let correcting_cleared = input.abs_error < input.correcting_threshold * easing;
+        let degraded = self.misses >= self.config.degraded_after_misses;
+        let restored = self.met >= self.config.degraded_after_misses;
+        let dwelled = held_ns >= self.config.min_dwell_ms.saturating_mul(1_000_000);
+
+        match self.status {
+            SafeStop if self.held => None,
+            Init if emergency => Some((Emergency, TransitionCause::Started)),
+            Init if correcting => Some((Correcting, TransitionCause::Started)),
+            Init => Some((Normal, TransitionCause::Started)),
+            Emergency | SafeStop if self.acknowledged && emergency_cleared => {
+                Some((Recovering, TransitionCause::Acknowledged))
+            }
+            Emergency if held_ns >= self.config.safe_stop_after_ms.saturating_mul(1_000_000) => {
+                Some((SafeStop, TransitionCause::EmergencyHeld))
+            }
+            Emergency | SafeStop => None,
+            _ if emergency => Some((Emergency, TransitionCause::ErrorAbove)),
+            Normal | Correcting | Recovering if degraded => Some((Degraded, TransitionCause::DeadlineMisses)),
+            Normal if correcting => Some((Correcting, TransitionCause::ErrorAbove)),
+            Correcting | Recovering if dwelled && correcting_cleared => Some((Normal, TransitionCause::ErrorCleared)),
+            Degraded if dwelled && restored => {
+                Some((if correcting { Correcting } else { Normal }, TransitionCause::DeadlinesMet))
+            }
+            _ => None,
+        }
+    }
+
+    fn enter(
+        &mut self,
+        to: ActuatorStatus,
+        cause: TransitionCause,
+        at_ns: u64,
+        sensor_id: u64,
+        transitions: &mut Vec<StatusTransition>,
+    ) {
+        transitions.push(StatusTransition {
+            at_ns,
+            actuator: self.actuator,
+            sensor_id,
+            from: self.status,
+            to,
+            cause,
+        });
+        if to != ActuatorStatus::SafeStop {
+            // SafeStop keeps an acknowledgement given during the Emergency before it
+            self.acknowledged = false;
+        }
+        if to == ActuatorStatus::Init {
+            self.misses = 0;
+            self.met = 0;
+        }
+        self.status = to;
+        self.entered_ns = at_ns;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ScheduledCommand;
+    use ActuatorStatus::*;
+
+    const EMERGENCY: f64 = 10.0;
+    const CORRECTING: f64 = 5.0;
+
+    fn machine(config: StatusConfig) -> StatusMachine {
+        StatusMachine::new(ActuatorType::Motor, config, EMERGENCY)
+    }
+
+    /// One cycle at `ms` of run and sample time; returns the transitions it made.
+    fn cycle(machine: &mut StatusMachine, ms: u64, abs_error: f64) -> Vec<(ActuatorStatus, ActuatorStatus, TransitionCause)> {
+        cycle_with(machine, ms, abs_error, true)
+    }
+
+    fn cycle_with(
+        machine: &mut StatusMachine,
+        ms: u64,
+        abs_error: f64,
+        deadline_met: bool,
+    ) -> Vec<(ActuatorStatus, ActuatorStatus, TransitionCause)> {
+        let input = StatusInput {
+            now_ns: ms * 1_000_000,
+            t_secs: ms as f64 / 1000.0,
+            sensor_id: ms,
+            abs_error,
+            correcting_threshold: CORRECTING,
+            deadline_met,
+        };
+        machine.update(&input).into_iter().map(|transition| (transition.from, transition.to, transition.cause)).collect()
+    }
+
+    #[test]
+    fn error_thresholds_switch_status_with_hysteresis_and_dwell() {
+        let mut machine = machine(StatusConfig::default());
+        assert_eq!(cycle(&mut machine, 0, 1.0), vec![(Init, Normal, TransitionCause::Started)]);
+        assert_eq!(cycle(&mut machine, 10, 6.0), vec![(Normal, Correcting, TransitionCause::ErrorAbove)]);
+        // Cleared before the dwell is up, then below the threshold but inside the hysteresis band
+        assert!(cycle(&mut machine, 20, 1.0).is_empty());
+        assert!(cycle(&mut machine, 30, 4.5).is_empty());
+        assert_eq!(cycle(&mut machine, 40, 1.0), vec![(Correcting, Normal, TransitionCause::ErrorCleared)]);
+
+        for ms in [50, 60] {
+            assert!(cycle_with(&mut machine, ms, 1.0, false).is_empty());
+        }
+        assert_eq!(cycle_with(&mut machine, 70, 1.0, false), vec![(Normal, Degraded, TransitionCause::DeadlineMisses)]);
+    }
+
+    #[test]
+    fn emergency_latches_until_acknowledged_then_recovers() {
+        let mut machine = machine(StatusConfig::default());
+        cycle(&mut machine, 0, 1.0);
+        assert_eq!(cycle(&mut machine, 10, 20.0), vec![(Normal, Emergency, TransitionCause::ErrorAbove)]);
+        // The error clearing doesn't release it
+        assert!(cycle(&mut machine, 20, 0.0).is_empty());
+        assert_eq!(machine.status(), Emergency);
+
+        // Acknowledged while the error is still high: held until it clears
+        machine.command(StatusCommand::Acknowledge);
+        assert!(cycle(&mut machine, 30, 9.5).is_empty());
+        assert_eq!(cycle(&mut machine, 40, 1.0), vec![(Emergency, Recovering, TransitionCause::Acknowledged)]);
+        assert_eq!(cycle(&mut machine, 70, 1.0), vec![(Recovering, Normal, TransitionCause::ErrorCleared)]);
+    }
+
+    #[test]
+    fn the_longest_timeouts_never_expire() {
+        let forever = StatusConfig {
+            min_dwell_ms: u64::MAX,
+            safe_stop_after_ms: u64::MAX,
+            auto_acknowledge_ms: Some(u64::MAX),
+            ..StatusConfig::default()
+        };
+        let mut machine = machine(forever);
+        cycle(&mut machine, 0, 20.0);
+        assert!(cycle(&mut machine, 10_000_000, 0.0).is_empty());
+        assert_eq!(machine.status(), Emergency);
+    }
+
+    #[test]
+    fn an_unacknowledged_emergency_becomes_safe_stop() {
+        let mut machine = machine(StatusConfig { safe_output: -1.0, ..StatusConfig::default() });
+        cycle(&mut machine, 0, 20.0);
+        assert_eq!(machine.status(), Emergency);
+        assert!(cycle(&mut machine, 90, 0.0).is_empty());
+        assert_eq!(machine.output_override(), None);
+        assert_eq!(cycle(&mut machine, 100, 0.0), vec![(Emergency, SafeStop, TransitionCause::EmergencyHeld)]);
+        assert_eq!(machine.output_override(), Some(-1.0));
+
+        machine.command(StatusCommand::Acknowledge);
+        assert_eq!(cycle(&mut machine, 110, 0.0), vec![(SafeStop, Recovering, TransitionCause::Acknowledged)]);
+
+        // Acknowledging anything but a latched status does nothing, not even for the next latch
+        machine.command(StatusCommand::Acknowledge);
+        assert_eq!(cycle(&mut machine, 140, 0.0), vec![(Recovering, Normal, TransitionCause::ErrorCleared)]);
+        assert_eq!(cycle(&mut machine, 150, 20.0), vec![(Normal, Emergency, TransitionCause::ErrorAbove)]);
+        assert!(cycle(&mut machine, 160, 0.0).is_empty());
+    }
+
+    #[test]
+    fn reset_returns_to_init_from_any_status() {
+        let mut machine = machine(StatusConfig::default());
+        cycle(&mut machine, 0, 20.0);
+        cycle(&mut machine, 100, 20.0);
+        assert_eq!(machine.status(), SafeStop);
+        machine.command(StatusCommand::Reset);
+        let transitions = cycle(&mut machine, 110, 20.0);
+        assert_eq!(transitions, vec![(SafeStop, Init, TransitionCause::Reset), (Init, Emergency, TransitionCause::Started)]);
+
+        machine.command(StatusCommand::Reset);
+        let transitions = cycle(&mut machine, 120, 1.0);
+        assert_eq!(transitions, vec![(Emergency, Init, TransitionCause::Reset), (Init, Normal, TransitionCause::Started)]);
+    }
+
+    #[test]
+    fn scheduled_commands_apply_once_due() {
+        let commands = vec![
+            ScheduledCommand { at_secs: 0.05, command: StatusCommand::Acknowledge },
+            ScheduledCommand { at_secs: 0.2, command: StatusCommand::Reset },
+        ];
+        let mut machine = machine(StatusConfig { commands, ..StatusConfig::default() });
+        cycle(&mut machine, 0, 20.0);
+        assert!(cycle(&mut machine, 40, 0.0).is_empty());
+        assert_eq!(cycle(&mut machine, 50, 0.0), vec![(Emergency, Recovering, TransitionCause::Acknowledged)]);
+        assert!(cycle(&mut machine, 60, 1.0).is_empty());
+        let transitions = cycle(&mut machine, 200, 1.0);
+        assert_eq!(transitions, vec![(Recovering, Init, TransitionCause::Reset), (Init, Normal, TransitionCause::Started)]);
+    }
+
+    #[test]
+    fn an_estop_hold_defers_commands_until_released() {
+        let mut machine = machine(StatusConfig::default());
+        cycle(&mut machine, 0, 1.0);
+        let held = machine.hold(5_000_000, 0);
+        let held: Vec<_> = held.iter().map(|transition| (transition.to, transition.cause)).collect();
+        assert_eq!(held, vec![(SafeStop, TransitionCause::EStop)]);
+        assert!(machine.is_held());
+        machine.command(StatusCommand::Acknowledge);
+        assert!(cycle(&mut machine, 10, 0.0).is_empty());
+        assert_eq!(machine.status(), SafeStop);
+
+        // Releasing the stop acknowledges the SafeStop it caused
+        machine.release();
+        assert_eq!(cycle(&mut machine, 20, 0.0), vec![(SafeStop, Recovering, TransitionCause::Acknowledged)]);
+
+        // A reset issued during a hold applies once it is released
+        machine.hold(30_000_000, 3);
+        machine.command(StatusCommand::Reset);
+        assert!(cycle(&mut machine, 40, 0.0).is_empty());
+        machine.release();
+        assert_eq!(cycle(&mut machine, 50, 0.0), vec![
+            (SafeStop, Init, TransitionCause::Reset),
+            (Init, Normal, TransitionCause::Started),
+        ]);
+    }
+}
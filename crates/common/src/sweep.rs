@@ -0,0 +1,342 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use serde::Deserialize;
+use toml::{Table, Value};
+use crate::config::{merge_tables, ConfigError, ConfigIssue, ExperimentConfig};
+use crate::config_validation::{locate_issues, unknown_field_issues};
+
+/// Special sweep key selecting which implementation runs a point.
+pub const IMPL_KEY: &str = "impl";
+
+/// Which pipeline implementation a sweep point runs on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Implementation {
+    Threaded,
+    Async,
+    Sim, // Deterministic simulation in virtual time
+}
+
+impl Implementation {
+    /// The real-time implementations, compared by default. `Sim` is opt-in.
+    pub const ALL: [Implementation; 2] = [Implementation::Threaded, Implementation::Async];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Implementation::Threaded => "threaded",
+            Implementation::Async => "async",
+            Implementation::Sim => "sim",
+        }
+    }
+}
+
+impl fmt::Display for Implementation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Implementation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "threaded" => Ok(Implementation::Threaded),
+            "async" => Ok(Implementation::Async),
+            "sim" => Ok(Implementation::Sim),
+            other => Err(format!("unknown implementation \"{}\" (expected \"threaded\", \"async\" or \"sim\")", other)),
+        }
+    }
+}
+
+/// Experiment matrix description. Every key in `[sweep]` is a config field path
+/// (e.g. `sensor_period_ms` or `workload.distribution`) mapped to the values to try,
+/// plus the special `impl` key; the Cartesian product of all lists is run.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SweepConfig {
+    pub base_config: String,
+    #[serde(default = "default_repetitions")]
+    pub repetitions: u32,
+    #[serde(default = "default_output")]
+    pub output: String,
+    #[serde(default)]
+    pub overrides: Table, // Applied to the base config before every point
+    #[serde(default)]
+    pub strict: bool, // Reject unknown keys anywhere in a point, not just in [sweep]
+    pub sweep: Table,
+}
+
+fn default_repetitions() -> u32 {
+    1
+}
+
+fn default_output() -> String {
+    "sweep_results.csv".to_string()
+}
+
+/// One fully resolved combination of sweep values.
+#[derive(Debug, Clone)]
+pub struct SweepPoint {
+    pub params: Vec<(String, String)>, // Parameter name and value, in [sweep] order
+    pub implementation: Implementation,
+    pub config: ExperimentConfig,
+}
+
+impl SweepPoint {
+    /// Compact `name=value` description used in progress output and error messages.
+    pub fn label(&self) -> String {
+        format_params(&self.params)
+    }
+}
+
+fn format_params(params: &[(String, String)]) -> String {
+    params
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Reads a sweep description, checking its own structure before any point is expanded.
+/// A relative `base_config` is resolved against the sweep file's directory.
+pub fn load_sweep(path: &str) -> Result<SweepConfig, ConfigError> {
+    let content = fs::read_to_string(path).map_err(|source| ConfigError::Io {
+        path: path.to_string(),
+        source,
+    })?;
+    let mut sweep: SweepConfig =
+        toml::from_str(&content).map_err(|err| ConfigError::from_toml(path, &content, err))?;
+    if let Some(dir) = Path::new(path).parent() {
+        sweep.base_config = dir.join(&sweep.base_config).to_string_lossy().into_owned();
+    }
+
+    let mut issues = sweep.structure_issues();
+    if issues.is_empty() {
+        Ok(sweep)
+    } else {
+        locate_issues(&content, &mut issues);
+        Err(ConfigError::Invalid { path: path.to_string(), issues })
+    }
+}
+
+impl SweepConfig {
+    fn structure_issues(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+        let mut issue = |field: String, message: String| {
+            issues.push(ConfigIssue { field, line: None, message });
+        };
+
+        if self.repetitions == 0 {
+            issue("repetitions".to_string(), "must be greater than 0".to_string());
+        }
+        if self.sweep.is_empty() {
+            issue("sweep".to_string(), "must list at least one parameter".to_string());
+        }
+        for (name, values) in &self.sweep {
+            let field = format!("sweep.{}", name);
+            match values.as_array() {
+                Some(values) if values.is_empty() => issue(field, "must list at least one value".to_string()),
+                Some(values) if name == IMPL_KEY => {
+                    for value in values {
+                        if let Err(message) = value.as_str().unwrap_or_default().parse::<Implementation>() {
+                            issue(field.clone(), message);
+                        }
+                    }
+                }
+                Some(_) => {}
+                None => issue(field, "must be an array of values".to_string()),
+            }
+        }
+        issues
+    }
+
+    /// Forces `path` (a dotted config field) to `value` for every point.
+    pub fn set_override(&mut self, path: &str, value: impl Into<Value>) {
+        set_path(&mut self.overrides, path, value.into());
+    }
+
+    /// Merges a partial config, such as tuned gains, into the overrides.
+    pub fn add_overlay(&mut self, overlay: &Table) {
+        merge_tables(&mut self.overrides, overlay);
+    }
+
+    /// Number of points in the matrix (not counting repetitions).
+    pub fn point_count(&self) -> usize {
+        let mut count: usize = self.sweep.values().filter_map(Value::as_array).map(Vec::len).product();
+        if !self.sweep.contains_key(IMPL_KEY) {
+            count *= Implementation::ALL.len();
+        }
+        count
+    }
+
+    /// Expands the matrix against the base config. Every point is validated up front so
+    /// a bad combination is reported before hours of runs rather than part-way through.
+    /// Without an `impl` key each point runs on both implementations.
+    pub fn expand(&self) -> Result<Vec<SweepPoint>, ConfigError> {
+        let base_content = fs::read_to_string(&self.base_config).map_err(|source| ConfigError::Io {
+            path: self.base_config.clone(),
+            source,
+        })?;
+        let mut base: Table = toml::from_str(&base_content)
+            .map_err(|err| ConfigError::from_toml(&self.base_config, &base_content, err))?;
+        merge_tables(&mut base, &self.overrides);
+
+        let mut axes: Vec<(String, Vec<Value>)> = self
+            .sweep
+            .iter()
+            .map(|(name, values)| (name.clone(), values.as_array().cloned().unwrap_or_default()))
+            .collect();
+        if !self.sweep.contains_key(IMPL_KEY) {
+            let impls = Implementation::ALL.iter().map(|i| Value::String(i.as_str().to_string())).collect();
+            axes.push((IMPL_KEY.to_string(), impls));
+        }
+
+        // Cartesian product with the first [sweep] key varying slowest
+        let mut combinations: Vec<Vec<&Value>> = vec![Vec::new()];
+        for (_, values) in &axes {
+            combinations = combinations
+                .into_iter()
+                .flat_map(|prefix| {
+                    values.iter().map(move |value| {
+                        let mut combination = prefix.clone();
+                        combination.push(value);
+                        combination
+                    })
+                })
+                .collect();
+        }
+
+        let mut points = Vec::with_capacity(combinations.len());
+        let mut issues = Vec::new();
+        for combination in combinations {
+            let mut table = base.clone();
+            let mut params = Vec::with_capacity(axes.len());
+            let mut implementation = Implementation::Threaded;
+
+            for ((name, _), value) in axes.iter().zip(combination) {
+                if name == IMPL_KEY {
+                    implementation = value.as_str().unwrap_or_default().parse().unwrap_or(Implementation::Threaded);
+                } else {
+                    set_path(&mut table, name, value.clone());
+                }
+                params.push((name.clone(), display_value(value)));
+            }
+
+            let label = format_params(&params);
+            match Value::Table(table.clone()).try_into::<ExperimentConfig>() {
+                Ok(config) => {
+                    // A misspelt [sweep] key would silently leave the whole axis unused
+                    let unknown = unknown_field_issues(&table, &config).into_iter().filter(|issue| {
+                        self.strict || axes.iter().any(|(name, _)| issue.field == *name || issue.field.starts_with(&format!("{}.", name)))
+                    });
+                    for mut issue in config.validation_issues().into_iter().chain(unknown) {
+                        issue.message = format!("[{}] {}", label, issue.message);
+                        issues.push(issue);
+                    }
+                    points.push(SweepPoint { params, implementation, config });
+                }
+                Err(err) => issues.push(ConfigIssue {
+                    field: "sweep".to_string(),
+                    line: None,
+                    message: format!("[{}] {}", label, err.message()),
+                }),
+            }
+        }
+
+        if issues.is_empty() {
+            Ok(points)
+        } else {
+            Err(ConfigError::Invalid { path: self.base_config.clone(), issues })
+        }
+    }
+}
+
+/// Sets a dotted field path such as `actuators.motor.deadline_us`, creating tables as needed.
+fn set_path(table: &mut Table, path: &str, value: Value) {
+    let mut parts: Vec<&str> = path.split('.').collect();
+    let leaf = parts.pop().unwrap_or(path);
+    let mut current = table;
+    for part in parts {
+        let entry = current.entry(part.to_string()).or_insert_with(|| Value::Table(Table::new()));
+        if !entry.is_table() {
+            *entry = Value::Table(Table::new());
+        }
+        current = entry.as_table_mut().expect("entry was just made a table");
+    }
+    current.insert(leaf.to_string(), value);
+}
+
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIGS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../../configs");
+
+    fn sweep(body: &str) -> SweepConfig {
+        let mut sweep: SweepConfig = toml::from_str(body).unwrap();
+        sweep.base_config = format!("{}/experiment_baseline.toml", CONFIGS);
+        sweep
+    }
+
+    #[test]
+    fn grid_is_the_cartesian_product_with_the_first_key_slowest() {
+        let sweep = sweep("base_config = \"\"\n[sweep]\nsensor_period_ms = [5, 10]\ncpu_load_threads = [0, 1, 2]\nimpl = [\"sim\"]\n");
+        let points = sweep.expand().unwrap();
+        assert_eq!(sweep.point_count(), 6);
+        let grid: Vec<(u64, usize)> = points.iter().map(|point| (point.config.sensor_period_ms, point.config.cpu_load_threads)).collect();
+        assert_eq!(grid, vec![(5, 0), (5, 1), (5, 2), (10, 0), (10, 1), (10, 2)]);
+        assert!(points.iter().all(|point| point.implementation == Implementation::Sim));
+        assert_eq!(points[4].label(), "sensor_period_ms=10 cpu_load_threads=1 impl=sim");
+    }
+
+    #[test]
+    fn without_impl_every_point_runs_on_both_implementations() {
+        let points = sweep("base_config = \"\"\n[sweep]\nsensor_period_ms = [5, 10]\n").expand().unwrap();
+        let implementations: Vec<Implementation> = points.iter().map(|point| point.implementation).collect();
+        assert_eq!(implementations, [Implementation::ALL, Implementation::ALL].concat());
+    }
+
+    #[test]
+    fn sweep_values_override_overrides_which_override_the_base() {
+        let mut sweep = sweep(
+            "base_config = \"\"\n[overrides]\nduration_secs = 3\nsensor_period_ms = 7\n\
+             [overrides.actuators.motor]\ndeadline_us = 900\n[sweep]\nsensor_period_ms = [20]\nimpl = [\"sim\"]\n",
+        );
+        sweep.set_override("enable_logging", false);
+        let point = &sweep.expand().unwrap()[0];
+        assert_eq!(point.config.sensor_period_ms, 20);
+        assert_eq!(point.config.duration_secs, 3);
+        assert_eq!(point.config.actuators.motor.deadline_us, 900);
+        assert!(!point.config.enable_logging);
+        // Untouched settings keep the base config's values
+        let base = crate::config::load_config(&sweep.base_config).unwrap();
+        assert_eq!(point.config.actuators.gripper.deadline_us, base.actuators.gripper.deadline_us);
+    }
+
+    #[test]
+    fn misspelt_sweep_keys_are_rejected() {
+        let err = sweep("base_config = \"\"\n[sweep]\nsensor_period_msec = [5]\n").expand().unwrap_err();
+        match err {
+            ConfigError::Invalid { issues, .. } => {
+                assert!(!issues.is_empty() && issues.iter().all(|issue| issue.field == "sensor_period_msec"));
+            }
+            other => panic!("expected invalid settings, got {}", other),
+        }
+    }
+
+    #[test]
+    fn base_config_is_resolved_against_the_sweep_file() {
+        let sweep = load_sweep(&format!("{}/sweep_load.toml", CONFIGS)).unwrap();
+        assert_eq!(Path::new(&sweep.base_config), Path::new(CONFIGS).join("experiment_baseline.toml"));
+        assert_eq!(sweep.expand().unwrap().len(), sweep.point_count());
+    }
+}
@@ -29,6 +29,12 @@ impl MutexStrategy {
     }
 }
 
+impl Default for MutexStrategy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SyncStrategy for MutexStrategy {
     fn record(&self, result: CycleResult) {
         // Measure lock acquisition latency - time from before lock() until lock is acquired
@@ -90,6 +96,12 @@ impl RwLockStrategy {
     }
 }
 
+impl Default for RwLockStrategy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SyncStrategy for RwLockStrategy {
     fn record(&self, result: CycleResult) {
         // Measure lock acquisition latency - time from before write() until write lock is acquired
@@ -155,6 +167,12 @@ impl AtomicStrategy {
     }
 }
 
+impl Default for AtomicStrategy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl SyncStrategy for AtomicStrategy {
     fn record(&self, result: CycleResult) {
         // Use atomic for counter updates (lock-free) - these should have minimal contention
@@ -0,0 +1,149 @@
+use hdrhistogram::Histogram;
+use serde::{Deserialize, Serialize};
+use crate::metrics::{HISTOGRAM_MAX_NS, HISTOGRAM_SIGNIFICANT_FIGURES};
+use crate::ActuatorType;
+
+/// When one sensor sample reached each pipeline stage, in ns since the run's
+/// `start_time`. Every stage reads the same monotonic clock, so differences between
+/// fields are true cross-thread/task latencies. Zero means the stage wasn't reached.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct StageTimestamps {
+    pub generated_ns: u64,
+    pub filtered_ns: u64,
+    pub sent_ns: u64,
+    pub dispatched_ns: u64,
+    pub actuator_received_ns: u64,
+    pub control_computed_ns: u64,
+    pub feedback_sent_ns: u64,
+    pub feedback_consumed_ns: u64,
+}
+
+/// The hand-off at which a partial trace ended because the receiving queue was full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraceDrop {
+    Send,     // Sensor -> dispatcher; the sample reached no actuator
+    Dispatch, // Dispatcher -> this actuator
+    Feedback, // This actuator -> sensor; the sensor never heard back
+}
+
+/// End-to-end record joining a sensor sample (the trace id) with one actuator's
+/// handling of it and the feedback that came back to the sensor. A sample dropped
+/// on the way leaves a partial record: `dropped` says where, and the stamps of the
+/// stages it never reached are zero.
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceRecord {
+    pub trace_id: u64,
+    pub actuator: ActuatorType,
+    pub dropped: Option<TraceDrop>,
+    // Stage timestamps, flattened so the record is one CSV row
+    pub generated_ns: u64,
+    pub filtered_ns: u64,
+    pub sent_ns: u64,
+    pub dispatched_ns: u64,
+    pub actuator_received_ns: u64,
+    pub control_computed_ns: u64,
+    pub feedback_sent_ns: u64,
+    pub feedback_consumed_ns: u64,
+    pub sensor_to_actuation_ns: u64, // Sample generated -> control output computed
+    pub round_trip_ns: u64,          // Sample generated -> feedback consumed by the sensor
+}
+
+impl TraceRecord {
+    pub fn new(trace_id: u64, actuator: ActuatorType, stamps: StageTimestamps) -> Self {
+        Self {
+            trace_id,
+            actuator,
+            dropped: None,
+            generated_ns: stamps.generated_ns,
+            filtered_ns: stamps.filtered_ns,
+            sent_ns: stamps.sent_ns,
+            dispatched_ns: stamps.dispatched_ns,
+            actuator_received_ns: stamps.actuator_received_ns,
+            control_computed_ns: stamps.control_computed_ns,
+            feedback_sent_ns: stamps.feedback_sent_ns,
+            feedback_consumed_ns: stamps.feedback_consumed_ns,
+            sensor_to_actuation_ns: stamps.control_computed_ns.saturating_sub(stamps.generated_ns),
+            round_trip_ns: stamps.feedback_consumed_ns.saturating_sub(stamps.generated_ns),
+        }
+    }
+
+    /// The partial trace of a sample dropped at `at`, with the stamps it got so far.
+    pub fn dropped(trace_id: u64, actuator: ActuatorType, stamps: StageTimestamps, at: TraceDrop) -> Self {
+        Self { dropped: Some(at), ..Self::new(trace_id, actuator, stamps) }
+    }
+
+    /// When the hand-off that dropped the sample was attempted.
+    pub fn dropped_ns(&self) -> Option<u64> {
+        self.dropped.map(|at| match at {
+            TraceDrop::Send => self.sent_ns,
+            TraceDrop::Dispatch => self.dispatched_ns,
+            TraceDrop::Feedback => self.feedback_sent_ns,
+        })
+    }
+}
+
+/// p50/p99/max of the two end-to-end latencies for one actuator.
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceLatency {
+    pub actuator: ActuatorType,
+    pub traces: u64,
+    pub actuation_p50_ns: u64,
+    pub actuation_p99_ns: u64,
+    pub actuation_max_ns: u64,
+    pub round_trip_p50_ns: u64,
+    pub round_trip_p99_ns: u64,
+    pub round_trip_max_ns: u64,
+}
+
+/// Actuation and round-trip histograms per actuator, filled one trace at a time so
+/// the latencies cover every trace however many are retained.
+#[derive(Clone)]
+pub struct TraceHistograms {
+    histograms: Vec<(Histogram<u64>, Histogram<u64>)>, // (actuation, round trip), by actuator stage index
+}
+
+impl Default for TraceHistograms {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TraceHistograms {
+    pub fn new() -> Self {
+        let histogram = Histogram::<u64>::new_with_bounds(1, HISTOGRAM_MAX_NS, HISTOGRAM_SIGNIFICANT_FIGURES)
+            .expect("valid histogram bounds");
+        Self { histograms: vec![(histogram.clone(), histogram); ActuatorType::ALL.len()] }
+    }
+
+    pub fn record(&mut self, trace: &TraceRecord) {
+        // Partial traces only count towards the latencies they got as far as
+        let (actuation, round_trip) = &mut self.histograms[trace.actuator.stage_index() - 1];
+        if trace.control_computed_ns > 0 {
+            actuation.saturating_record(trace.sensor_to_actuation_ns);
+        }
+        if trace.feedback_consumed_ns > 0 {
+            round_trip.saturating_record(trace.round_trip_ns);
+        }
+    }
+
+    /// p50/p99/max per actuator, skipping actuators without traces.
+    pub fn latencies(&self) -> Vec<TraceLatency> {
+        ActuatorType::ALL
+            .into_iter()
+            .filter_map(|actuator| {
+                let (actuation, round_trip) = &self.histograms[actuator.stage_index() - 1];
+                (!actuation.is_empty()).then(|| TraceLatency {
+                    actuator,
+                    traces: actuation.len(),
+                    actuation_p50_ns: actuation.value_at_percentile(50.0),
+                    actuation_p99_ns: actuation.value_at_percentile(99.0),
+                    actuation_max_ns: actuation.max(),
+                    round_trip_p50_ns: round_trip.value_at_percentile(50.0),
+                    round_trip_p99_ns: round_trip.value_at_percentile(99.0),
+                    round_trip_max_ns: round_trip.max(),
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,173 @@
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use crate::config::{ExperimentConfig, WorkloadConfig, WorkloadDistribution};
+use crate::rng::SimRng;
+use crate::ActuatorType;
+
+/// Synthetic per-cycle computation executed by a pipeline stage.
+/// The mean cost is `processing_time_ns`, shaped by the `[workload]` section.
+pub struct Workload {
+    config: WorkloadConfig,
+    mean_ns: f64,
+    rng: SimRng,
+}
+
+impl Workload {
+    pub fn for_sensor(config: &ExperimentConfig) -> Self {
+        Self::new(config, config.workload.sensor_scale, 0)
+    }
+
+    pub fn for_actuator(config: &ExperimentConfig, actuator_type: ActuatorType) -> Self {
+        Self::new(config, config.workload.actuator_scale, actuator_type.stage_index() as u64)
+    }
+
+    fn new(config: &ExperimentConfig, scale: f64, stream: u64) -> Self {
+        Self {
+            config: config.workload.clone(),
+            mean_ns: config.processing_time_ns as f64 * scale,
+            // Each stage gets its own stream so adding a stage doesn't shift the others
+            rng: SimRng::new(config.workload.seed ^ stream.wrapping_mul(0xA24B_AED4_963E_E407)),
+        }
+    }
+
+    /// Draws the cost of the next cycle without executing it.
+    pub fn sample(&mut self) -> Duration {
+        if self.mean_ns <= 0.0 {
+            return Duration::ZERO;
+        }
+
+        let ns = match self.config.distribution {
+            WorkloadDistribution::Fixed => self.mean_ns,
+            WorkloadDistribution::Uniform => {
+                let spread = self.config.spread_ns as f64;
+                self.rng.uniform(self.mean_ns - spread, self.mean_ns + spread)
+            }
+            WorkloadDistribution::Normal => self.rng.normal(self.mean_ns, self.config.spread_ns as f64),
+            WorkloadDistribution::Exponential => self.rng.exponential(self.mean_ns),
+            WorkloadDistribution::Bimodal => {
+                if self.rng.chance(self.config.tail_probability) {
+                    self.config.tail_ns as f64
+                } else {
+                    self.mean_ns
+                }
+            }
+        };
+
+        Duration::from_nanos(ns.max(0.0) as u64)
+    }
+
+    /// Samples and busy-waits for the next cycle's cost, returning the target duration.
+    pub fn run(&mut self) -> Duration {
+        let cost = self.sample();
+        busy_wait(cost);
+        cost
+    }
+}
+
+/// Spins on the CPU (no yielding) until `duration` has elapsed.
+pub fn busy_wait(duration: Duration) {
+    if duration.is_zero() {
+        return;
+    }
+
+    let start = Instant::now();
+    let mut acc = 0u64;
+    while start.elapsed() < duration {
+        for i in 0..64u64 {
+            acc = black_box(acc.wrapping_mul(31).wrapping_add(i));
+        }
+    }
+    black_box(acc);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config;
+
+    fn experiment(processing_time_ns: u64, workload: WorkloadConfig) -> ExperimentConfig {
+        let mut config = parse_config(
+            "test.toml",
+            &format!(
+                "experiment_name = \"test\"\nduration_secs = 1\nsensor_period_ms = 10\ncpu_load_threads = 0\n\
+                 mode = \"baseline\"\nprocessing_time_ns = {}\nenable_logging = false\n",
+                processing_time_ns,
+            ),
+            false,
+        )
+        .unwrap();
+        config.workload = workload;
+        config
+    }
+
+    fn samples(workload: &mut Workload, n: usize) -> Vec<u64> {
+        (0..n).map(|_| workload.sample().as_nanos() as u64).collect()
+    }
+
+    #[test]
+    fn fixed_costs_are_scaled_per_stage() {
+        let config = experiment(10_000, WorkloadConfig { sensor_scale: 0.5, actuator_scale: 2.0, ..WorkloadConfig::default() });
+        assert_eq!(samples(&mut Workload::for_sensor(&config), 3), [5_000; 3]);
+        assert_eq!(samples(&mut Workload::for_actuator(&config, ActuatorType::Motor), 3), [20_000; 3]);
+
+        // No processing time means no work, whatever the distribution
+        let idle = experiment(0, WorkloadConfig { distribution: WorkloadDistribution::Exponential, ..WorkloadConfig::default() });
+        assert_eq!(Workload::for_sensor(&idle).run(), Duration::ZERO);
+    }
+
+    #[test]
+    fn distributions_shape_the_cost_around_the_mean() {
+        let uniform = experiment(10_000, WorkloadConfig {
+            distribution: WorkloadDistribution::Uniform,
+            spread_ns: 2_000,
+            ..WorkloadConfig::default()
+        });
+        let costs = samples(&mut Workload::for_sensor(&uniform), 1_000);
+        assert!(costs.iter().all(|&ns| (8_000..=12_000).contains(&ns)));
+        assert!(costs.iter().any(|&ns| ns < 9_000) && costs.iter().any(|&ns| ns > 11_000));
+
+        // A normal spread wider than the mean clamps at zero instead of going negative
+        let normal = experiment(1_000, WorkloadConfig {
+            distribution: WorkloadDistribution::Normal,
+            spread_ns: 5_000,
+            ..WorkloadConfig::default()
+        });
+        assert!(samples(&mut Workload::for_sensor(&normal), 1_000).contains(&0));
+
+        let bimodal = experiment(1_000, WorkloadConfig {
+            distribution: WorkloadDistribution::Bimodal,
+            tail_ns: 50_000,
+            tail_probability: 0.1,
+            ..WorkloadConfig::default()
+        });
+        let costs = samples(&mut Workload::for_sensor(&bimodal), 10_000);
+        assert!(costs.iter().all(|&ns| ns == 1_000 || ns == 50_000));
+        let tail_share = costs.iter().filter(|&&ns| ns == 50_000).count() as f64 / costs.len() as f64;
+        assert!((tail_share - 0.1).abs() < 0.02);
+    }
+
+    #[test]
+    fn each_stage_draws_its_own_seeded_stream() {
+        let config = experiment(10_000, WorkloadConfig {
+            distribution: WorkloadDistribution::Exponential,
+            seed: 99,
+            ..WorkloadConfig::default()
+        });
+        let sensor = samples(&mut Workload::for_sensor(&config), 16);
+        assert_eq!(sensor, samples(&mut Workload::for_sensor(&config), 16));
+        let gripper = samples(&mut Workload::for_actuator(&config, ActuatorType::Gripper), 16);
+        let motor = samples(&mut Workload::for_actuator(&config, ActuatorType::Motor), 16);
+        assert!(sensor != gripper && gripper != motor);
+
+        let reseeded = ExperimentConfig { workload: WorkloadConfig { seed: 100, ..config.workload.clone() }, ..config.clone() };
+        assert_ne!(sensor, samples(&mut Workload::for_sensor(&reseeded), 16));
+    }
+
+    #[test]
+    fn running_a_cycle_spins_for_its_cost() {
+        let config = experiment(2_000_000, WorkloadConfig::default());
+        let start = Instant::now();
+        assert_eq!(Workload::for_sensor(&config).run(), Duration::from_millis(2));
+        assert!(start.elapsed() >= Duration::from_millis(2));
+    }
+}
@@ -0,0 +1,167 @@
+//! Deterministic simulation of the sensor-actuator pipeline. Time is virtual: it jumps
+//! from one event to the next, and every execution cost is drawn from the seeded
+//! `[workload]` and `[simulation]` models instead of being measured. The same config
+//! and seed reproduce a run bit-for-bit, including which cycles miss their deadlines.
+//!
+//! Background load and shared-resource lock contention are not modeled.
+
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use common::runtime::{print_final_summary, print_start_banner, RuntimeError};
+use common::{
+    BenchmarkRecorder, CaptureFile, DashboardBuffer, ExperimentConfig, PlantLoop, SensorSource, SharedDiagnostics,
+    ShutdownReport, VirtualClock,
+};
+
+mod runtime;
+mod simulation;
+
+pub use runtime::SimRuntime;
+use simulation::Simulation;
+
+/// Component names, in pipeline order, for the shutdown report.
+const COMPONENTS: [&str; 5] = ["sensor", "dispatcher", "gripper", "motor", "stabilizer"];
+
+/// Runs one experiment in virtual time on the calling thread.
+pub fn run_experiment(config: ExperimentConfig) -> Result<Arc<BenchmarkRecorder>, RuntimeError> {
+    let (source, capture) = prepare(&config)?;
+    Ok(run(config, None, source, capture, &AtomicBool::new(false)))
+}
+
+/// The sensor's source and the run's capture file, both set up for the run's effective
+/// seed so the capture header reproduces the run.
+pub(crate) fn prepare(config: &ExperimentConfig) -> Result<(SensorSource, Option<CaptureFile>), RuntimeError> {
+    let mut config = config.clone();
+    config.workload.seed = config.simulation.effective_seed(&config.workload);
+    let source = SensorSource::from_config(&config).map_err(|e| RuntimeError::Failed(e.to_string()))?;
+    let capture = CaptureFile::create(&config, "sim").map_err(|e| RuntimeError::Failed(e.to_string()))?;
+    Ok((source, capture))
+}
+
+/// Simulates one run of `duration_secs` of virtual time, until `stop` is set or until a
+/// replay that doesn't loop runs out. Once the sensor stops, everything queued is
+/// drained as in the real runtimes. `capture`, when given, records the run.
+fn run(
+    mut config: ExperimentConfig,
+    dashboard: Option<DashboardBuffer>,
+    source: SensorSource,
+    capture: Option<CaptureFile>,
+    stop: &AtomicBool,
+) -> Arc<BenchmarkRecorder> {
+    // One seed drives every stage's workload stream and the release jitter
+    config.workload.seed = config.simulation.effective_seed(&config.workload);
+    if config.enable_logging {
+        print_start_banner("Simulated", &config);
+        let simulation = &config.simulation;
+        println!("[SYSTEM] Seed {}, release jitter {} ns, channel send {} ns, dispatch {} ns, control {} ns",
+                 config.workload.seed, simulation.release_jitter_ns, simulation.channel_cost_ns,
+                 simulation.dispatch_cost_ns, simulation.control_cost_ns);
+    }
+
+    let clock = Arc::new(VirtualClock::new());
+    let recorder = Arc::new(BenchmarkRecorder::for_run(clock.clone(), &config.recording));
+    if let Some(file) = capture {
+        recorder.start_capture(file);
+    }
+    let diagnostics = Arc::new(SharedDiagnostics::default());
+    let plants = PlantLoop::from_config(&config).map(Arc::new);
+
+    let drain = Simulation::new(
+        config.clone(),
+        clock,
+        Arc::clone(&recorder),
+        Arc::clone(&diagnostics),
+        dashboard,
+        source,
+        plants.clone(),
+        stop,
+    )
+    .run();
+
+    recorder.close();
+    recorder.set_shutdown_report(ShutdownReport {
+        elapsed: drain,
+        deadline: config.shutdown.deadline(),
+        stopped: COMPONENTS.iter().map(|name| name.to_string()).collect(),
+        ..ShutdownReport::default()
+    });
+    if let Some(plants) = &plants {
+        recorder.set_loop_performance(plants.performance());
+    }
+    recorder.set_status_transitions(diagnostics.transitions());
+
+    if config.enable_logging {
+        print_final_summary("Simulated", &recorder, &diagnostics, None);
+    }
+
+    recorder
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use common::config::{parse_config, ReplayPacing};
+    use common::{ActuatorType, TraceDrop};
+
+    use super::run_experiment;
+
+    const BASELINE: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/../../configs/experiment_baseline.toml"));
+
+    /// The run's results and traces, as saved to CSV.
+    fn saved_run(content: &str, name: &str) -> (Vec<u8>, Vec<u8>) {
+        let config = parse_config("experiment_baseline.toml", content, false).unwrap();
+        let recorder = run_experiment(config).unwrap();
+        let dir = std::env::temp_dir().join(format!("sim_determinism_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path: PathBuf = dir.join(format!("{}.csv", name));
+        recorder.save_to_csv(path.to_str().unwrap()).unwrap();
+        let saved = (std::fs::read(&path).unwrap(), std::fs::read(path.with_extension("traces.csv")).unwrap());
+        std::fs::remove_dir_all(&dir).ok();
+        saved
+    }
+
+    #[test]
+    fn same_seed_gives_byte_identical_results() {
+        // A closed-loop plant, so the run feeds control outputs back into the sensor
+        let content = format!(
+            "{}\n[plants.motor]\ndynamics = {{ model = \"first_order_lag\", gain = 1.0, time_constant_secs = 0.05 }}\n\
+             channel = \"position\"\ninitial = 1.0\n",
+            BASELINE
+                .replace("duration_secs = ", "duration_secs = 2 # ")
+                .replace("enable_logging = true", "enable_logging = false"),
+        );
+        let (results, traces) = saved_run(&content, "first");
+        let (again_results, again_traces) = saved_run(&content, "second");
+        assert!(results.len() > 1_000 && traces.len() > 1_000);
+        assert!(results == again_results, "results differ between runs with one seed");
+        assert!(traces == again_traces, "traces differ between runs with one seed");
+    }
+
+    #[test]
+    fn unpaced_replay_waits_for_room_instead_of_dropping() {
+        let dir = std::env::temp_dir().join(format!("sim_backpressure_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let trace = dir.join("trace.csv");
+        let rows: String = (0..300).map(|i| format!("{},{},0.0,25.0\n", i * 1_000_000, 50 + i % 7)).collect();
+        std::fs::write(&trace, format!("timestamp,force,position,temperature\n{}", rows)).unwrap();
+
+        let mut config = parse_config("experiment_baseline.toml", BASELINE, false).unwrap();
+        config.enable_logging = false;
+        config.duration_secs = 3_600; // The trace ends the run
+        config.workload.actuator_scale = 20.0; // Actuators far slower than the sensor
+        config.sensor.channel_capacity = 1;
+        config.replay.path = Some(trace.to_str().unwrap().to_string());
+        config.replay.pacing = ReplayPacing::AsFastAsPossible;
+        let recorder = run_experiment(config).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+
+        let results = recorder.get_results();
+        assert_eq!(results.iter().filter(|result| result.actuator.is_none()).count(), 300);
+        for actuator in ActuatorType::ALL {
+            assert_eq!(results.iter().filter(|result| result.actuator == Some(actuator)).count(), 300);
+        }
+        assert!(recorder.traces().iter().all(|trace| trace.dropped.is_none() || trace.dropped == Some(TraceDrop::Feedback)));
+    }
+}
@@ -0,0 +1,66 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use common::runtime::{Runtime, RuntimeError, RuntimeStatus};
+use common::{BenchmarkRecorder, DashboardBuffer, ExperimentConfig};
+
+/// [`Runtime`] over the deterministic simulation. A run finishes as fast as the host
+/// can simulate it, on a thread of its own so `start` still returns straight away.
+#[derive(Default)]
+pub struct SimRuntime {
+    dashboard: Option<DashboardBuffer>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<Arc<BenchmarkRecorder>>>,
+}
+
+impl SimRuntime {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Runtime for SimRuntime {
+    fn name(&self) -> &'static str {
+        "sim"
+    }
+
+    fn attach_dashboard(&mut self, dashboard: DashboardBuffer) {
+        self.dashboard = Some(dashboard);
+    }
+
+    fn start(&mut self, config: ExperimentConfig) -> Result<(), RuntimeError> {
+        if self.handle.is_some() {
+            return Err(RuntimeError::AlreadyRunning);
+        }
+        let (source, capture) = crate::prepare(&config)?;
+        // Fresh flag so a stop aimed at an earlier run can't end this one
+        self.stop = Arc::new(AtomicBool::new(false));
+        let stop = Arc::clone(&self.stop);
+        let dashboard = self.dashboard.clone();
+        self.handle = Some(thread::spawn(move || crate::run(config, dashboard, source, capture, &stop)));
+        Ok(())
+    }
+
+    fn stop(&self) {
+        if self.handle.is_some() {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn status(&self) -> RuntimeStatus {
+        match &self.handle {
+            None => RuntimeStatus::Idle,
+            Some(handle) if handle.is_finished() => RuntimeStatus::Finished,
+            Some(_) if self.stop.load(Ordering::Relaxed) => RuntimeStatus::Stopping,
+            Some(_) => RuntimeStatus::Running,
+        }
+    }
+
+    fn wait(&mut self) -> Result<Arc<BenchmarkRecorder>, RuntimeError> {
+        let handle = self.handle.take().ok_or(RuntimeError::NotStarted)?;
+        handle
+            .join()
+            .map_err(|_| RuntimeError::Failed("simulation thread panicked".to_string()))
+    }
+}
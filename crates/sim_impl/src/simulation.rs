@@ -0,0 +1,499 @@
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::{BinaryHeap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::metrics::CycleResult;
+use common::estop::EStopCoordinator;
+use common::pipeline::{feedback_trace, ActuatorStage, ForceFilter, SensorVerdict, ANOMALY_FORCE};
+use common::rng::SimRng;
+use common::{
+    ActuatorFeedback, ActuatorType, BenchmarkRecorder, CaptureRecord, Clock, DashboardBuffer, DashboardData,
+    ExperimentConfig, MetricsSnapshot, PlantLoop, SensorData, SharedDiagnostics, StageTimestamps, SensorSource,
+    SourceSample, TraceDrop, TraceRecord, VirtualClock, Workload,
+};
+
+/// Keeps the release jitter stream apart from the per-stage workload streams.
+const JITTER_STREAM: u64 = 0xD1B5_4A32_D192_ED03;
+
+/// A state change at one instant of virtual time.
+enum Event {
+    /// The sensor wakes for its next cycle, to take `sample`.
+    Release(SourceSample),
+    /// The sensor finished filtering and starts sending to the dispatcher.
+    SensorFiltered,
+    /// The sensor's send completed.
+    SensorHandedOff,
+    /// The dispatcher finished routing its current sample.
+    Dispatched,
+    /// An actuator finished computing its control output.
+    ControlComputed(ActuatorType),
+    /// An actuator's feedback send completed.
+    FeedbackSent(ActuatorType),
+    /// An e-stop broadcast reached an actuator.
+    EStop(ActuatorType),
+}
+
+/// Events at the same instant run in the order they were scheduled.
+struct Scheduled {
+    at_ns: u64,
+    seq: u64,
+    event: Event,
+}
+
+impl Ord for Scheduled {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        // Reversed so the BinaryHeap pops the earliest event first
+        (other.at_ns, other.seq).cmp(&(self.at_ns, self.seq))
+    }
+}
+
+impl PartialOrd for Scheduled {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Scheduled {
+    fn eq(&self, other: &Self) -> bool {
+        (self.at_ns, self.seq) == (other.at_ns, other.seq)
+    }
+}
+
+impl Eq for Scheduled {}
+
+/// The sensor's cycle between its release and its hand-off.
+struct SensorCycle {
+    data: SensorData,
+    raw_force: f64,
+    scheduled_ns: Option<u64>, // `None` when the source is unpaced
+    processing_time_ns: u64,
+}
+
+struct Sensor {
+    workload: Workload,
+    source: SensorSource,
+    jitter_rng: SimRng,
+    filter: ForceFilter,
+    cycle_id: u64,
+    current: Option<SensorCycle>,
+    waiting: bool, // Unpaced hand-off waiting for room in the dispatcher's queue
+    inbox: VecDeque<ActuatorFeedback>, // Feedback channel
+    stopped: bool,
+}
+
+struct Actuator {
+    actuator_type: ActuatorType,
+    stage: ActuatorStage,
+    channel_capacity: usize,
+    workload: Workload,
+    queue: VecDeque<SensorData>, // Dispatcher -> actuator channel
+    current: Option<SensorData>,
+    feedback: Option<ActuatorFeedback>, // Being sent back to the sensor
+}
+
+/// One run of the pipeline as a discrete-event simulation. Each component is a single
+/// server with its own core: it takes one message at a time from a bounded FIFO queue
+/// and is busy for the modeled cost of handling it. Sends never block; a full queue
+/// drops the message, as `try_send` does. An unpaced replay is the exception: the sensor
+/// and the dispatcher wait for room, as the real runtimes do, so no sample is dropped.
+pub(crate) struct Simulation<'a> {
+    config: ExperimentConfig,
+    clock: Arc<VirtualClock>,
+    recorder: Arc<BenchmarkRecorder>,
+    diagnostics: Arc<SharedDiagnostics>,
+    dashboard: Option<DashboardBuffer>,
+    plants: Option<Arc<PlantLoop>>, // Closed-loop plants, measured by the sensor
+    stop: &'a AtomicBool,
+    paced: bool,
+    events: BinaryHeap<Scheduled>,
+    next_seq: u64,
+    sensor: Sensor,
+    dispatcher_queue: VecDeque<SensorData>, // Sensor -> dispatcher channel
+    dispatching: Option<SensorData>,
+    dispatch_waiting: Option<usize>, // Actuator whose full queue the dispatcher waits on
+    actuators: Vec<Actuator>,
+    stopped_at_ns: u64, // When the sensor stopped sampling
+}
+
+impl<'a> Simulation<'a> {
+    /// `config.workload.seed` must already be the run's effective seed.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        config: ExperimentConfig,
+        clock: Arc<VirtualClock>,
+        recorder: Arc<BenchmarkRecorder>,
+        diagnostics: Arc<SharedDiagnostics>,
+        dashboard: Option<DashboardBuffer>,
+        source: SensorSource,
+        plants: Option<Arc<PlantLoop>>,
+        stop: &'a AtomicBool,
+    ) -> Self {
+        let source_paced = source.is_paced();
+        let sensor = Sensor {
+            workload: Workload::for_sensor(&config),
+            source,
+            jitter_rng: SimRng::new(config.workload.seed ^ JITTER_STREAM),
+            filter: ForceFilter::new(),
+            cycle_id: 0,
+            current: None,
+            waiting: false,
+            inbox: VecDeque::new(),
+            stopped: false,
+        };
+        // Broadcasts are scheduled as events, so it needs no waker
+        let estop = Arc::new(EStopCoordinator::new(config.estop.clone()));
+        let actuators = ActuatorType::ALL
+            .iter()
+            .map(|&actuator_type| {
+                let stage = ActuatorStage::new(
+                    &config,
+                    actuator_type,
+                    estop.handle(actuator_type),
+                    clock.clone(),
+                    Arc::clone(&recorder),
+                    Arc::clone(&diagnostics),
+                    plants.clone(),
+                )
+                .with_fixed_control_cost(config.simulation.control_cost_ns)
+                .with_dashboard(dashboard.clone());
+                Actuator {
+                    actuator_type,
+                    stage,
+                    channel_capacity: config.actuators.get(actuator_type).channel_capacity,
+                    workload: Workload::for_actuator(&config, actuator_type),
+                    queue: VecDeque::new(),
+                    current: None,
+                    feedback: None,
+                }
+            })
+            .collect();
+
+        Self {
+            paced: source_paced,
+            config,
+            clock,
+            recorder,
+            diagnostics,
+            dashboard,
+            plants,
+            stop,
+            events: BinaryHeap::new(),
+            next_seq: 0,
+            sensor,
+            dispatcher_queue: VecDeque::new(),
+            dispatching: None,
+            dispatch_waiting: None,
+            actuators,
+            stopped_at_ns: 0,
+        }
+    }
+
+    /// Runs until the sensor has stopped and every queued message has been handled.
+    /// Returns how long the drain took in virtual time.
+    pub(crate) fn run(mut self) -> Duration {
+        match self.sensor.source.next(0) {
+            Some(sample) => self.schedule(sample.release_ns.unwrap_or(0), Event::Release(sample)),
+            None => self.sensor.stopped = true,
+        }
+        while let Some(Scheduled { at_ns, event, .. }) = self.events.pop() {
+            self.clock.advance_to(at_ns);
+            match event {
+                Event::Release(sample) => self.release(sample),
+                Event::SensorFiltered => self.sensor_filtered(),
+                Event::SensorHandedOff => self.sensor_handed_off(),
+                Event::Dispatched => self.dispatched(),
+                Event::ControlComputed(actuator_type) => self.control_computed(actuator_type),
+                Event::FeedbackSent(actuator_type) => self.feedback_sent(actuator_type),
+                Event::EStop(actuator_type) => self.estop_received(actuator_type),
+            }
+        }
+        Duration::from_nanos(self.clock.now_ns() - self.stopped_at_ns)
+    }
+
+    fn schedule(&mut self, at_ns: u64, event: Event) {
+        self.events.push(Scheduled { at_ns, seq: self.next_seq, event });
+        self.next_seq += 1;
+    }
+
+    // ---------------- SENSOR ----------------
+
+    fn release(&mut self, sample: SourceSample) {
+        let timestamp_ns = self.clock.now_ns();
+        let cycle_id = self.sensor.cycle_id;
+
+        let mut reading = sample.reading;
+        if let Some(plants) = &self.plants {
+            plants.sample(cycle_id, timestamp_ns, &mut reading);
+        }
+        let filtered_force = self.sensor.filter.filter(reading.force);
+        if filtered_force.abs() > ANOMALY_FORCE {
+            self.diagnostics.record_anomaly();
+        }
+
+        let cost = self.sensor.workload.sample();
+        self.sensor.current = Some(SensorCycle {
+            data: SensorData {
+                id: cycle_id,
+                timestamp: timestamp_ns,
+                force: filtered_force,
+                position: reading.position,
+                temperature: reading.temperature,
+                stamps: StageTimestamps {
+                    generated_ns: timestamp_ns,
+                    ..StageTimestamps::default()
+                },
+            },
+            raw_force: reading.force,
+            scheduled_ns: sample.release_ns,
+            processing_time_ns: cost.as_nanos() as u64,
+        });
+        self.schedule(timestamp_ns + cost.as_nanos() as u64, Event::SensorFiltered);
+    }
+
+    fn sensor_filtered(&mut self) {
+        let now = self.clock.now_ns();
+        let cycle = self.sensor.current.as_mut().expect("sensor cycle in progress");
+        cycle.data.stamps.filtered_ns = now;
+        cycle.data.stamps.sent_ns = now;
+        self.schedule(now + self.config.simulation.channel_cost_ns, Event::SensorHandedOff);
+    }
+
+    fn sensor_handed_off(&mut self) {
+        let now = self.clock.now_ns();
+        let full = self.dispatcher_queue.len() >= self.config.sensor.channel_capacity;
+        if full && !self.paced {
+            self.sensor.waiting = true; // Resumed by `start_dispatch`
+            return;
+        }
+        let cycle = self.sensor.current.take().expect("sensor cycle in progress");
+        let data = cycle.data;
+
+        let transmission_success = self.dispatcher_queue.len() < self.config.sensor.channel_capacity;
+        if transmission_success {
+            self.dispatcher_queue.push_back(data);
+            self.start_dispatch();
+        }
+        self.recorder.capture(CaptureRecord::Sensor { data, raw_force: cycle.raw_force, sent: transmission_success });
+        if !transmission_success {
+            for actuator in ActuatorType::ALL {
+                self.recorder.record_trace(TraceRecord::dropped(data.id, actuator, data.stamps, TraceDrop::Send));
+            }
+        }
+
+        let processing_time_ns = cycle.processing_time_ns;
+        let transmission_time_ns = now - data.stamps.sent_ns;
+        let SensorVerdict { deadline_met, lateness_ns, .. } =
+            SensorVerdict::new(&self.config.sensor, processing_time_ns, transmission_time_ns, transmission_success);
+        let total_latency_ns = now - data.timestamp; // Release to hand-off to the dispatcher
+
+        if self.config.enable_logging && !deadline_met {
+            println!("[{:>8}] [DEADLINE] SENSOR: Missed - processing {:.2}μs, transmission {:.2}μs, sent: {} (cycle #{})",
+                     format!("{:.6}s", now as f64 / 1e9), processing_time_ns as f64 / 1000.0,
+                     transmission_time_ns as f64 / 1000.0, transmission_success, data.id);
+        }
+
+        self.recorder.record(CycleResult {
+            cycle_id: data.id,
+            mode: self.config.mode.clone(),
+            actuator: None,
+            total_latency_ns,
+            processing_time_ns,
+            lock_wait_ns: 0, // Lock contention is not modeled
+            deadline_met,
+            lateness_ns,
+            release_jitter_ns: cycle.scheduled_ns.map(|scheduled_ns| (data.timestamp - scheduled_ns) as i64),
+            control_ns: None,
+        });
+
+        if let Some(dash) = &self.dashboard {
+            dash.add(DashboardData {
+                timestamp: data.timestamp,
+                sensor_data: Some(data),
+                actuator_feedback: None,
+                metrics: Some(MetricsSnapshot {
+                    cycle_id: data.id,
+                    processing_time_ns,
+                    lock_wait_ns: 0,
+                    total_latency_ns,
+                    deadline_met,
+                    lateness_ns,
+                }),
+            });
+        }
+
+        while let Some(feedback) = self.sensor.inbox.pop_front() {
+            self.consume_feedback(feedback);
+        }
+
+        // Next release, delayed by the modeled wake-up latency and never before this cycle ends.
+        // An unpaced replay releases right away.
+        let duration_ns = Duration::from_secs(self.config.duration_secs).as_nanos() as u64;
+        let next = self.sensor.source.next(self.sensor.cycle_id + 1);
+        let run_over = next.is_none_or(|sample| sample.release_ns.unwrap_or(now) >= duration_ns);
+        if run_over || self.stop.load(Ordering::Relaxed) {
+            self.sensor.stopped = true;
+            self.stopped_at_ns = now;
+            return;
+        }
+        let sample = next.expect("checked above");
+        let at_ns = match sample.release_ns {
+            Some(release_ns) => {
+                let jitter_ns = self.sensor.jitter_rng.exponential(self.config.simulation.release_jitter_ns as f64) as u64;
+                (release_ns + jitter_ns).max(now)
+            }
+            None => now,
+        };
+        self.sensor.cycle_id += 1;
+        self.schedule(at_ns, Event::Release(sample));
+    }
+
+    /// Closes the sample's trace and adapts the filter, as the real sensors do.
+    fn consume_feedback(&mut self, feedback: ActuatorFeedback) {
+        self.recorder.record_trace(feedback_trace(&feedback, self.clock.now_ns()));
+        self.sensor.filter.adapt(feedback.error);
+    }
+
+    // ---------------- DISPATCHER ----------------
+
+    fn start_dispatch(&mut self) {
+        if self.dispatching.is_some() {
+            return;
+        }
+        if let Some(mut data) = self.dispatcher_queue.pop_front() {
+            let now = self.clock.now_ns();
+            data.stamps.dispatched_ns = now;
+            self.dispatching = Some(data);
+            self.schedule(now + self.config.simulation.dispatch_cost_ns, Event::Dispatched);
+            if self.sensor.waiting {
+                self.sensor.waiting = false;
+                self.sensor_handed_off();
+            }
+        }
+    }
+
+    fn dispatched(&mut self) {
+        self.dispatch_from(0);
+    }
+
+    /// Routes the sample being dispatched to the actuators from `first` on.
+    fn dispatch_from(&mut self, first: usize) {
+        let data = self.dispatching.expect("dispatch in progress");
+        for index in first..self.actuators.len() {
+            let actuator = &mut self.actuators[index];
+            let sent = actuator.queue.len() < actuator.channel_capacity;
+            if !sent && !self.paced {
+                self.dispatch_waiting = Some(index); // Resumed by `start_actuator`
+                return;
+            }
+            self.recorder.capture(CaptureRecord::Dispatched { actuator: actuator.actuator_type, data, sent });
+            if sent {
+                actuator.queue.push_back(data);
+                self.start_actuator(index);
+            } else {
+                self.recorder.record_trace(TraceRecord::dropped(data.id, actuator.actuator_type, data.stamps, TraceDrop::Dispatch));
+            }
+        }
+        self.dispatching = None;
+        self.start_dispatch();
+    }
+
+    // ---------------- ACTUATORS ----------------
+
+    fn start_actuator(&mut self, index: usize) {
+        let now = self.clock.now_ns();
+        let actuator = &mut self.actuators[index];
+        if actuator.current.is_some() || actuator.feedback.is_some() {
+            return;
+        }
+        if let Some(mut data) = actuator.queue.pop_front() {
+            data.stamps.actuator_received_ns = now;
+            actuator.current = Some(data);
+            // The workload model, plus the controller's update at its fixed cost
+            let cost_ns = actuator.workload.sample().as_nanos() as u64 + self.config.simulation.control_cost_ns;
+            let actuator_type = actuator.actuator_type;
+            self.schedule(now + cost_ns, Event::ControlComputed(actuator_type));
+            if self.dispatch_waiting == Some(index) {
+                self.dispatch_waiting = None;
+                self.dispatch_from(index);
+            }
+        }
+    }
+
+    fn control_computed(&mut self, actuator_type: ActuatorType) {
+        let now = self.clock.now_ns();
+        let index = self.actuator_index(actuator_type);
+        let actuator = &mut self.actuators[index];
+        let mut data = actuator.current.take().expect("actuator cycle in progress");
+
+        let step = actuator.stage.control(&data);
+        let verdict = actuator.stage.actuate(&mut data, &step);
+        let deadline_ns = actuator.stage.settings().deadline().as_nanos() as u64;
+        if self.config.enable_logging && !verdict.deadline_met {
+            println!("[{:>8}] [DEADLINE] {:?}: Processing missed - {:.2}ms > {:.1}ms (cycle #{}) ✗",
+                     format!("{:.6}s", now as f64 / 1e9), actuator_type, verdict.processing_time_ns as f64 / 1e6,
+                     deadline_ns as f64 / 1e6, data.id);
+        }
+        let result = actuator.stage.cycle_result(&data, &step, &verdict, 0); // Lock contention is not modeled
+        let total_latency_ns = result.total_latency_ns;
+        self.recorder.record(result);
+        let feedback = actuator.stage.feedback(&mut data, &step);
+        actuator.feedback = Some(feedback);
+
+        if let Some(dash) = &self.dashboard {
+            dash.add(DashboardData {
+                timestamp: now,
+                sensor_data: None,
+                actuator_feedback: Some((actuator_type, feedback)),
+                metrics: Some(MetricsSnapshot {
+                    cycle_id: data.id,
+                    processing_time_ns: verdict.processing_time_ns,
+                    lock_wait_ns: 0,
+                    total_latency_ns,
+                    deadline_met: verdict.deadline_met,
+                    lateness_ns: verdict.lateness_ns,
+                }),
+            });
+        }
+
+        if step.triggered.is_some() {
+            // The broadcast costs one channel send to reach each of the others
+            for other in ActuatorType::ALL.into_iter().filter(|&other| other != actuator_type) {
+                self.schedule(now + self.config.simulation.channel_cost_ns, Event::EStop(other));
+            }
+        }
+        self.schedule(now + self.config.simulation.channel_cost_ns, Event::FeedbackSent(actuator_type));
+    }
+
+    fn feedback_sent(&mut self, actuator_type: ActuatorType) {
+        let index = self.actuator_index(actuator_type);
+        let feedback = self.actuators[index].feedback.take().expect("feedback send in progress");
+        let sent = self.sensor.stopped || self.sensor.inbox.len() < self.config.sensor.feedback_channel_capacity;
+        self.actuators[index].stage.feedback_sent(feedback, sent);
+        if self.sensor.stopped {
+            // The sensor is draining, so it consumes feedback as soon as it arrives
+            self.consume_feedback(feedback);
+        } else if sent {
+            self.sensor.inbox.push_back(feedback);
+        }
+        self.actuators[index].stage.react_to_estop(); // A stop that arrived while this cycle was in progress
+        self.start_actuator(index);
+    }
+
+    /// An idle actuator reacts to the broadcast right away; a busy one when its cycle ends.
+    fn estop_received(&mut self, actuator_type: ActuatorType) {
+        let index = self.actuator_index(actuator_type);
+        let actuator = &mut self.actuators[index];
+        if actuator.current.is_none() && actuator.feedback.is_none() {
+            actuator.stage.react_to_estop();
+        }
+    }
+
+    fn actuator_index(&self, actuator_type: ActuatorType) -> usize {
+        self.actuators
+            .iter()
+            .position(|actuator| actuator.actuator_type == actuator_type)
+            .expect("every actuator type is simulated")
+    }
+}
@@ -1,41 +1,110 @@
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc;
+use std::sync::mpsc::{self, RecvTimeoutError};
 use std::sync::Arc;
-use std::thread;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use common::{
-    ActuatorFeedback, ActuatorType, BenchmarkRecorder,
-    ExperimentConfig, SensorData, SharedDiagnostics,
+    ActuatorFeedback, ActuatorType, BenchmarkRecorder, CaptureFile, CaptureRecord,
+    ExperimentConfig, LoadGenerator, MonotonicClock, SensorData, SharedDiagnostics,
+    DashboardBuffer, PlantLoop, SensorSource, SharedClock, TraceDrop, TraceRecord,
 };
 
+use common::estop::{EStopCoordinator, EStopHandle};
+use common::runtime::{print_final_summary, print_start_banner, RuntimeError};
+use common::shared_resource::{SharedResourceClient, SharedStateTable};
+
 mod actuator;
+mod runtime;
 mod sensor;
+mod shutdown;
+
+pub use runtime::ThreadedRuntime;
+use actuator::ActuatorInput;
+use shutdown::Components;
+
+/// Runs one experiment for `config.duration_secs` on the calling thread.
+pub fn run_experiment(config: ExperimentConfig) -> Result<Arc<BenchmarkRecorder>, RuntimeError> {
+    let source = SensorSource::from_config(&config).map_err(|e| RuntimeError::Failed(e.to_string()))?;
+    let capture = CaptureFile::create(&config, "threaded").map_err(|e| RuntimeError::Failed(e.to_string()))?;
+    let (stop_tx, stop_rx) = mpsc::channel();
+    Ok(run(config, None, source, capture, stop_tx, stop_rx))
+}
 
-pub fn run_experiment(config: ExperimentConfig) -> Arc<BenchmarkRecorder> {
+/// Orchestrates one run. It ends after `duration_secs`, when a replay that doesn't
+/// loop runs out, or early when `stop_rx` receives a message. `stop_tx` is its pair,
+/// which the sensor uses to end the run. `capture`, when given, records the run.
+///
+/// Shutdown drains in pipeline order: the sensor stops sampling and hangs up, the
+/// dispatcher forwards what is queued and hangs up, each actuator finishes its queue,
+/// and the sensor collects the last feedback. Components are joined against
+/// `shutdown.deadline_ms` and the recorder is sealed before it is returned.
+fn run(
+    config: ExperimentConfig,
+    dashboard: Option<DashboardBuffer>,
+    source: SensorSource,
+    capture: Option<CaptureFile>,
+    stop_tx: mpsc::Sender<()>,
+    stop_rx: mpsc::Receiver<()>,
+) -> Arc<BenchmarkRecorder> {
     if config.enable_logging {
-        println!("[SYSTEM] Real-Time Sensor-Actuator System Started (Threaded)");
-        println!("[SYSTEM] Configuration loaded: {} mode, {}ms sensor period", config.mode, config.sensor_period_ms);
-        println!("[SYSTEM] Components initialized: Sensor + 3 Actuators (Gripper, Motor, Stabilizer)");
-        println!("[SYSTEM] Starting experiment - Duration: {} seconds", config.duration_secs);
+        print_start_banner("Threaded", &config);
     }
 
-    let recorder = Arc::new(BenchmarkRecorder::new());
     let shutdown_flag = Arc::new(AtomicBool::new(false));
     let diagnostics = Arc::new(SharedDiagnostics::default());
+    let estop = Arc::new(EStopCoordinator::new(config.estop.clone()));
+
+    // Background CPU load runs for the whole experiment
+    let load_generator = LoadGenerator::start(config.cpu_load_threads, &config.cpu_load);
+    if config.enable_logging && load_generator.is_active() {
+        println!("[SYSTEM] Background load started: {} threads {:?} ({}% duty cycle)",
+                 config.cpu_load_threads, config.cpu_load.workers, config.cpu_load.duty_cycle_percent);
+    }
+
+    // Contention mode: sensor and all actuators share one lock-guarded state table
+    let shared_state = config
+        .is_contention_mode()
+        .then(|| Arc::new(SharedStateTable::new(&config.shared_resource)));
+    let shared_client = |stage: usize| shared_state.as_ref().map(|table| table.client(stage, config.workload.seed));
+
+    // Closed loop: actuator outputs drive the plants the sensor measures
+    let plants = PlantLoop::from_config(&config).map(Arc::new);
 
     // Sensor -> dispatcher
-    let (sensor_tx, dispatcher_rx) = mpsc::sync_channel::<SensorData>(100);
+    let (sensor_tx, dispatcher_rx) = mpsc::sync_channel::<SensorData>(config.sensor.channel_capacity);
 
     // Dispatcher -> actuators
-    let (gripper_tx, gripper_rx) = mpsc::sync_channel::<SensorData>(100);
-    let (motor_tx, motor_rx) = mpsc::sync_channel::<SensorData>(100);
-    let (stabilizer_tx, stabilizer_rx) = mpsc::sync_channel::<SensorData>(100);
+    let (gripper_tx, gripper_rx) = mpsc::sync_channel::<ActuatorInput>(config.actuators.gripper.channel_capacity);
+    let (motor_tx, motor_rx) = mpsc::sync_channel::<ActuatorInput>(config.actuators.motor.channel_capacity);
+    let (stabilizer_tx, stabilizer_rx) = mpsc::sync_channel::<ActuatorInput>(config.actuators.stabilizer.channel_capacity);
+    let (gripper_tx, motor_tx, stabilizer_tx) = (Arc::new(gripper_tx), Arc::new(motor_tx), Arc::new(stabilizer_tx));
+
+    // A stop wakes actuators idle between samples through their own channels. A full
+    // channel means the actuator is busy and reacts in its next cycle anyway. The wakers
+    // hold the senders weakly, so the channels still close once the dispatcher exits.
+    for tx in [&gripper_tx, &motor_tx, &stabilizer_tx] {
+        let tx = Arc::downgrade(tx);
+        estop.on_trigger(move || {
+            if let Some(tx) = tx.upgrade() {
+                let _ = tx.try_send(ActuatorInput::EStop);
+            }
+        });
+    }
 
     // Feedback channel
-    let (feedback_tx, feedback_rx) = mpsc::sync_channel::<ActuatorFeedback>(100);
+    let (feedback_tx, feedback_rx) = mpsc::sync_channel::<ActuatorFeedback>(config.sensor.feedback_channel_capacity);
 
-    let start_time = Instant::now();
+    // The recorder's clock starts here, so every stage measures from the same epoch
+    let recorder = Arc::new(BenchmarkRecorder::for_run(Arc::new(MonotonicClock::start()), &config.recording));
+    let clock = recorder.clock();
+    if let Some(file) = capture {
+        recorder.start_capture(file);
+    }
+    let mut components = Components::new();
+
+    // Unpaced replay waits for room in every queue instead of dropping, so each sample
+    // reaches every actuator
+    let paced = source.is_paced();
 
     // ---------------- SENSOR ----------------
     {
@@ -43,10 +112,14 @@ pub fn run_experiment(config: ExperimentConfig) -> Arc<BenchmarkRecorder> {
         let rec = Arc::clone(&recorder);
         let diag = Arc::clone(&diagnostics);
         let shutdown = Arc::clone(&shutdown_flag);
+        let clk = Arc::clone(&clock);
         let tx = sensor_tx;
         let feedback_recv = feedback_rx;
+        let dash = dashboard.clone();
+        let shared = shared_client(0);
+        let plants = plants.clone();
 
-        thread::spawn(move || {
+        components.spawn("sensor", move || {
             sensor::run_sensor_thread(
                 cfg,
                 tx,
@@ -54,37 +127,54 @@ pub fn run_experiment(config: ExperimentConfig) -> Arc<BenchmarkRecorder> {
                 rec,
                 diag,
                 shutdown,
-                start_time,
+                source,
+                stop_tx,
+                clk,
+                dash,
+                shared,
+                plants,
             );
         });
     }
 
     // ---------------- DISPATCHER ----------------
     {
-        let tx1 = gripper_tx.clone();
-        let tx2 = motor_tx.clone();
-        let tx3 = stabilizer_tx.clone();
+        // Moved, not cloned, so the actuators see the channels close once this exits
+        let tx1 = gripper_tx;
+        let tx2 = motor_tx;
+        let tx3 = stabilizer_tx;
 
         let dispatcher_config = config.clone();
-        thread::spawn(move || {
+        let clock = Arc::clone(&clock);
+        let recorder = Arc::clone(&recorder);
+        components.spawn("dispatcher", move || {
             let mut cycle_count = 0u64;
-            let dispatcher_start = Instant::now();
-            if dispatcher_config.enable_logging {
-                if config.enable_logging {
-                    println!("[{:>8}] [SYSTEM] Threaded dispatcher initialized - routing sensor data to 3 actuators",
-                             format!("{:.3}s", dispatcher_start.duration_since(start_time).as_secs_f64()));
-                }
+            let dispatcher_start = clock.elapsed();
+            if dispatcher_config.enable_logging && config.enable_logging {
+                println!("[{:>8}] [SYSTEM] Threaded dispatcher initialized - routing sensor data to 3 actuators",
+                         format!("{:.3}s", dispatcher_start.as_secs_f64()));
             }
 
-            while let Ok(data) = dispatcher_rx.recv() {
+            while let Ok(mut data) = dispatcher_rx.recv() {
                 cycle_count += 1;
-                let gripper_sent = tx1.try_send(data).is_ok();
-                let motor_sent = tx2.try_send(data).is_ok();
-                let stabilizer_sent = tx3.try_send(data).is_ok();
+                data.stamps.dispatched_ns = clock.now_ns();
+                let send = |tx: &mpsc::SyncSender<ActuatorInput>| {
+                    let input = ActuatorInput::Sample(data);
+                    if paced { tx.try_send(input).is_ok() } else { tx.send(input).is_ok() }
+                };
+                let gripper_sent = send(&tx1);
+                let motor_sent = send(&tx2);
+                let stabilizer_sent = send(&tx3);
+                for (actuator, sent) in ActuatorType::ALL.into_iter().zip([gripper_sent, motor_sent, stabilizer_sent]) {
+                    recorder.capture(CaptureRecord::Dispatched { actuator, data, sent });
+                    if !sent {
+                        recorder.record_trace(TraceRecord::dropped(data.id, actuator, data.stamps, TraceDrop::Dispatch));
+                    }
+                }
 
                 // Log dispatcher activity more frequently for demonstration
-                if dispatcher_config.enable_logging && cycle_count % 5 == 0 {
-                    let elapsed = dispatcher_start.duration_since(start_time).as_secs_f64();
+                if dispatcher_config.enable_logging && cycle_count.is_multiple_of(5) {
+                    let elapsed = clock.elapsed().as_secs_f64();
                     if config.enable_logging {
                         println!("[{:>8}] DISPATCHER: Routed cycle #{:<4} to actuators (G:{:?}, M:{:?}, S:{:?})",
                                 format!("{:.3}s", elapsed), cycle_count, gripper_sent, motor_sent, stabilizer_sent);
@@ -93,53 +183,63 @@ pub fn run_experiment(config: ExperimentConfig) -> Arc<BenchmarkRecorder> {
 
                 // Log transmission failures
                 if dispatcher_config.enable_logging && (!gripper_sent || !motor_sent || !stabilizer_sent) {
-                    let elapsed = dispatcher_start.duration_since(start_time).as_secs_f64();
+                    let elapsed = clock.elapsed().as_secs_f64();
                     if config.enable_logging {
                         println!("[{:>8}] [WARNING] DISPATCHER: Transmission failed - G:{:?}, M:{:?}, S:{:?} (cycle #{})",
                                 format!("{:.3}s", elapsed), gripper_sent, motor_sent, stabilizer_sent, cycle_count);
                     }
                 }
             }
-            if dispatcher_config.enable_logging {
-                if config.enable_logging {
-                    println!("[SYSTEM] Threaded dispatcher shutting down");
-                }
+            if dispatcher_config.enable_logging && config.enable_logging {
+                println!("[SYSTEM] Threaded dispatcher shutting down");
             }
         });
     }
 
     // ---------------- ACTUATORS ----------------
     spawn_actuator(
+        &mut components,
         ActuatorType::Gripper,
-        Duration::from_millis(1),
         config.clone(),
         gripper_rx,
         feedback_tx.clone(),
         Arc::clone(&recorder),
-        Arc::clone(&shutdown_flag),
-        start_time,
+        Arc::clone(&diagnostics),
+        estop.handle(ActuatorType::Gripper),
+        Arc::clone(&clock),
+        dashboard.clone(),
+        shared_client(ActuatorType::Gripper.stage_index()),
+        plants.clone(),
     );
 
     spawn_actuator(
+        &mut components,
         ActuatorType::Motor,
-        Duration::from_millis(2),
         config.clone(),
         motor_rx,
         feedback_tx.clone(),
         Arc::clone(&recorder),
-        Arc::clone(&shutdown_flag),
-        start_time,
+        Arc::clone(&diagnostics),
+        estop.handle(ActuatorType::Motor),
+        Arc::clone(&clock),
+        dashboard.clone(),
+        shared_client(ActuatorType::Motor.stage_index()),
+        plants.clone(),
     );
 
     spawn_actuator(
+        &mut components,
         ActuatorType::Stabilizer,
-        Duration::from_micros(1500),
         config.clone(),
         stabilizer_rx,
         feedback_tx,
         Arc::clone(&recorder),
-        Arc::clone(&shutdown_flag),
-        start_time,
+        Arc::clone(&diagnostics),
+        estop.handle(ActuatorType::Stabilizer),
+        Arc::clone(&clock),
+        dashboard.clone(),
+        shared_client(ActuatorType::Stabilizer.stage_index()),
+        plants.clone(),
     );
 
     // ---------------- RUN ----------------
@@ -147,20 +247,20 @@ pub fn run_experiment(config: ExperimentConfig) -> Arc<BenchmarkRecorder> {
         println!("[SYSTEM] Threaded experiment running for {} seconds...", config.duration_secs);
     }
 
-    // Performance monitoring thread
+    // Performance monitoring thread, woken early when its stop sender is dropped
     let recorder_clone = Arc::clone(&recorder);
-    let shutdown_flag_clone = Arc::clone(&shutdown_flag);
+    let (monitor_stop_tx, monitor_stop_rx) = mpsc::channel::<()>();
     let perf_config = config.clone();
-    let perf_monitor = thread::spawn(move || {
+    components.spawn("perf_monitor", move || {
         let mut last_cycle_count = 0u64;
-        while !shutdown_flag_clone.load(Ordering::Relaxed) {
-            thread::sleep(Duration::from_secs(2));
-            let results = recorder_clone.get_results();
-            let current_cycles = results.len() as u64;
+        let mut last_missed_count = 0u64;
+        while let Err(RecvTimeoutError::Timeout) = monitor_stop_rx.recv_timeout(Duration::from_secs(2)) {
+            let current_cycles = recorder_clone.recorded_results() as u64;
+            let current_missed = recorder_clone.missed_deadlines.load(Ordering::Relaxed) as u64;
 
             if perf_config.enable_logging && current_cycles > last_cycle_count {
                 let new_cycles = current_cycles - last_cycle_count;
-                let missed = results.iter().rev().take(new_cycles as usize).filter(|r| !r.deadline_met).count();
+                let missed = (current_missed - last_missed_count).min(new_cycles) as usize;
                 let throughput = new_cycles as f64 / 2.0; // cycles per second over 2 second window
 
                 let compliance_rate = if new_cycles > 0 {
@@ -172,61 +272,69 @@ pub fn run_experiment(config: ExperimentConfig) -> Arc<BenchmarkRecorder> {
                             throughput, compliance_rate, new_cycles - missed as u64, new_cycles);
                 }
                 last_cycle_count = current_cycles;
+                last_missed_count = current_missed;
             }
         }
     });
 
-    thread::sleep(Duration::from_secs(config.duration_secs));
-    shutdown_flag.store(true, Ordering::Relaxed);
+    let _ = stop_rx.recv_timeout(Duration::from_secs(config.duration_secs));
+    shutdown_flag.store(true, Ordering::Relaxed); // Stops the sensor; the rest drains behind it
+    drop(monitor_stop_tx);
 
     if config.enable_logging {
         println!("===========================================");
         println!("Threaded experiment completed - initiating shutdown");
     }
-    thread::sleep(Duration::from_millis(100)); // Brief pause for cleanup
 
-    // Wait for performance monitor to finish
-    let _ = perf_monitor.join();
+    let report = components.join_all(config.shutdown.deadline());
+    recorder.close();
+    if config.enable_logging && !report.is_clean() {
+        println!("[WARNING] Threaded shutdown incomplete: {}", report);
+    }
+    recorder.set_shutdown_report(report);
+    if let Some(plants) = &plants {
+        recorder.set_loop_performance(plants.performance());
+    }
+    recorder.set_status_transitions(diagnostics.transitions());
 
-    let results = recorder.get_results();
-    let total_cycles = results.len();
-    let missed_deadlines = results.iter().filter(|r| !r.deadline_met).count();
-    let deadline_compliance = if total_cycles > 0 {
-        (total_cycles - missed_deadlines) as f64 / total_cycles as f64 * 100.0
-    } else { 0.0 };
+    let load_profile = load_generator.stop();
+    recorder.set_load_profile(load_profile);
 
     if config.enable_logging {
-        println!("===========================================");
-        println!("FINAL THREADED SYSTEM RESULTS");
-        println!("===========================================");
-        println!("Total Cycles: {}", total_cycles);
-        println!("Deadline Compliance: {:.2}% ({} missed)", deadline_compliance, missed_deadlines);
-        println!("===========================================");
+        print_final_summary("Threaded", &recorder, &diagnostics, shared_state.as_ref().map(|table| table.stats()));
     }
 
     recorder
 }
 
+#[allow(clippy::too_many_arguments)]
 fn spawn_actuator(
+    components: &mut Components,
     actuator_type: ActuatorType,
-    deadline: Duration,
     config: ExperimentConfig,
-    receiver: mpsc::Receiver<SensorData>,
+    receiver: mpsc::Receiver<ActuatorInput>,
     feedback_tx: mpsc::SyncSender<ActuatorFeedback>,
     recorder: Arc<BenchmarkRecorder>,
-    shutdown: Arc<AtomicBool>,
-    start_time: Instant,
+    diagnostics: Arc<SharedDiagnostics>,
+    estop: EStopHandle,
+    clock: SharedClock,
+    dashboard: Option<DashboardBuffer>,
+    shared_state: Option<SharedResourceClient>,
+    plants: Option<Arc<PlantLoop>>,
 ) {
-    thread::spawn(move || {
+    components.spawn(format!("{:?}", actuator_type).to_lowercase(), move || {
         actuator::run_actuator_thread(
             actuator_type,
-            deadline,
             config,
             receiver,
             feedback_tx,
             recorder,
-            shutdown,
-            start_time,
+            diagnostics,
+            estop,
+            clock,
+            dashboard,
+            shared_state,
+            plants,
         );
     });
 }
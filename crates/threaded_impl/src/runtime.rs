@@ -0,0 +1,75 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use common::runtime::{Runtime, RuntimeError, RuntimeStatus};
+use common::{BenchmarkRecorder, CaptureFile, DashboardBuffer, ExperimentConfig, SensorSource};
+
+/// [`Runtime`] over OS threads. Each run is orchestrated from its own thread.
+#[derive(Default)]
+pub struct ThreadedRuntime {
+    dashboard: Option<DashboardBuffer>,
+    stop_tx: Option<mpsc::Sender<()>>,
+    stop_requested: Arc<AtomicBool>,
+    handle: Option<JoinHandle<Arc<BenchmarkRecorder>>>,
+}
+
+impl ThreadedRuntime {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Runtime for ThreadedRuntime {
+    fn name(&self) -> &'static str {
+        "threaded"
+    }
+
+    fn attach_dashboard(&mut self, dashboard: DashboardBuffer) {
+        self.dashboard = Some(dashboard);
+    }
+
+    fn start(&mut self, config: ExperimentConfig) -> Result<(), RuntimeError> {
+        if self.handle.is_some() {
+            return Err(RuntimeError::AlreadyRunning);
+        }
+        let source = SensorSource::from_config(&config).map_err(|e| RuntimeError::Failed(e.to_string()))?;
+        let capture = CaptureFile::create(&config, self.name()).map_err(|e| RuntimeError::Failed(e.to_string()))?;
+        let (stop_tx, stop_rx) = mpsc::channel();
+        let dashboard = self.dashboard.clone();
+        self.stop_tx = Some(stop_tx.clone());
+        self.stop_requested.store(false, Ordering::Relaxed);
+        self.handle = Some(thread::spawn(move || crate::run(config, dashboard, source, capture, stop_tx, stop_rx)));
+        Ok(())
+    }
+
+    fn stop(&self) {
+        if let Some(stop_tx) = &self.stop_tx {
+            self.stop_requested.store(true, Ordering::Relaxed);
+            let _ = stop_tx.send(());
+        }
+    }
+
+    fn status(&self) -> RuntimeStatus {
+        match &self.handle {
+            None => RuntimeStatus::Idle,
+            Some(handle) if handle.is_finished() => RuntimeStatus::Finished,
+            Some(_) if self.stop_requested.load(Ordering::Relaxed) => RuntimeStatus::Stopping,
+            Some(_) => RuntimeStatus::Running,
+        }
+    }
+
+    fn wait(&mut self) -> Result<Arc<BenchmarkRecorder>, RuntimeError> {
+        let handle = self.handle.take().ok_or(RuntimeError::NotStarted)?;
+        let result = handle
+            .join()
+            .map_err(|_| RuntimeError::Failed("experiment thread panicked".to_string()));
+        self.stop_tx = None;
+        let recorder = result?;
+        match recorder.shutdown_report().and_then(|report| report.panicked.first().cloned()) {
+            Some(component) => Err(RuntimeError::Panicked { component, message: "thread panicked".to_string() }),
+            None => Ok(recorder),
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use common::ShutdownReport;
+
+/// Every pipeline thread of one run, kept in pipeline order so shutdown can join
+/// them with a deadline and say which ones didn't stop.
+pub(crate) struct Components {
+    handles: Vec<(String, JoinHandle<()>)>,
+    done_tx: Sender<usize>,
+    done_rx: Receiver<usize>,
+}
+
+/// Tells the supervisor which component exited, including by panic.
+struct ExitSignal(Sender<usize>, usize);
+
+impl Drop for ExitSignal {
+    fn drop(&mut self) {
+        let _ = self.0.send(self.1);
+    }
+}
+
+impl Components {
+    pub(crate) fn new() -> Self {
+        let (done_tx, done_rx) = mpsc::channel();
+        Self { handles: Vec::new(), done_tx, done_rx }
+    }
+
+    pub(crate) fn spawn(&mut self, name: impl Into<String>, body: impl FnOnce() + Send + 'static) {
+        let name = name.into();
+        let exit_signal = ExitSignal(self.done_tx.clone(), self.handles.len());
+        let handle = thread::Builder::new()
+            .name(name.clone())
+            .spawn(move || {
+                let _exit_signal = exit_signal;
+                body();
+            })
+            .expect("failed to spawn pipeline thread");
+        self.handles.push((name, handle));
+    }
+
+    /// Joins the components in pipeline order, sensor first, each one draining what the
+    /// one before it left queued; all of them share `deadline`. Components still
+    /// running at the deadline are left detached and reported.
+    pub(crate) fn join_all(self, deadline: Duration) -> ShutdownReport {
+        let Components { handles, done_rx, .. } = self;
+        let start = Instant::now();
+        let mut exited = vec![false; handles.len()];
+        let mut report = ShutdownReport {
+            deadline,
+            ..ShutdownReport::default()
+        };
+        for (index, (name, handle)) in handles.into_iter().enumerate() {
+            // Exits signalled out of order are remembered; past the deadline this only
+            // picks up signals that are already queued
+            while !exited[index] {
+                match done_rx.recv_timeout(deadline.saturating_sub(start.elapsed())) {
+                    Ok(done) => exited[done] = true,
+                    Err(_) => break,
+                }
+            }
+            // An exited component's thread is past its body, so joining it can't block
+            if !exited[index] {
+                report.failed_to_stop.push(name);
+            } else if handle.join().is_err() {
+                report.panicked.push(name);
+            } else {
+                report.stopped.push(name);
+            }
+        }
+        report.elapsed = start.elapsed();
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use super::Components;
+
+    #[test]
+    fn join_all_reports_stragglers_and_panics_in_pipeline_order() {
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        let mut components = Components::new();
+        components.spawn("sensor", || std::thread::sleep(Duration::from_millis(20)));
+        components.spawn("dispatcher", || panic!("dispatcher failed"));
+        components.spawn("motor", move || {
+            let _ = release_rx.recv();
+        });
+        components.spawn("perf_monitor", || {});
+
+        let report = components.join_all(Duration::from_millis(100));
+        assert_eq!(report.stopped, ["sensor", "perf_monitor"]);
+        assert_eq!(report.panicked, ["dispatcher"]);
+        assert_eq!(report.failed_to_stop, ["motor"]);
+        assert!(report.elapsed >= Duration::from_millis(100));
+        drop(release_tx);
+    }
+}
@@ -0,0 +1,323 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use common::config::{load_config_with_overlays, load_table, ConfigError, ExperimentConfig};
+use common::pid::tuning::{self, TuningMethod, TuningOptions, TuningRule};
+use common::estop::EStopSummary;
+use common::metrics::{JitterSummary, RunStats, Stage};
+use common::sweep::{load_sweep, Implementation};
+use common::{ActuatorType, BenchmarkRecorder};
+
+use crate::RunOptions;
+
+pub const DEFAULT_CONFIG: &str = "configs/experiment_baseline.toml";
+
+pub type CommandResult = Result<(), Box<dyn Error>>;
+
+impl RunOptions {
+    pub fn config_path(&self) -> &str {
+        self.config.as_deref().unwrap_or(DEFAULT_CONFIG)
+    }
+
+    /// `--log` / `--quiet` if either was given.
+    fn logging_override(&self) -> Option<bool> {
+        if self.log {
+            Some(true)
+        } else if self.quiet {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// Loads the selected config and applies the duration and logging overrides.
+    /// `default_logging` is the command's own choice when neither flag was given;
+    /// `None` keeps the config file's setting.
+    pub fn load_config(&self, default_logging: Option<bool>) -> Result<ExperimentConfig, ConfigError> {
+        let mut config = load_config_with_overlays(self.config_path(), &self.overlay, self.strict)?;
+        if let Some(duration) = self.duration {
+            config.duration_secs = duration;
+        }
+        if let Some(enable_logging) = self.logging_override().or(default_logging) {
+            config.enable_logging = enable_logging;
+        }
+        Ok(config)
+    }
+
+    /// Where `file_name` should be written, creating the output directory if needed.
+    pub fn output_path(&self, file_name: &str) -> Result<Option<PathBuf>, std::io::Error> {
+        match &self.output_dir {
+            Some(dir) => {
+                fs::create_dir_all(dir)?;
+                Ok(Some(dir.join(file_name)))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+fn run_implementation(implementation: Implementation, config: ExperimentConfig) -> Result<Arc<BenchmarkRecorder>, Box<dyn Error>> {
+    Ok(benchmark_runner::create_runtime(implementation)?.run(config)?)
+}
+
+fn save_results(recorder: &BenchmarkRecorder, implementation: Implementation, config: &ExperimentConfig, options: &RunOptions) -> CommandResult {
+    if let Some(path) = options.output_path(&format!("{}_results.csv", implementation))? {
+        recorder.save_to_csv(&path.to_string_lossy())?;
+    }
+    if let Some(path) = options.output_path(&format!("{}_trace.json", implementation))? {
+        recorder.save_chrome_trace(&path.to_string_lossy(), &format!("{}_impl", implementation), config)?;
+    }
+    Ok(())
+}
+
+fn print_jitter(jitter: &JitterSummary) {
+    println!("- Release jitter: mean {:.2}μs, p99 {:.2}μs, max {:.2}μs, drift {:+.1} ns/cycle",
+             jitter.mean_us, jitter.p99_us, jitter.max_us, jitter.drift_ns_per_cycle);
+}
+
+pub fn run(implementation: Implementation, options: &RunOptions) -> CommandResult {
+    let title = match implementation {
+        Implementation::Threaded => "Threaded",
+        Implementation::Async => "Async",
+        Implementation::Sim => "Simulated",
+    };
+    println!("\n=== Running {} Implementation ===", title);
+
+    let config = options.load_config(None)?;
+    println!("Configuration: {} mode, {}ms sensor period, {} seconds duration",
+             config.mode, config.sensor_period_ms, config.duration_secs);
+
+    let recorder = run_implementation(implementation, config.clone())?;
+    let stats = recorder.stats();
+    display_results(&stats);
+    benchmark_runner::analysis::print_trace_latencies(&recorder.trace_latencies());
+    benchmark_runner::analysis::print_loop_performance(&recorder.loop_performance());
+    benchmark_runner::analysis::print_controller_costs(stats.histograms(), &config.actuators);
+    benchmark_runner::analysis::print_status_transitions(&recorder.status_transitions());
+    benchmark_runner::analysis::print_estop_reactions(&recorder.estop_reactions(), &config.estop);
+    if let Some(report) = recorder.shutdown_report() {
+        println!("\nShutdown: {}", report);
+    }
+    if let Some(capture) = recorder.capture_summary() {
+        println!("Capture: {}", capture);
+    }
+    save_results(&recorder, implementation, &config, options)
+}
+
+pub fn compare(options: &RunOptions) -> CommandResult {
+    println!("\n=== Running Benchmark Comparison (Async vs Threaded) ===");
+
+    let config = options.load_config(Some(false))?; // Logging off by default for valid benchmarks
+
+    println!("Benchmark Configuration:");
+    println!("- Config: {}", options.config_path());
+    println!("- Duration: {} seconds", config.duration_secs);
+    println!("- Sensor period: {} ms", config.sensor_period_ms);
+    if !config.enable_logging {
+        println!("- Logging disabled for methodological validity");
+    }
+
+    println!("\n--- Running THREADED Implementation ---");
+    let threaded_start = std::time::Instant::now();
+    let threaded_recorder = run_implementation(Implementation::Threaded, config.clone())?;
+    let threaded_duration = threaded_start.elapsed();
+
+    let threaded_summary = threaded_recorder.summary(Stage::All);
+    let threaded_compliance = threaded_summary.compliance_percent;
+
+    println!("Threaded Results:");
+    println!("- Execution time: {:.2}s", threaded_duration.as_secs_f64());
+    println!("- Total cycles: {}", threaded_summary.cycles);
+    println!("- Deadline compliance: {:.1}% ({} missed)", threaded_compliance, threaded_summary.missed_deadlines);
+    let threaded_jitter = threaded_recorder.jitter();
+    print_jitter(&threaded_jitter);
+
+    println!("\n--- Running ASYNC Implementation ---");
+    let async_start = std::time::Instant::now();
+    let async_recorder = run_implementation(Implementation::Async, config.clone())?;
+    let async_duration = async_start.elapsed();
+
+    let async_summary = async_recorder.summary(Stage::All);
+    let async_compliance = async_summary.compliance_percent;
+
+    println!("Async Results:");
+    println!("- Execution time: {:.2}s", async_duration.as_secs_f64());
+    println!("- Total cycles: {}", async_summary.cycles);
+    println!("- Deadline compliance: {:.1}% ({} missed)", async_compliance, async_summary.missed_deadlines);
+    let async_jitter = async_recorder.jitter();
+    print_jitter(&async_jitter);
+
+    println!("\n=== Benchmark Comparison Summary ===");
+    let time_diff = if async_duration > threaded_duration {
+        format!("Async slower by {:.2}s", (async_duration - threaded_duration).as_secs_f64())
+    } else {
+        format!("Threaded slower by {:.2}s", (threaded_duration - async_duration).as_secs_f64())
+    };
+
+    println!("- Performance: {}", time_diff);
+    println!("- Threaded compliance: {:.1}%", threaded_compliance);
+    println!("- Async compliance: {:.1}%", async_compliance);
+    println!("- Threaded jitter p99: {:.2}μs", threaded_jitter.p99_us);
+    println!("- Async jitter p99: {:.2}μs", async_jitter.p99_us);
+    for (name, recorder) in [("Threaded", &threaded_recorder), ("Async", &async_recorder)] {
+        let estop = EStopSummary::from_reactions(&recorder.estop_reactions());
+        if estop.reactions > 0 {
+            println!("- {} e-stop reaction p99: {:.2}μs, max {:.2}μs ({} of {} over {}μs)", name, estop.p99_us,
+                     estop.max_us, estop.missed_deadlines, estop.reactions, config.estop.reaction_deadline_us);
+        }
+    }
+
+    save_results(&threaded_recorder, Implementation::Threaded, &config, options)?;
+    save_results(&async_recorder, Implementation::Async, &config, options)
+}
+
+pub fn dashboard(implementation: Implementation, options: &RunOptions) -> CommandResult {
+    // Runs are started and stopped from the window and are not saved
+    if options.output_dir.is_some() {
+        return Err("--output-dir is not supported by dashboard, whose runs are not saved".into());
+    }
+    let config = options.load_config(None)?;
+
+    println!("\n=== Launching Real-Time Dashboard ===");
+    println!("Note: Close the GUI window to return");
+
+    // Blocks until the GUI window is closed
+    visualiser::run_dashboard(config, implementation.to_string())
+        .map_err(|e| format!("Dashboard failed: {}", e))?;
+    println!("Dashboard closed successfully.");
+    Ok(())
+}
+
+pub fn bench(target: &str, options: &RunOptions) -> CommandResult {
+    println!("\n=== Running Statistical Benchmark Mode (Criterion) ===");
+    println!("This will run detailed statistical analysis using Criterion.");
+    println!("Each benchmark takes about 30 seconds to complete.\n");
+
+    let config = options.load_config(Some(false))?;
+    benchmark_runner::print_header(options.config_path(), &config, true);
+
+    let report_dir = options.output_dir.as_deref();
+    if let Some(dir) = report_dir {
+        fs::create_dir_all(dir)?;
+    }
+    benchmark_runner::run_criterion(&config, target, report_dir);
+
+    let report_dir = report_dir.map_or_else(
+        || benchmark_runner::CRITERION_REPORT_DIR.to_string(),
+        |dir| dir.display().to_string(),
+    );
+    println!("\nHTML reports available in: {}/", report_dir);
+    if target == "sync" {
+        println!("- sync_strategies/report/index.html");
+    } else {
+        println!("- async_experiment/report/index.html");
+        println!("- threaded_experiment/report/index.html");
+    }
+    println!("\nOpen the HTML files in your browser to view detailed");
+    println!("statistical analysis, performance distributions, and charts.");
+    Ok(())
+}
+
+pub fn sweep(sweep_file: &str, options: &RunOptions) -> CommandResult {
+    let mut sweep = load_sweep(sweep_file)?;
+    if let Some(config) = &options.config {
+        sweep.base_config = config.clone();
+    }
+    for overlay in &options.overlay {
+        sweep.add_overlay(&load_table(overlay)?);
+    }
+    sweep.strict |= options.strict;
+    if let Some(duration) = options.duration {
+        sweep.set_override("duration_secs", duration as i64);
+    }
+    if let Some(enable_logging) = options.logging_override() {
+        sweep.set_override("enable_logging", enable_logging);
+    }
+    let file_name = Path::new(&sweep.output)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| sweep.output.clone());
+    if let Some(path) = options.output_path(&file_name)? {
+        sweep.output = path.to_string_lossy().into_owned();
+    }
+
+    benchmark_runner::sweep::run_sweep(&sweep, sweep_file)
+}
+
+/// Experiment settings for `tune`, from the command line.
+pub struct TuneArgs {
+    pub method: TuningMethod,
+    pub rule: TuningRule,
+    pub amplitude: f64,
+    pub hysteresis: f64,
+    pub closed_loop_secs: Option<f64>,
+}
+
+pub fn tune(actuator: &str, args: TuneArgs, write: Option<&str>, options: &RunOptions) -> CommandResult {
+    let config = options.load_config(None)?;
+    let tuning = TuningOptions {
+        method: args.method,
+        rule: args.rule,
+        amplitude: args.amplitude,
+        hysteresis: args.hysteresis,
+        closed_loop_secs: args.closed_loop_secs,
+        ..TuningOptions::for_config(&config)
+    };
+    let actuators: Vec<ActuatorType> = ActuatorType::ALL
+        .into_iter()
+        .filter(|&actuator_type| match actuator {
+            "all" => config.plants.get(actuator_type).is_some(),
+            name => format!("{:?}", actuator_type).to_lowercase() == name,
+        })
+        .collect();
+    if actuators.is_empty() {
+        return Err("no actuator has a plant under [plants] to tune against".into());
+    }
+
+    println!("\n=== Tuning against plant models ({}ms sample period) ===", config.sensor_period_ms);
+    let mut snippets = Vec::new();
+    for actuator_type in actuators {
+        let tuned = tuning::tune(&config, actuator_type, &tuning)?;
+        println!("{:?}: {} -> kp {:.4}, ki {:.4}, kd {:.4}", actuator_type, tuned.identification, tuned.kp, tuned.ki, tuned.kd);
+        if let Some(warning) = tuned.warning {
+            println!("[WARNING] {:?}: {}", actuator_type, warning);
+        }
+        snippets.push(tuned.to_toml()?);
+    }
+    let snippet = snippets.join("\n");
+    println!("\n{}", snippet);
+
+    if let Some(file_name) = write {
+        let path = options.output_path(file_name)?.unwrap_or_else(|| PathBuf::from(file_name));
+        fs::write(&path, &snippet)?;
+        println!("Gains written to {} (load them with --overlay {})", path.display(), path.display());
+    }
+    Ok(())
+}
+
+pub fn display_results(stats: &RunStats) {
+    let all = stats.totals(Stage::All);
+    if all.cycles == 0 {
+        println!("No results to display.");
+        return;
+    }
+
+    println!("\n=== Experiment Results ===");
+    println!("Total Cycles: {}", all.cycles);
+    println!("Deadline Compliance: {:.2}% ({} missed)", all.compliance_percent(), all.missed_deadlines);
+
+    // Show actuator breakdown
+    let actuators: Vec<_> = ActuatorType::ALL
+        .into_iter()
+        .map(|actuator| (actuator, stats.totals(Stage::Actuator(actuator))))
+        .filter(|(_, totals)| totals.cycles > 0)
+        .collect();
+    if !actuators.is_empty() {
+        println!("Actuator Performance:");
+        for (actuator, totals) in actuators {
+            println!("- {:?}: {:.1}% compliance ({} cycles)", actuator, totals.compliance_percent(), totals.cycles);
+        }
+    }
+}
@@ -1,224 +1,143 @@
+mod commands;
 mod menu;
 
-use common::config::load_config;
-
-fn main() {
-    println!("===========================================");
-    println!("Welcome to Real-Time Sensor-Actuator System");
-    println!("===========================================");
-
-    loop {
-        menu::show_menu();
-
-        match menu::get_user_choice() {
-            Ok(1) => run_threaded_demo(),
-            Ok(2) => run_async_demo(),
-            Ok(3) => run_benchmark_comparison(),
-            Ok(4) => run_realtime_dashboard(),
-            Ok(5) => run_criterion_benchmark(),
-            Ok(6) => {
-                println!("Goodbye!");
-                break;
-            }
-            _ => println!("Invalid choice. Please select 1-6."),
-        }
-    }
-}
-
-fn run_threaded_demo() {
-    println!("\n=== Running Threaded Implementation Demo ===");
+use std::path::PathBuf;
+use std::process::ExitCode;
 
-    let mut config = load_config("configs/experiment_baseline.toml")
-        .expect("Failed to load config");
-    config.enable_logging = true; // Enable logging for demo
+use clap::{Args, Parser, Subcommand};
+use common::pid::tuning::{TuningMethod, TuningRule};
+use common::sweep::Implementation;
 
-    println!("Configuration: {} mode, {}ms sensor period, {} seconds duration",
-             config.mode, config.sensor_period_ms, config.duration_secs);
+#[derive(Parser)]
+#[command(name = "real-time-sensor-actuator", version)]
+#[command(about = "Real-time sensor-actuator system: demos, benchmarks and parameter sweeps")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
 
-    let recorder = threaded_impl::run_experiment(config);
-    display_results(&recorder.get_results());
-
-    menu::wait_for_enter();
+    #[command(flatten)]
+    options: RunOptions,
 }
 
-fn run_async_demo() {
-    println!("\n=== Running Async Implementation Demo ===");
-
-    let mut config = load_config("configs/experiment_baseline.toml")
-        .expect("Failed to load config");
-    config.enable_logging = true; // Enable logging for demo
-
-    println!("Configuration: {} mode, {}ms sensor period, {} seconds duration",
-             config.mode, config.sensor_period_ms, config.duration_secs);
-
-    let rt = tokio::runtime::Runtime::new().unwrap();
-    let recorder = rt.block_on(async_impl::run_experiment(config));
-    display_results(&recorder.get_results());
-
-    menu::wait_for_enter();
+#[derive(Subcommand)]
+enum Command {
+    /// Run one implementation and print its results
+    Run {
+        /// Implementation to run (threaded, async or sim)
+        implementation: Implementation,
+    },
+    /// Run both implementations back to back and compare them
+    Compare,
+    /// Launch the real-time dashboard
+    Dashboard {
+        /// Implementation the dashboard drives (threaded, async or sim)
+        #[arg(default_value = "async")]
+        implementation: Implementation,
+    },
+    /// Run the Criterion statistical benchmarks
+    Bench {
+        /// What to benchmark: threaded, async, both or sync
+        #[arg(default_value = "both", value_parser = ["threaded", "async", "both", "sync"])]
+        target: String,
+    },
+    /// Run a parameter sweep described by a sweep file
+    Sweep {
+        /// Sweep description with a [sweep] table
+        #[arg(default_value = "configs/sweep_load.toml")]
+        sweep_file: String,
+    },
+    /// Auto-tune PID gains against the configured plant models and print them as TOML
+    Tune {
+        /// Actuator to tune: gripper, motor, stabilizer or all (every actuator with a plant)
+        #[arg(default_value = "all", value_parser = ["gripper", "motor", "stabilizer", "all"])]
+        actuator: String,
+
+        /// Identification experiment: relay or step
+        #[arg(long, default_value = "relay")]
+        method: TuningMethod,
+
+        /// Tuning rule: ziegler_nichols, cohen_coon or simc (the last two need --method step)
+        #[arg(long, default_value = "ziegler_nichols")]
+        rule: TuningRule,
+
+        /// Relay output amplitude, or the step size
+        #[arg(long, default_value_t = 1.0)]
+        amplitude: f64,
+
+        /// Relay hysteresis around the plant's rest output
+        #[arg(long, default_value_t = 0.0)]
+        hysteresis: f64,
+
+        /// SIMC closed-loop time constant in seconds (defaults to the identified dead time)
+        #[arg(long)]
+        closed_loop_secs: Option<f64>,
+
+        /// Also write the gains to this file (under --output-dir when given), for --overlay
+        #[arg(long)]
+        write: Option<String>,
+    },
+    /// Start the numbered interactive menu
+    Interactive,
 }
 
-fn run_benchmark_comparison() {
-    println!("\n=== Running Benchmark Comparison (Async vs Threaded) ===");
-
-    let config_path = "configs/experiment_baseline.toml";
-    let mut config = load_config(config_path).expect("Failed to load config");
-    config.enable_logging = false; // Disable logging for valid benchmarks
-
-    println!("Benchmark Configuration:");
-    println!("- Config: {}", config_path);
-    println!("- Duration: {} seconds", config.duration_secs);
-    println!("- Sensor period: {} ms", config.sensor_period_ms);
-    println!("- Logging disabled for methodological validity");
-
-    println!("\n--- Running THREADED Implementation ---");
-    let threaded_start = std::time::Instant::now();
-    let threaded_recorder = threaded_impl::run_experiment(config.clone());
-    let threaded_duration = threaded_start.elapsed();
-
-    let threaded_results = threaded_recorder.get_results();
-    let threaded_missed = threaded_results.iter().filter(|r| !r.deadline_met).count();
-    let threaded_compliance = if !threaded_results.is_empty() {
-        (threaded_results.len() - threaded_missed) as f64 / threaded_results.len() as f64 * 100.0
-    } else { 0.0 };
-
-    println!("Threaded Results:");
-    println!("- Execution time: {:.2}s", threaded_duration.as_secs_f64());
-    println!("- Total cycles: {}", threaded_results.len());
-    println!("- Deadline compliance: {:.1}% ({} missed)", threaded_compliance, threaded_missed);
-
-    println!("\n--- Running ASYNC Implementation ---");
-    let async_start = std::time::Instant::now();
-    let rt = tokio::runtime::Runtime::new().unwrap();
-    let async_recorder = rt.block_on(async_impl::run_experiment(config.clone()));
-    let async_duration = async_start.elapsed();
-
-    let async_results = async_recorder.get_results();
-    let async_missed = async_results.iter().filter(|r| !r.deadline_met).count();
-    let async_compliance = if !async_results.is_empty() {
-        (async_results.len() - async_missed) as f64 / async_results.len() as f64 * 100.0
-    } else { 0.0 };
-
-    println!("Async Results:");
-    println!("- Execution time: {:.2}s", async_duration.as_secs_f64());
-    println!("- Total cycles: {}", async_results.len());
-    println!("- Deadline compliance: {:.1}% ({} missed)", async_compliance, async_missed);
-
-    println!("\n=== Benchmark Comparison Summary ===");
-    let time_diff = if async_duration > threaded_duration {
-        format!("Async slower by {:.2}s", (async_duration - threaded_duration).as_secs_f64())
-    } else {
-        format!("Threaded slower by {:.2}s", (threaded_duration - async_duration).as_secs_f64())
-    };
-
-    println!("- Performance: {}", time_diff);
-    println!("- Threaded compliance: {:.1}%", threaded_compliance);
-    println!("- Async compliance: {:.1}%", async_compliance);
-
-    menu::wait_for_enter();
+/// Flags shared by every subcommand.
+#[derive(Args, Clone)]
+pub struct RunOptions {
+    /// Experiment config to load (for sweeps: replaces the sweep's base_config)
+    #[arg(short, long, global = true)]
+    pub config: Option<String>,
+
+    /// Partial config merged over the experiment config, such as gains written by tune (repeatable)
+    #[arg(long, global = true)]
+    pub overlay: Vec<String>,
+
+    /// Reject config keys that no setting reads, such as misspelt field names
+    #[arg(long, global = true)]
+    pub strict: bool,
+
+    /// Override the experiment duration in seconds
+    #[arg(short, long, global = true, value_parser = clap::value_parser!(u64).range(1..))]
+    pub duration: Option<u64>,
+
+    /// Directory results are written to: CSV tables and Chrome traces, or Criterion reports for bench
+    /// (run and compare only save when this is set)
+    #[arg(short, long, global = true)]
+    pub output_dir: Option<PathBuf>,
+
+    /// Force per-cycle logging on
+    #[arg(long, global = true, conflicts_with = "quiet")]
+    pub log: bool,
+
+    /// Force per-cycle logging off
+    #[arg(short, long, global = true)]
+    pub quiet: bool,
 }
 
-fn run_realtime_dashboard() {
-    println!("\n=== Launching Real-Time Dashboard ===");
-    println!("Note: Close the GUI window to return to menu");
-
-    let config_path = "configs/experiment_baseline.toml";
-    let mode = "async";
-
-    // Launch the visualiser - this will block until the GUI window is closed
-    // We can't directly call the visualiser's main function from here,
-    // so we'll execute it as a subprocess
-    match std::process::Command::new("cargo")
-        .args(&["run", "--release", "-p", "visualiser", "--", config_path, mode])
-        .status() {
-        Ok(status) if status.success() => {
-            println!("Dashboard closed successfully.");
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let options = &cli.options;
+
+    let result = match cli.command {
+        Command::Run { implementation } => commands::run(implementation, options),
+        Command::Compare => commands::compare(options),
+        Command::Dashboard { implementation } => commands::dashboard(implementation, options),
+        Command::Bench { target } => commands::bench(&target, options),
+        Command::Sweep { sweep_file } => commands::sweep(&sweep_file, options),
+        Command::Tune { actuator, method, rule, amplitude, hysteresis, closed_loop_secs, write } => {
+            let tuning = commands::TuneArgs { method, rule, amplitude, hysteresis, closed_loop_secs };
+            commands::tune(&actuator, tuning, write.as_deref(), options)
         }
-        Ok(status) => {
-            println!("Dashboard exited with status: {}", status);
+        Command::Interactive => {
+            menu::run_interactive(options);
+            Ok(())
         }
-        Err(e) => {
-            println!("Failed to launch dashboard: {}", e);
-            println!("Make sure you have the visualiser binary available.");
-        }
-    }
-
-    menu::wait_for_enter();
-}
+    };
 
-fn run_criterion_benchmark() {
-    println!("\n=== Running Statistical Benchmark Mode (Criterion) ===");
-    println!("This will run detailed statistical analysis using Criterion.");
-    println!("Each benchmark takes about 30 seconds to complete.");
-    println!("Results will be saved to target/criterion/ directory.\n");
-
-    let config_path = "configs/experiment_baseline.toml";
-    let mode = "both";
-
-    println!("Configuration:");
-    println!("- Config: {}", config_path);
-    println!("- Mode: {}", mode);
-    println!("- Using Criterion for statistical analysis");
-    println!("\nStarting benchmarks... (this may take a few minutes)\n");
-
-    match std::process::Command::new("cargo")
-        .args(&["run", "-p", "benchmark_runner", "--", config_path, mode, "--criterion"])
-        .status() {
-        Ok(status) if status.success() => {
-            println!("\n========================================");
-            println!("Statistical benchmarks completed successfully!");
-            println!("========================================");
-            println!("HTML reports available in: target/criterion/");
-            println!("- async_experiment/report/index.html");
-            println!("- threaded_experiment/report/index.html");
-            println!("\nOpen the HTML files in your browser to view detailed");
-            println!("statistical analysis, performance distributions, and charts.");
-        }
-        Ok(status) => {
-            println!("Benchmark runner exited with status: {}", status);
-        }
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
         Err(e) => {
-            println!("Failed to launch benchmark runner: {}", e);
-            println!("Make sure you're running from the project root directory.");
-        }
-    }
-
-    menu::wait_for_enter();
-}
-
-fn display_results(results: &[common::metrics::CycleResult]) {
-    if results.is_empty() {
-        println!("No results to display.");
-        return;
-    }
-
-    let total_cycles = results.len();
-    let missed_deadlines = results.iter().filter(|r| !r.deadline_met).count();
-    let deadline_compliance = (total_cycles - missed_deadlines) as f64 / total_cycles as f64 * 100.0;
-
-    println!("\n=== Experiment Results ===");
-    println!("Total Cycles: {}", total_cycles);
-    println!("Deadline Compliance: {:.2}% ({} missed)", deadline_compliance, missed_deadlines);
-
-    // Show actuator breakdown
-    let mut actuator_stats = std::collections::HashMap::new();
-    for result in results {
-        if let Some(actuator) = &result.actuator {
-            let entry = actuator_stats.entry(*actuator).or_insert((0, 0));
-            entry.0 += 1; // total cycles
-            if !result.deadline_met {
-                entry.1 += 1; // missed deadlines
-            }
-        }
-    }
-
-    if !actuator_stats.is_empty() {
-        println!("Actuator Performance:");
-        for (actuator, (total, missed)) in actuator_stats {
-            let compliance = if total > 0 {
-                (total - missed) as f64 / total as f64 * 100.0
-            } else { 0.0 };
-            println!("- {:?}: {:.1}% compliance ({} cycles)", actuator, compliance, total);
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
         }
     }
 }
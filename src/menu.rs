@@ -1,6 +1,47 @@
 use std::io::{self, Write};
 
-pub fn show_menu() {
+use common::sweep::Implementation;
+
+use crate::commands;
+use crate::RunOptions;
+
+/// Numbered menu loop, kept for interactive demos. Every action uses the same
+/// config/duration/output/logging flags as the scriptable subcommands.
+pub fn run_interactive(options: &RunOptions) {
+    println!("===========================================");
+    println!("Welcome to Real-Time Sensor-Actuator System");
+    println!("===========================================");
+
+    // Demos show per-cycle logging unless --quiet was given
+    let demo_options = RunOptions { log: !options.quiet, ..options.clone() };
+
+    loop {
+        show_menu();
+
+        let result = match get_user_choice() {
+            Ok(1) => commands::run(Implementation::Threaded, &demo_options),
+            Ok(2) => commands::run(Implementation::Async, &demo_options),
+            Ok(3) => commands::compare(options),
+            Ok(4) => commands::dashboard(Implementation::Async, options),
+            Ok(5) => commands::bench("both", options),
+            Ok(6) => {
+                println!("Goodbye!");
+                break;
+            }
+            _ => {
+                println!("Invalid choice. Please select 1-6.");
+                continue;
+            }
+        };
+
+        if let Err(e) = result {
+            println!("Error: {}", e);
+        }
+        wait_for_enter();
+    }
+}
+
+fn show_menu() {
     println!("\n===========================================");
     println!("Real-Time Sensor-Actuator System");
     println!("===========================================");
@@ -16,13 +57,13 @@ pub fn show_menu() {
     io::stdout().flush().unwrap();
 }
 
-pub fn get_user_choice() -> Result<u32, std::num::ParseIntError> {
+fn get_user_choice() -> Result<u32, std::num::ParseIntError> {
     let mut input = String::new();
     io::stdin().read_line(&mut input).unwrap();
     input.trim().parse::<u32>()
 }
 
-pub fn wait_for_enter() {
+fn wait_for_enter() {
     println!("\nPress Enter to return to menu...");
     let mut input = String::new();
     io::stdin().read_line(&mut input).unwrap();